@@ -1,4 +1,8 @@
-use crate::rpc::{caller::Sender, message::Response, Caller, HandleError, RpcWriter, WriteError};
+use crate::rpc::{
+    caller::{PendingCall, Sender},
+    message::Response,
+    Caller, HandleError, Metrics, RpcWriter, WriteError,
+};
 
 #[macro_export]
 macro_rules! dict {
@@ -14,7 +18,8 @@ macro_rules! dict {
 pub struct Client<W: RpcWriter> {
     writer: W,
     msgid_counter: u32,
-    callbacks: Vec<(u32, Sender)>,
+    callbacks: Vec<PendingCall>,
+    metrics: Metrics,
 }
 
 impl<W: RpcWriter> Client<W> {
@@ -23,9 +28,15 @@ impl<W: RpcWriter> Client<W> {
             writer,
             callbacks: Vec::new(),
             msgid_counter: 0,
+            metrics: Metrics::default(),
         }
     }
 
+    /// Metrics of the calls made through this client.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
     pub fn handle_response(
         &mut self,
         response: Response<rmpv::Value, rmpv::Value>,
@@ -33,11 +44,22 @@ impl<W: RpcWriter> Client<W> {
         let caller = self
             .callbacks
             .iter()
-            .position(|(msgid, _)| *msgid == response.msgid)
+            .position(|pending| pending.msgid == response.msgid)
             .map(|index| self.callbacks.swap_remove(index));
 
         match caller {
-            Some((_, recv)) => recv.send(response).map_err(HandleError::CallerDropped),
+            Some(pending) => {
+                self.metrics.record(
+                    &pending.method,
+                    pending.started.elapsed(),
+                    response.error.is_some(),
+                );
+
+                pending
+                    .sender
+                    .send(response)
+                    .map_err(HandleError::CallerDropped)
+            }
             None => Err(HandleError::CallerMissing(response)),
         }
     }
@@ -58,8 +80,8 @@ impl<W: futures::AsyncWrite + Unpin> Caller for &mut Client<W> {
         msgid
     }
 
-    fn store_handler(&mut self, msgid: u32, sender: Sender) {
-        self.callbacks.push((msgid, sender));
+    fn store_handler(&mut self, msgid: u32, method: &str, sender: Sender) {
+        self.callbacks.push(PendingCall::new(msgid, method, sender));
     }
 
     async fn write<S: AsRef<str>, V: serde::Serialize>(
@@ -0,0 +1,53 @@
+//! Conversions to `rmpv::Value`, for writing custom rpc calls and handlers
+//! without building the values by hand.
+
+use rmpv::Value;
+use serde::Serialize;
+
+/// Conversion to `rmpv::Value`.
+///
+/// Implemented for all the serializable types (e.g. tuples, `Option<T>`,
+/// `HashMap<String, T>`, `&str` and nested `Vec`s).
+pub trait IntoValue {
+    /// Converts `self` to a value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type's `Serialize` implementation fails.
+    fn into_value(self) -> Value;
+}
+
+impl<T: Serialize> IntoValue for T {
+    fn into_value(self) -> Value {
+        rmpv::ext::to_value(self).expect("failed to serialize value")
+    }
+}
+
+/// Builder for map values (e.g. the `opts` parameters of the api functions).
+#[derive(Debug, Default)]
+pub struct MapBuilder(Vec<(Value, Value)>);
+
+impl MapBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value` with `key`.
+    pub fn insert(mut self, key: &str, value: impl IntoValue) -> Self {
+        self.0.push((Value::from(key), value.into_value()));
+        self
+    }
+
+    /// Inserts `value` with `key`, if the value is set. Nvim treats missing
+    /// keys as "use the default", while nil is usually an error.
+    pub fn insert_some(self, key: &str, value: Option<impl IntoValue>) -> Self {
+        match value {
+            Some(value) => self.insert(key, value),
+            None => self,
+        }
+    }
+
+    pub fn build(self) -> Value {
+        Value::Map(self.0)
+    }
+}
@@ -1,10 +1,13 @@
 pub mod client;
 mod gen;
+pub mod into_value;
+pub mod lines;
 pub mod rpc;
 pub mod types;
 
 pub use client::Client;
 pub use gen::Neovim as NeovimApi;
+pub use into_value::IntoValue;
 pub use types::decode_redraw_params;
 
 pub use async_trait;
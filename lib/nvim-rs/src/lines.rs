@@ -0,0 +1,85 @@
+//! Fetching large ranges of buffer lines in chunks.
+
+use futures::{stream, Stream};
+
+use crate::{
+    rpc::{CallError, Caller, WriteError},
+    types::Buffer,
+    NeovimApi,
+};
+
+/// Error from fetching the lines.
+#[derive(Debug)]
+pub enum LinesError {
+    Write(WriteError),
+    Call(CallError),
+}
+
+impl From<WriteError> for LinesError {
+    fn from(err: WriteError) -> Self {
+        Self::Write(err)
+    }
+}
+
+impl From<CallError> for LinesError {
+    fn from(err: CallError) -> Self {
+        Self::Call(err)
+    }
+}
+
+/// Fetches the lines `start..end` (zero based, end exclusive) of `buffer`,
+/// `chunk_size` lines at a time. Negative `end` counts from the end of the
+/// buffer, as in `nvim_buf_get_lines` (i.e. -1 is the end of the buffer).
+/// `chunk_size` is at least one.
+///
+/// The next chunk is requested only when the previous one has been consumed.
+/// This way huge ranges don't end up as one huge response that stalls the
+/// main loop.
+///
+/// To cancel, drop the stream: no more chunks are requested, and the
+/// response to a chunk already in flight is discarded. To cancel from
+/// elsewhere, wrap the stream with e.g. `futures::stream::Abortable`.
+pub fn buf_get_lines_chunked<C>(
+    caller: C,
+    buffer: Buffer,
+    start: i64,
+    end: i64,
+    chunk_size: i64,
+) -> impl Stream<Item = Result<Vec<String>, LinesError>>
+where
+    C: Caller + Copy,
+{
+    let chunk_size = chunk_size.max(1);
+
+    stream::try_unfold(
+        (buffer, start, end),
+        move |(buffer, start, end)| async move {
+            let end = if end < 0 {
+                let count = caller.nvim_buf_line_count(&buffer).await?.await?;
+                count + 1 + end
+            } else {
+                end
+            };
+
+            if start >= end {
+                return Ok(None);
+            }
+
+            let chunk_end = (start + chunk_size).min(end);
+            let lines = caller
+                .nvim_buf_get_lines(&buffer, start, chunk_end, false)
+                .await?
+                .await?;
+
+            // A short chunk means the buffer ended (e.g. it shrunk in the
+            // meantime).
+            let next = if (lines.len() as i64) < chunk_end - start {
+                end
+            } else {
+                chunk_end
+            };
+
+            Ok::<_, LinesError>(Some((lines, (buffer, next, end))))
+        },
+    )
+}
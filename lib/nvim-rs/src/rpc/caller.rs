@@ -1,5 +1,6 @@
 use std::any::{Any, TypeId};
 use std::pin::Pin;
+use std::time::Instant;
 
 use futures::{channel::oneshot, prelude::*};
 use serde::Deserialize;
@@ -27,6 +28,26 @@ pub type Response = message::Response<rmpv::Value, rmpv::Value>;
 
 pub type Sender = oneshot::Sender<Response>;
 
+/// Call that is waiting for its response.
+#[derive(Debug)]
+pub struct PendingCall {
+    pub msgid: u32,
+    pub method: String,
+    pub started: Instant,
+    pub sender: Sender,
+}
+
+impl PendingCall {
+    pub fn new(msgid: u32, method: &str, sender: Sender) -> Self {
+        Self {
+            msgid,
+            method: method.to_string(),
+            started: Instant::now(),
+            sender,
+        }
+    }
+}
+
 impl From<oneshot::Canceled> for CallError {
     fn from(_: oneshot::Canceled) -> Self {
         Self::Cancelled
@@ -57,7 +78,7 @@ where
         let msgid = self.next_msgid();
 
         let (sender, receiver) = oneshot::channel();
-        self.store_handler(msgid, sender);
+        self.store_handler(msgid, method.as_ref(), sender);
 
         self.write(msgid, method, &args).await?;
 
@@ -93,5 +114,5 @@ where
 
     fn next_msgid(&mut self) -> u32;
 
-    fn store_handler(&mut self, msgid: u32, sender: Sender);
+    fn store_handler(&mut self, msgid: u32, method: &str, sender: Sender);
 }
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 
 use serde::ser::SerializeTuple;
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 #[serde(untagged)]
 pub enum Message {
     Request(Request<'static, rmpv::Value>),
@@ -10,7 +10,7 @@ pub enum Message {
     Notification(Notification<'static, rmpv::Value>),
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 #[serde(bound = "P: serde::Deserialize<'de>")]
 pub struct Request<'a, P> {
     r#type: u32,
@@ -47,7 +47,7 @@ impl<'a, P> Request<'a, P> {
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 #[serde(bound = "R: serde::Deserialize<'de>, E: serde::Deserialize<'de>")]
 pub struct Response<R, E> {
     r#type: u32,
@@ -86,7 +86,7 @@ where
     }
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 #[serde(bound = "P: serde::Deserialize<'de>")]
 pub struct Notification<'a, P> {
     r#type: u32,
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How many latency samples to keep per method.
+const MAX_SAMPLES: usize = 1000;
+
+/// Per method call metrics.
+#[derive(Debug, Default, Clone)]
+pub struct MethodMetrics {
+    /// Number of completed calls.
+    pub calls: u64,
+    /// Number of calls that resulted into an error response.
+    pub errors: u64,
+    /// Latency samples of the most recent calls. Used as a ring buffer once
+    /// `MAX_SAMPLES` is reached.
+    samples: Vec<Duration>,
+    /// Next index to write to in `samples`, once its full.
+    next: usize,
+}
+
+impl MethodMetrics {
+    fn record(&mut self, latency: Duration, failed: bool) {
+        self.calls += 1;
+        if failed {
+            self.errors += 1;
+        }
+
+        if self.samples.len() < MAX_SAMPLES {
+            self.samples.push(latency);
+        } else {
+            self.samples[self.next] = latency;
+            self.next = (self.next + 1) % MAX_SAMPLES;
+        }
+    }
+
+    /// Latency percentile (`p` between 0.0 and 1.0) over the recent calls.
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut samples = self.samples.clone();
+        samples.sort_unstable();
+
+        let index = ((samples.len() - 1) as f64 * p.clamp(0.0, 1.0)).round() as usize;
+        samples.get(index).copied()
+    }
+}
+
+/// Call metrics, grouped by the rpc method.
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    methods: HashMap<String, MethodMetrics>,
+}
+
+impl Metrics {
+    /// Record a completed call.
+    pub fn record(&mut self, method: &str, latency: Duration, failed: bool) {
+        if let Some(metrics) = self.methods.get_mut(method) {
+            metrics.record(latency, failed);
+        } else {
+            let mut metrics = MethodMetrics::default();
+            metrics.record(latency, failed);
+            self.methods.insert(method.to_string(), metrics);
+        }
+    }
+
+    pub fn get(&self, method: &str) -> Option<&MethodMetrics> {
+        self.methods.get(method)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &MethodMetrics)> {
+        self.methods.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Metrics, MAX_SAMPLES};
+
+    #[test]
+    fn record_counts_calls_and_errors() {
+        let mut metrics = Metrics::default();
+        metrics.record("foo", Duration::from_millis(1), false);
+        metrics.record("foo", Duration::from_millis(2), true);
+        metrics.record("bar", Duration::from_millis(3), false);
+
+        let foo = metrics.get("foo").unwrap();
+        assert_eq!(foo.calls, 2);
+        assert_eq!(foo.errors, 1);
+
+        let bar = metrics.get("bar").unwrap();
+        assert_eq!(bar.calls, 1);
+        assert_eq!(bar.errors, 0);
+
+        assert!(metrics.get("foobar").is_none());
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut metrics = Metrics::default();
+        for i in 1..=100 {
+            metrics.record("foo", Duration::from_millis(i), false);
+        }
+
+        let foo = metrics.get("foo").unwrap();
+        assert_eq!(foo.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(foo.percentile(0.5), Some(Duration::from_millis(51)));
+        assert_eq!(foo.percentile(1.0), Some(Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn samples_are_bounded() {
+        let mut metrics = Metrics::default();
+        for _ in 0..MAX_SAMPLES {
+            metrics.record("foo", Duration::from_millis(100), false);
+        }
+        for _ in 0..MAX_SAMPLES {
+            metrics.record("foo", Duration::from_millis(1), false);
+        }
+
+        let foo = metrics.get("foo").unwrap();
+        assert_eq!(foo.calls, 2 * MAX_SAMPLES as u64);
+        assert_eq!(foo.percentile(1.0), Some(Duration::from_millis(1)));
+    }
+}
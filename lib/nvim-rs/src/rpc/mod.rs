@@ -1,9 +1,13 @@
 pub mod caller;
 pub mod message;
+pub mod metrics;
 pub mod reader;
+pub mod transport;
 pub mod writer;
 
 pub use caller::{CallError, CallResponse, Caller, HandleError};
 pub use message::Message;
+pub use metrics::Metrics;
 pub use reader::{ReadError, RpcReader};
+pub use transport::Transport;
 pub use writer::{RpcWriter, WriteError};
@@ -0,0 +1,49 @@
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadHalf, WriteHalf};
+
+use crate::{rpc::RpcReader, Client};
+
+/// A connection to nvim, split into its read and write halves.
+///
+/// Implemented for pairs of halves (e.g. the stdout and stdin of an embedded
+/// nvim), and for any bidirectional stream, such as a TCP socket connected
+/// to `nvim --listen 127.0.0.1:PORT`.
+pub trait Transport {
+    type Reader: AsyncRead + Unpin;
+    type Writer: AsyncWrite + Unpin;
+
+    fn split(self) -> (Self::Reader, Self::Writer);
+}
+
+impl<T> Transport for T
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Reader = ReadHalf<T>;
+    type Writer = WriteHalf<T>;
+
+    fn split(self) -> (Self::Reader, Self::Writer) {
+        AsyncReadExt::split(self)
+    }
+}
+
+/// Separate read and write halves, used as is.
+pub struct Halves<R, W>(pub R, pub W);
+
+impl<R, W> Transport for Halves<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    type Reader = R;
+    type Writer = W;
+
+    fn split(self) -> (Self::Reader, Self::Writer) {
+        (self.0, self.1)
+    }
+}
+
+/// Creates the reader and the client for `transport`.
+pub fn connect<T: Transport>(transport: T) -> (RpcReader<T::Reader>, Client<T::Writer>) {
+    let (reader, writer) = transport.split();
+    (RpcReader::new(reader), Client::new(writer))
+}
@@ -247,15 +247,15 @@ pub struct MsgShow {
 }
 #[derive(Debug, serde :: Deserialize)]
 pub struct MsgShowcmd {
-    pub content: Vec<rmpv::Value>,
+    pub content: Vec<MsgShowContent>,
 }
 #[derive(Debug, serde :: Deserialize)]
 pub struct MsgShowmode {
-    pub content: Vec<rmpv::Value>,
+    pub content: Vec<MsgShowContent>,
 }
 #[derive(Debug, serde :: Deserialize)]
 pub struct MsgRuler {
-    pub content: Vec<rmpv::Value>,
+    pub content: Vec<MsgShowContent>,
 }
 #[derive(Debug, serde :: Deserialize)]
 pub struct MsgHistoryShow {
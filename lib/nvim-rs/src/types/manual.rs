@@ -8,8 +8,11 @@ pub enum ShowTabline {
 
 #[derive(Debug)]
 pub enum OptionSet {
+    Ambiwidth(String),
     Guifont(String),
+    Guifontwide(String),
     Linespace(i64),
+    Pumblend(i64),
     ShowTabline(ShowTabline),
     Unknown(String),
 }
@@ -26,9 +29,16 @@ impl<'de> serde::Deserialize<'de> for OptionSet {
 
         match name {
             "linespace" => Ok(Self::Linespace(data[1].as_i64().ok_or_else(bad_value)?)),
+            "pumblend" => Ok(Self::Pumblend(data[1].as_i64().ok_or_else(bad_value)?)),
+            "ambiwidth" => Ok(Self::Ambiwidth(
+                data[1].as_str().ok_or_else(bad_value)?.to_string(),
+            )),
             "guifont" => Ok(Self::Guifont(
                 data[1].as_str().ok_or_else(bad_value)?.to_string(),
             )),
+            "guifontwide" => Ok(Self::Guifontwide(
+                data[1].as_str().ok_or_else(bad_value)?.to_string(),
+            )),
             "showtabline" => Ok(Self::ShowTabline(
                 data[1]
                     .as_i64()
@@ -75,11 +85,16 @@ pub struct HlAttr {
     pub bold: Option<bool>,
     pub strikethrough: Option<bool>,
     pub underline: Option<bool>,
+    // NOTE(ville): Neovim 0.7 used different names for the underline styles.
+    #[serde(alias = "underlineline")]
     pub underdouble: Option<bool>,
     pub undercurl: Option<bool>,
+    #[serde(alias = "underdot")]
     pub underdotted: Option<bool>,
+    #[serde(alias = "underdash")]
     pub underdashed: Option<bool>,
     pub blend: Option<i64>,
+    pub url: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -156,15 +171,39 @@ pub struct CmdlineContent {
     pub text: String,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Window(rmpv::Value);
 
+impl Window {
+    /// The window's handle (i.e. the window id used in lua and vimscript).
+    pub fn handle(&self) -> Option<i64> {
+        ext_handle(&self.0)
+    }
+}
+
+/// Handle of a remote object (e.g. a window), which is sent as an ext value.
+fn ext_handle(value: &rmpv::Value) -> Option<i64> {
+    match value {
+        rmpv::Value::Ext(_, data) => rmpv::decode::read_value(&mut data.as_slice())
+            .ok()
+            .and_then(|value| value.as_i64()),
+        value => value.as_i64(),
+    }
+}
+
 #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Buffer(rmpv::Value);
 
 #[derive(Debug, PartialEq, Clone, serde::Deserialize, serde::Serialize)]
 pub struct Tabpage(rmpv::Value);
 
+impl Tabpage {
+    /// The tabpage's handle (i.e. the tabpage id used in lua).
+    pub fn handle(&self) -> Option<i64> {
+        ext_handle(&self.0)
+    }
+}
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct TablineTab {
     pub name: String,
@@ -177,7 +216,7 @@ pub struct TablineBuffer {
     pub buffer: Buffer,
 }
 
-#[derive(Default, Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Default, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct PopupmenuItem {
     pub word: String,
     pub kind: String,
@@ -213,6 +252,14 @@ impl Dictionary {
     }
 }
 
+impl std::ops::Deref for Dictionary {
+    type Target = rmpv::Value;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 #[derive(Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(transparent)]
 pub struct LuaRef(rmpv::Value);
@@ -226,3 +273,11 @@ impl Object {
         Self(v.into())
     }
 }
+
+impl std::ops::Deref for Object {
+    type Target = rmpv::Value;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
@@ -57,3 +57,71 @@ async fn void_response_decodes_correctly() {
         })
         .await;
 }
+
+#[tokio::test]
+async fn metrics_are_recorded_per_method() {
+    let (client, server) = tokio::io::duplex(1024 * 64);
+
+    let local = tokio::task::LocalSet::new();
+
+    local
+        .run_until(async move {
+            let server_handle = tokio::task::spawn_local(async move {
+                let (reader, writer) = tokio::io::split(server);
+                let mut writer = writer.compat_write();
+                let mut reader: RpcReader<_> = reader.compat().into();
+
+                for _ in 0..2 {
+                    let req = match reader.recv().await.unwrap() {
+                        Message::Request(req) => req,
+                        got => panic!("Unexpected message: {:?}", got),
+                    };
+
+                    let err = rmpv::Value::from("failed");
+                    let err = (req.method == "fail").then_some(&err);
+                    (&mut writer)
+                        .write_rpc_response(req.msgid, err, None::<&rmpv::Value>)
+                        .await
+                        .unwrap();
+                }
+            });
+
+            let client_handle = tokio::task::spawn_local(async move {
+                let (reader, writer) = tokio::io::split(client);
+                let writer = writer.compat_write();
+                let mut reader: RpcReader<_> = reader.compat().into();
+
+                let mut client = Client::new(writer);
+
+                let ok = client.call::<(), _, _>("get_nil", ()).await.unwrap();
+                let failed = client.call::<(), _, _>("fail", ()).await.unwrap();
+
+                let handle = tokio::task::spawn(async move {
+                    for _ in 0..2 {
+                        match reader.recv().await.unwrap() {
+                            Message::Response(response) => {
+                                client.handle_response(response).unwrap()
+                            }
+                            v => panic!("unexpected message: {:?}", v),
+                        }
+                    }
+
+                    let metrics = client.metrics().get("get_nil").unwrap();
+                    assert_eq!(metrics.calls, 1);
+                    assert_eq!(metrics.errors, 0);
+
+                    let metrics = client.metrics().get("fail").unwrap();
+                    assert_eq!(metrics.calls, 1);
+                    assert_eq!(metrics.errors, 1);
+                });
+
+                assert_eq!(ok.await, Ok(()));
+                assert!(failed.await.is_err());
+
+                handle.await.unwrap();
+            });
+
+            tokio::try_join!(server_handle, client_handle).unwrap();
+        })
+        .await;
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+use rmpv::Value;
+
+use nvim_rs::{into_value::MapBuilder, IntoValue};
+
+#[test]
+fn into_value_converts_std_types() {
+    assert_eq!("foo".into_value(), Value::from("foo"));
+    assert_eq!(None::<i64>.into_value(), Value::Nil);
+    assert_eq!(Some(1).into_value(), Value::from(1));
+    assert_eq!(
+        (1, "a", true).into_value(),
+        Value::Array(vec![Value::from(1), Value::from("a"), Value::from(true)])
+    );
+    assert_eq!(
+        vec![vec![true], vec![]].into_value(),
+        Value::Array(vec![
+            Value::Array(vec![Value::from(true)]),
+            Value::Array(vec![])
+        ])
+    );
+
+    let map = HashMap::from([(String::from("a"), 1)]);
+    assert_eq!(
+        map.into_value(),
+        Value::Map(vec![(Value::from("a"), Value::from(1))])
+    );
+}
+
+#[test]
+fn map_builder_skips_missing_values() {
+    let value = MapBuilder::new()
+        .insert("width", 10)
+        .insert_some("height", None::<i64>)
+        .insert_some("relative", Some("editor"))
+        .build();
+
+    assert_eq!(
+        value,
+        Value::Map(vec![
+            (Value::from("width"), Value::from(10)),
+            (Value::from("relative"), Value::from("editor")),
+        ])
+    );
+}
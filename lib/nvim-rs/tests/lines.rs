@@ -0,0 +1,123 @@
+use std::cell::{Cell, RefCell};
+
+use futures::{executor::block_on, StreamExt, TryStreamExt};
+
+use nvim_rs::lines::buf_get_lines_chunked;
+use nvim_rs::rpc::caller::{Response, Sender};
+use nvim_rs::rpc::{Caller, WriteError};
+use nvim_rs::types::Buffer;
+use nvim_rs::{async_trait, serde};
+
+/// Answers the calls from a fake buffer, and records the called methods.
+#[derive(Default)]
+struct FakeNvim {
+    lines: Vec<String>,
+    calls: RefCell<Vec<String>>,
+    msgid: Cell<u32>,
+    senders: RefCell<Vec<(u32, Sender)>>,
+}
+
+#[async_trait::async_trait(?Send)]
+impl Caller for &FakeNvim {
+    async fn write<S: AsRef<str>, V: serde::Serialize>(
+        self,
+        msgid: u32,
+        method: S,
+        args: V,
+    ) -> Result<(), WriteError> {
+        let args = rmpv::ext::to_value(args).unwrap();
+        let args = args.as_array().unwrap();
+        let result = match method.as_ref() {
+            "nvim_buf_line_count" => rmpv::Value::from(self.lines.len() as u64),
+            "nvim_buf_get_lines" => {
+                let start = args[1].as_u64().unwrap() as usize;
+                let end = (args[2].as_u64().unwrap() as usize).min(self.lines.len());
+                rmpv::Value::Array(
+                    self.lines[start..end]
+                        .iter()
+                        .map(|line| rmpv::Value::from(line.as_str()))
+                        .collect(),
+                )
+            }
+            method => panic!("unexpected call: {}", method),
+        };
+
+        self.calls.borrow_mut().push(method.as_ref().to_string());
+
+        let mut senders = self.senders.borrow_mut();
+        let index = senders.iter().position(|(id, _)| *id == msgid).unwrap();
+        let (_, sender) = senders.swap_remove(index);
+        sender
+            .send(Response::new(msgid, None, Some(result)))
+            .unwrap();
+
+        Ok(())
+    }
+
+    fn next_msgid(&mut self) -> u32 {
+        let msgid = self.msgid.get();
+        self.msgid.set(msgid + 1);
+        msgid
+    }
+
+    fn store_handler(&mut self, msgid: u32, _method: &str, sender: Sender) {
+        self.senders.borrow_mut().push((msgid, sender));
+    }
+}
+
+fn fake_nvim(lines: usize) -> FakeNvim {
+    FakeNvim {
+        lines: (0..lines).map(|i| i.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn buffer() -> Buffer {
+    rmpv::ext::from_value(rmpv::Value::from(0)).unwrap()
+}
+
+#[test]
+fn buf_get_lines_chunked_fetches_all_lines() {
+    let nvim = fake_nvim(10);
+
+    let chunks: Vec<Vec<String>> =
+        block_on(buf_get_lines_chunked(&nvim, buffer(), 0, -1, 4).try_collect()).unwrap();
+
+    let lens = chunks.iter().map(Vec::len).collect::<Vec<_>>();
+    assert_eq!(lens, vec![4, 4, 2]);
+    assert_eq!(chunks.concat(), nvim.lines);
+    assert_eq!(
+        *nvim.calls.borrow(),
+        vec![
+            "nvim_buf_line_count",
+            "nvim_buf_get_lines",
+            "nvim_buf_get_lines",
+            "nvim_buf_get_lines",
+        ]
+    );
+}
+
+#[test]
+fn buf_get_lines_chunked_stops_when_dropped() {
+    let nvim = fake_nvim(10);
+
+    {
+        let stream = buf_get_lines_chunked(&nvim, buffer(), 2, 8, 3);
+        futures::pin_mut!(stream);
+
+        let chunk = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(chunk, vec!["2", "3", "4"]);
+    }
+
+    assert_eq!(*nvim.calls.borrow(), vec!["nvim_buf_get_lines"]);
+}
+
+#[test]
+fn buf_get_lines_chunked_clamps_chunk_size() {
+    let nvim = fake_nvim(3);
+
+    let chunks: Vec<Vec<String>> =
+        block_on(buf_get_lines_chunked(&nvim, buffer(), 0, -1, 0).try_collect()).unwrap();
+
+    assert_eq!(chunks, vec![vec!["0"], vec!["1"], vec!["2"]]);
+}
@@ -0,0 +1,47 @@
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use nvim_rs::rpc::{message::Message, transport, Caller, RpcWriter};
+
+#[tokio::test]
+async fn call_over_bidirectional_stream() {
+    let (client, server) = tokio::io::duplex(1024 * 64);
+
+    let local = tokio::task::LocalSet::new();
+
+    local
+        .run_until(async move {
+            let server_handle = tokio::task::spawn_local(async move {
+                let (mut reader, mut server) = transport::connect(server.compat());
+
+                let got = reader.recv().await.unwrap();
+                let req = match got {
+                    Message::Request(req) => req,
+                    _ => panic!("Unexpected message: {:?}", got),
+                };
+
+                assert_eq!(req.method, "get_nil");
+
+                server
+                    .as_mut()
+                    .write_rpc_response(req.msgid, None::<&rmpv::Value>, None::<&rmpv::Value>)
+                    .await
+                    .unwrap();
+            });
+
+            let client_handle = tokio::task::spawn_local(async move {
+                let (mut reader, mut client) = transport::connect(client.compat());
+
+                let res = client.call::<(), _, _>("get_nil", ()).await.unwrap();
+
+                match reader.recv().await.unwrap() {
+                    Message::Response(response) => client.handle_response(response).unwrap(),
+                    v => panic!("unexpected message: {:?}", v),
+                }
+
+                assert_eq!(res.await, Ok(()));
+            });
+
+            tokio::try_join!(server_handle, client_handle).unwrap();
+        })
+        .await;
+}
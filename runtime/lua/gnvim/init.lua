@@ -1,5 +1,28 @@
 local M = {}
 
+--- Maximum number of closed windows to remember.
+local MAX_CLOSED_WINDOWS = 20
+
+--- Recently closed windows, latest last.
+M.closed_windows = {}
+
+--- Handlers for notifications forwarded by gnvim, by method.
+M.notification_handlers = {}
+
+--- Callbacks of the current toast's actions, by id.
+M.toast_callbacks = {}
+
+--- How messages are shown as toasts, by message kind. See
+--- `setup_message_toasts`.
+M.message_toast_kinds = {
+  emsg = { severity = 'error', timeout = 8000 },
+  echoerr = { severity = 'error', timeout = 8000 },
+  lua_error = { severity = 'error', timeout = 8000 },
+  rpc_error = { severity = 'error', timeout = 8000 },
+  wmsg = { severity = 'warning', timeout = 5000 },
+  echomsg = { severity = 'info', timeout = 3000 },
+}
+
 function M.find_gnvim_chans()
   local nvim_chans = {}
   local chans = vim.api.nvim_list_chans();
@@ -30,6 +53,23 @@ function M.notify(fn, ...)
   end
 end
 
+--- Send request to the first avaialble gnvim GUI.
+---
+---@param fn Function to call
+---@param ... Arguments for fn
+---@return Result of the call
+function M.request(fn, ...)
+  local chan = M.find_gnvim_chans()[1]
+  if chan == nil then
+    return nil
+  end
+
+  return vim.rpcrequest(chan, "gnvim", {
+    ['fn'] = fn,
+    ['args'] = ...,
+  })
+end
+
 function M.echo_repeat(msg, times)
   M.notify('echo_repeat', {
     msg = msg,
@@ -49,8 +89,1404 @@ function M.set_cursor_position_transition(t)
   M.notify('cursor_position_transition', t)
 end
 
+--- Set the easing of the cursor position animation.
+---
+---@param easing One of 'linear', 'ease_out_quad', 'ease_out_cubic' (default),
+---              'ease_out_expo' or 'ease_in_out_cubic'
+function M.set_cursor_position_easing(easing)
+  M.notify('cursor_position_easing', easing)
+end
+
 function M.set_scroll_transition(t)
   M.notify('scroll_transition', t)
 end
 
+function M.set_ligatures(enabled)
+  M.notify('ligatures', enabled)
+end
+
+--- Toggle the shadow and rounded corners of the floating windows.
+---
+---@param enabled boolean
+function M.set_float_decoration(enabled)
+  M.notify('float_decoration', enabled)
+end
+
+--- Toggle the preview of closed folds, shown when a fold is hovered with the
+--- mouse. Clicking the preview opens the fold.
+---
+---@param enabled boolean
+function M.set_fold_preview(enabled)
+  M.notify('fold_preview', enabled)
+end
+
+--- Maximum number of lines in a fold preview.
+M.fold_preview_lines = 20
+--- Maximum length of the lines in a fold preview, in characters.
+local FOLD_PREVIEW_COLS = 120
+
+--- Attributes of a highlight group. Treesitter captures without their own
+--- group (e.g. `@keyword.function.lua`) fall back to their parents.
+local function hl_attrs(name)
+  while name do
+    local hl = vim.api.nvim_get_hl(0, { name = name, link = false })
+    if next(hl) then
+      return hl
+    end
+    name = name:match('^(.+)%.[^.]+$')
+  end
+
+  return {}
+end
+
+--- Highlight group at a position (zero based), from treesitter or the
+--- syntax. Must be called in the buffer's window.
+local function hl_at(buf, row, col)
+  if vim.treesitter.highlighter.active[buf] then
+    local ok, captures = pcall(vim.treesitter.get_captures_at_pos, buf, row, col)
+    if ok and #captures > 0 then
+      local capture = captures[#captures]
+      return '@' .. capture.capture .. '.' .. capture.lang
+    end
+  end
+
+  local id = vim.fn.synID(row + 1, col + 1, 1)
+  if id > 0 then
+    return vim.fn.synIDattr(vim.fn.synIDtrans(id), 'name')
+  end
+end
+
+--- A line as highlighted chunks. Must be called in the buffer's window.
+local function highlighted_line(buf, lnum)
+  local line = vim.api.nvim_buf_get_lines(buf, lnum - 1, lnum, true)[1]
+  line = vim.fn.strcharpart(line, 0, FOLD_PREVIEW_COLS)
+
+  -- One query per character, not per byte, so multibyte characters stay
+  -- whole. Whitespace only has a background, which isn't previewed, so it
+  -- joins the previous chunk.
+  local starts = vim.str_utf_pos(line)
+  local chunks = {}
+  local prev
+  for i, start in ipairs(starts) do
+    local text = line:sub(start, (starts[i + 1] or #line + 1) - 1)
+    local group
+    if prev and text:match('^%s$') then
+      group = prev.group
+    else
+      group = hl_at(buf, lnum - 1, start - 1) or ''
+    end
+
+    if text == '\t' then
+      text = string.rep(' ', vim.bo[buf].tabstop)
+    end
+
+    if prev and prev.group == group then
+      prev.text = prev.text .. text
+    else
+      prev = { group = group, text = text }
+      table.insert(chunks, prev)
+    end
+  end
+
+  for i, chunk in ipairs(chunks) do
+    local hl = hl_attrs(chunk.group)
+    chunks[i] = {
+      text = chunk.text,
+      fg = hl.fg,
+      bold = hl.bold or false,
+      italic = hl.italic or false,
+    }
+  end
+
+  return chunks
+end
+
+--- Preview of the closed fold on a window's grid row (zero based), or nil
+--- if there's none.
+---
+---@param win integer Window handle
+---@param row integer
+---@return table|nil {lnum, lines}, where lines are lists of
+---                  {text, fg, bold, italic}
+function M.fold_preview(win, row)
+  if not vim.api.nvim_win_is_valid(win) then
+    return nil
+  end
+
+  return vim.api.nvim_win_call(win, function()
+    local info = vim.fn.getwininfo(win)[1]
+    local target = info.winrow + row
+
+    -- Find the line on the row, skipping over the closed folds.
+    local lnum = info.topline
+    while lnum <= info.botline do
+      local pos = vim.fn.screenpos(win, lnum, 1)
+      if pos.row == target then
+        break
+      elseif pos.row > target then
+        return nil
+      end
+
+      local fold_end = vim.fn.foldclosedend(lnum)
+      lnum = (fold_end ~= -1 and fold_end or lnum) + 1
+    end
+
+    if lnum > info.botline or vim.fn.foldclosed(lnum) == -1 then
+      return nil
+    end
+
+    local buf = vim.api.nvim_win_get_buf(win)
+    local last = math.min(vim.fn.foldclosedend(lnum), lnum + M.fold_preview_lines - 1)
+    local lines = {}
+    for l = lnum, last do
+      table.insert(lines, highlighted_line(buf, l))
+    end
+
+    return { lnum = lnum, lines = lines }
+  end)
+end
+
+--- Open the closed fold at a line (i.e. `zo`).
+---
+---@param win integer Window handle
+---@param lnum integer
+function M.open_fold(win, lnum)
+  vim.api.nvim_win_call(win, function()
+    vim.cmd(lnum .. 'foldopen')
+  end)
+end
+
+--- Toggle the scrollbars of the windows. The scrollbars can be dragged and
+--- clicked to scroll the windows.
+---
+---@param enabled boolean
+function M.set_scrollbars(enabled)
+  M.notify('scrollbars', enabled)
+end
+
+--- Scroll a window so a line is at the top.
+---
+---@param win integer Window handle
+---@param topline integer
+function M.scroll_to(win, topline)
+  vim.api.nvim_win_call(win, function()
+    vim.fn.winrestview({ topline = topline })
+  end)
+end
+
+--- Toggle the cell inspector. When enabled, hovering a cell with ctrl+shift
+--- held shows the cell's text, highlight and grid.
+---
+---@param enabled boolean
+function M.set_cell_inspector(enabled)
+  M.notify('cell_inspector', enabled)
+end
+
+--- Show the highlight inspector window, which lists all the highlights
+--- defined by nvim. Clicking a highlight copies its colors.
+function M.hl_inspector()
+  M.notify('hl_inspector')
+end
+
+--- Show the messages in gnvim, or let nvim draw them in its message grid
+--- (the default).
+---
+---@param enabled boolean
+function M.set_ext_messages(enabled)
+  M.notify('ext_messages', enabled)
+end
+
+--- Show or hide the message history panel (i.e. `:messages`).
+function M.toggle_messages()
+  M.notify('toggle_msg_history')
+end
+
+--- Show a toast at the top of the window, replacing the previous one.
+---
+---@param opts table
+---    - text: The message.
+---    - severity: 'info' (default), 'warning' or 'error'.
+---    - timeout: Milliseconds until the toast is hidden. Defaults to
+---      keeping the toast until it's dismissed.
+---    - actions: Up to two action buttons, as a list of {label, callback}.
+function M.toast(opts)
+  M.toast_callbacks = {}
+
+  local actions = {}
+  for i, action in ipairs(opts.actions or {}) do
+    M.toast_callbacks[i] = action[2]
+    table.insert(actions, { label = action[1], id = i })
+  end
+
+  M.notify('toast', {
+    text = opts.text,
+    severity = opts.severity,
+    timeout = opts.timeout,
+    actions = actions,
+  })
+end
+
+--- Called by gnvim when a toast's action is clicked.
+function M.toast_action(id)
+  local callback = M.toast_callbacks[id]
+  M.toast_callbacks = {}
+  if callback then
+    callback()
+  end
+end
+
+--- Show messages (see |ui-messages|) as toasts on top of the editor instead
+--- of in the message area, by their kind (e.g. "emsg" for errors, "echoerr"
+--- for |:echoerr|, "wmsg" for warnings and "echomsg" for |:echomsg|).
+---
+---@param kinds table|nil Toast options ({severity, timeout}) by message kind,
+---    merged with the defaults. False keeps the kind in the message area.
+function M.setup_message_toasts(kinds)
+  M.message_toast_kinds = vim.tbl_extend('force', M.message_toast_kinds, kinds or {})
+
+  local toasts = vim.empty_dict()
+  for kind, toast in pairs(M.message_toast_kinds) do
+    if toast then
+      toasts[kind] = toast
+    end
+  end
+  M.notify('message_toasts', toasts)
+end
+
+--- Show error messages (e.g. |E5108|, |:echoerr| and errors from
+--- `vim.notify`) also as desktop notifications, when the gnvim window isn't
+--- focused.
+---
+---@param enabled boolean|nil Defaults to true
+function M.setup_desktop_notifications(enabled)
+  M.notify('desktop_notifications', enabled ~= false)
+end
+
+--- Check for new gnvim releases once a day, and show a toast when there is
+--- one. Does nothing if gnvim is installed by a package manager.
+---
+---@param enabled boolean|nil Defaults to true
+function M.setup_update_check(enabled)
+  M.notify('update_check', enabled ~= false)
+end
+
+--- Fetch the latest gnvim release and send it to gnvim. Called by gnvim.
+function M.check_for_updates()
+  local url = 'https://api.github.com/repos/vhakulinen/gnvim/releases/latest'
+  vim.fn.jobstart({ 'curl', '-sfL', '--max-time', '30', url }, {
+    stdout_buffered = true,
+    on_stdout = function(_, data)
+      local ok, release = pcall(vim.json.decode, table.concat(data, '\n'))
+      if not ok or type(release) ~= 'table' or not release.tag_name then
+        return
+      end
+
+      M.notify('latest_release', {
+        tag = release.tag_name,
+        url = release.html_url,
+        notes = type(release.body) == 'string' and release.body or nil,
+      })
+    end,
+  })
+end
+
+--- Path of nvim's trust database (see |vim.secure.trust()|).
+local function trust_db_path()
+  return vim.fn.stdpath('state') .. '/trust'
+end
+
+--- Reads nvim's trust database. Returns the files' hashes (or '!' for denied
+--- files), by path. The database is only written by |vim.secure.trust()|.
+local function read_trust_db()
+  local trust = {}
+  local ok, lines = pcall(vim.fn.readfile, trust_db_path())
+  if not ok then
+    return trust
+  end
+
+  for _, line in ipairs(lines) do
+    local hash, path = line:match('^(%S+) (.+)$')
+    if hash then
+      trust[path] = hash
+    end
+  end
+
+  return trust
+end
+
+--- Parses the `key = value` lines of a `.gnvim.toml`. The values can be
+--- booleans, numbers or strings; tables aren't supported.
+local function parse_project_config(path, contents)
+  local config = {}
+  for n, line in ipairs(vim.split(contents, '\n')) do
+    line = vim.trim(line)
+    if line ~= '' and not vim.startswith(line, '#') then
+      local key, raw = line:match('^([%w_]+)%s*=%s*(.-)$')
+      local value
+      if raw == 'true' or raw == 'false' then
+        value = raw == 'true'
+      elseif raw then
+        value = raw:match('^"(.*)"$') or raw:match("^'(.*)'$") or tonumber(raw)
+      end
+
+      if value == nil then
+        vim.notify(string.format('gnvim: invalid line %d in %s', n, path), vim.log.levels.WARN)
+      else
+        config[key] = value
+      end
+    end
+  end
+
+  return config
+end
+
+local function read_file(path)
+  local f = io.open(path, 'r')
+  if not f then
+    return nil
+  end
+
+  local contents = f:read('*a')
+  f:close()
+  return contents
+end
+
+--- Keep the window open when neovim exits cleanly, showing a start screen
+--- for relaunching neovim, reopening the last session, or quitting.
+---
+---@param enabled boolean
+function M.set_keep_open(enabled)
+  M.notify('keep_open', enabled)
+end
+
+--- Save the session (see |:mksession|) to `path` when exiting cleanly, for
+--- the start screen. Called by gnvim.
+---
+---@param path string
+---@param enabled boolean
+function M.save_session_on_exit(path, enabled)
+  local group = vim.api.nvim_create_augroup('gnvim_session', {})
+  if not enabled then
+    return
+  end
+
+  vim.api.nvim_create_autocmd('VimLeavePre', {
+    group = group,
+    callback = function()
+      if vim.v.exiting ~= 0 then
+        return
+      end
+      vim.fn.mkdir(vim.fn.fnamemodify(path, ':h'), 'p')
+      vim.cmd('mksession! ' .. vim.fn.fnameescape(path))
+    end,
+  })
+end
+
+--- Float configs of the externalized windows, for docking them back.
+local float_configs = {}
+
+--- Pop a floating window out into its own window on the desktop. Closing
+--- that window docks the floating window back.
+---
+---@param win integer|nil Window handle, current window by default
+function M.externalize_win(win)
+  win = win or vim.api.nvim_get_current_win()
+  local config = vim.api.nvim_win_get_config(win)
+  if config.external then
+    return
+  end
+  if config.relative == '' then
+    vim.notify('gnvim: not a floating window', vim.log.levels.ERROR)
+    return
+  end
+
+  float_configs[win] = config
+  vim.api.nvim_win_set_config(win, {
+    external = true,
+    width = config.width,
+    height = config.height,
+  })
+end
+
+--- Dock an externalized window back as a floating window. Called by gnvim
+--- when the window is closed on the desktop.
+---
+---@param win integer Window handle
+---@return boolean If the window was docked
+function M.dock_win(win)
+  if not vim.api.nvim_win_is_valid(win) then
+    return false
+  end
+
+  local config = float_configs[win]
+    or {
+      relative = 'editor',
+      row = math.floor((vim.o.lines - vim.api.nvim_win_get_height(win)) / 2),
+      col = math.floor((vim.o.columns - vim.api.nvim_win_get_width(win)) / 2),
+    }
+  float_configs[win] = nil
+  return (pcall(vim.api.nvim_win_set_config, win, config))
+end
+
+--- Start a server at `path` for `gnvim --remote-send` and `--remote-expr`,
+--- unless another gnvim already has it. Called on startup by gnvim.
+---
+---@param path string
+function M.start_server(path)
+  if vim.loop.fs_stat(path) ~= nil then
+    local ok, chan = pcall(vim.fn.sockconnect, 'pipe', path)
+    if ok and chan > 0 then
+      vim.fn.chanclose(chan)
+      return
+    end
+    -- Left behind by a gnvim that crashed.
+    os.remove(path)
+  end
+
+  vim.fn.mkdir(vim.fn.fnamemodify(path, ':h'), 'p')
+  vim.fn.serverstart(path)
+end
+
+--- Stores `action` ("allow" or "deny") for `path` in nvim's trust database.
+--- Allowing only takes a buffer, so the file's `contents` go through a
+--- scratch buffer. Returns false and an error message on failure.
+local function trust_file(action, path, contents)
+  if action == 'deny' then
+    local ok, res, err = pcall(vim.secure.trust, { action = 'deny', path = path })
+    return ok and res, ok and err or res
+  end
+
+  local buf = vim.api.nvim_create_buf(false, true)
+  local ok, res, err = pcall(function()
+    vim.api.nvim_buf_set_name(buf, path)
+    -- Hashed the way nvim hashes the file on read.
+    local lines = vim.split(contents, '\n')
+    local eol = lines[#lines] == ''
+    if eol then
+      table.remove(lines)
+    end
+    vim.bo[buf].fileformat = 'unix'
+    vim.bo[buf].endofline = eol
+    vim.api.nvim_buf_set_lines(buf, 0, -1, false, lines)
+    return vim.secure.trust({ action = 'allow', bufnr = buf })
+  end)
+  vim.api.nvim_buf_delete(buf, { force = true })
+  return ok and res, ok and err or res
+end
+
+--- Ask for trusting project-local configs (see 'exrc') in a gnvim dialog,
+--- instead of in the message area. The choices are stored in nvim's trust
+--- database, like with nvim's own prompt. Called on startup by gnvim.
+function M.setup_trust_prompt()
+  if not vim.secure or M.original_secure_read then
+    return
+  end
+
+  -- Files read before our ui is attached. The dialog can't be answered
+  -- before that, so they're asked about (and sourced) on `UIEnter`.
+  local ui_entered = #vim.api.nvim_list_uis() > 0
+  local deferred = {}
+
+  M.original_secure_read = vim.secure.read
+  vim.secure.read = function(path)
+    local fullpath = vim.loop.fs_realpath(vim.fs.normalize(path))
+    local contents = fullpath and read_file(fullpath)
+    if not contents or #M.find_gnvim_chans() == 0 then
+      return M.original_secure_read(path)
+    end
+
+    local trust = read_trust_db()
+    local hash = vim.fn.sha256(contents)
+    -- Already decided, nvim won't prompt.
+    if trust[fullpath] == '!' or trust[fullpath] == hash then
+      return M.original_secure_read(path)
+    end
+
+    if not ui_entered then
+      table.insert(deferred, fullpath)
+      return nil
+    end
+
+    local choice = M.request('trust_prompt', fullpath)
+    if choice ~= 'allow' and choice ~= 'deny' then
+      return nil
+    end
+
+    local ok, err = trust_file(choice, fullpath, contents)
+    if not ok then
+      vim.notify('gnvim: ' .. tostring(err), vim.log.levels.ERROR)
+    end
+
+    return choice == 'allow' and contents or nil
+  end
+
+  if ui_entered then
+    vim.schedule(M.load_project_config)
+    return
+  end
+
+  vim.api.nvim_create_autocmd('UIEnter', {
+    once = true,
+    callback = function()
+      ui_entered = true
+      for _, path in ipairs(deferred) do
+        if vim.secure.read(path) then
+          vim.cmd.source(vim.fn.fnameescape(path))
+        end
+      end
+      deferred = {}
+
+      M.load_project_config()
+    end,
+  })
+end
+
+--- Apply the settings of the current directory's `.gnvim.toml`, once it's
+--- trusted (see |gnvim-trust|). Each `key = value` calls `set_<key>(value)`
+--- of this module. Called on startup by `setup_trust_prompt`.
+function M.load_project_config()
+  local path = vim.fn.fnamemodify('.gnvim.toml', ':p')
+  if vim.fn.filereadable(path) == 0 then
+    return
+  end
+
+  local contents = vim.secure.read(path)
+  if not contents then
+    return
+  end
+
+  for key, value in pairs(parse_project_config(path, contents)) do
+    local set = M['set_' .. key]
+    if type(set) == 'function' then
+      set(value)
+    else
+      vim.notify(string.format('gnvim: unknown setting in %s: %s', path, key), vim.log.levels.WARN)
+    end
+  end
+end
+
+--- Set up the `--scratch` window. Called by gnvim on startup.
+---
+---@param notes boolean The current buffer is the notes file, to save
+---    automatically. Otherwise it's a scratch buffer.
+function M.setup_scratch(notes)
+  -- Fixed title, for window manager rules.
+  vim.o.title = true
+  vim.o.titlestring = 'Gnvim Scratch'
+
+  if not notes then
+    vim.bo.buftype = 'nofile'
+    vim.bo.bufhidden = 'hide'
+    vim.bo.swapfile = false
+    return
+  end
+
+  vim.fn.mkdir(vim.fn.expand('%:p:h'), 'p')
+  -- Save often, the window is likely closed by the window manager.
+  vim.api.nvim_create_autocmd({ 'InsertLeave', 'TextChanged', 'FocusLost', 'VimLeavePre' }, {
+    group = vim.api.nvim_create_augroup('gnvim_scratch', {}),
+    buffer = vim.api.nvim_get_current_buf(),
+    command = 'silent! update',
+  })
+end
+
+--- Set the scale of the undercurl's waves.
+---
+---@param amplitude Non-negative number, 1.0 being the default
+function M.set_undercurl_amplitude(amplitude)
+  M.notify('undercurl_amplitude', amplitude)
+end
+
+--- Set how emojis are presented: 'color', 'text', or 'auto' (the default)
+--- for color when nvim gives them two cells (see 'emoji').
+---
+---@param presentation string
+function M.set_emoji_presentation(presentation)
+  M.notify('emoji_presentation', presentation)
+end
+
+--- Set how ambiguous width characters are laid out: 'single', 'double', or
+--- 'auto' for double when the font's glyphs for them are wide. Sets
+--- 'ambiwidth' accordingly, also when the font changes.
+---
+---@param ambiwidth string
+function M.set_ambiwidth(ambiwidth)
+  M.notify('ambiwidth', ambiwidth)
+end
+
+--- Set OpenType features for the font, in addition to the ones in guifont.
+---
+---@param features Comma separated features (e.g. 'ss01,cv02=2,-liga')
+function M.set_font_features(features)
+  M.notify('font_features', features)
+end
+
+--- Set the font family for bold text, instead of guifont's.
+---
+---@param family Font family (e.g. 'Victor Mono'), or empty for guifont's
+function M.set_bold_font(family)
+  M.notify('bold_font', family or '')
+end
+
+--- Set the font family for italic text, instead of guifont's.
+---
+---@param family Font family (e.g. 'Victor Mono'), or empty for guifont's
+function M.set_italic_font(family)
+  M.notify('italic_font', family or '')
+end
+
+--- Set the power saver mode.
+---
+---@param mode 'auto', 'on' or 'off'
+function M.set_power_saver(mode)
+  M.notify('power_saver', mode)
+end
+
+--- Toggle the safe rendering, i.e. rendering without caching and animations
+--- for graphics drivers that cause rendering artifacts. The renderer itself
+--- can't be switched at runtime, see `--safe-rendering` for that.
+---
+---@param enabled boolean
+function M.set_safe_rendering(enabled)
+  M.notify('safe_rendering', enabled)
+end
+
+--- Override how keys are translated into neovim input. Keys are gdk key
+--- names (e.g. `XF86AudioPlay`), values are neovim keys (e.g. `F13` or
+--- `<F13>`).
+---
+---@param remap Table of key name to neovim key, replacing the previous one
+function M.set_key_remap(remap)
+  -- Make sure an empty table is sent as a map.
+  M.notify('key_remap', next(remap) and remap or vim.empty_dict())
+end
+
+--- Set the mapping from mouse buttons to nvim input, e.g. for the back and
+--- forward buttons: >
+---   require('gnvim').set_mouse_map({ X1Mouse = '<C-o>', X2Mouse = '<C-i>' })
+--- <
+--- Modifiers are given in the order S, C, M, D (e.g. `S-C-MiddleMouse`).
+---
+---@param map table Mouse button to keys (see |nvim_input()|)
+function M.set_mouse_map(map)
+  -- Make sure an empty table is sent as a map.
+  M.notify('mouse_map', next(map) and map or vim.empty_dict())
+end
+
+--- Set the notation of the Hyper modifier (e.g. 'T' for `<T-a>`).
+---
+---@param notation Modifier notation, '' to drop the modifier, nil for the
+---                default ('T')
+function M.set_hyper_modifier(notation)
+  M.notify('hyper_modifier', notation or vim.NIL)
+end
+
+--- Set the focus mode, which centers the text and hides the tabline, the
+--- header bar and the message toasts.
+---
+---@param opts table|nil
+---    - enabled: Defaults to toggling the focus mode.
+---    - width: Maximum text width, in columns. Defaults to 100.
+---    - dim: Dim the windows other than the current one.
+function M.focus_mode(opts)
+  opts = opts or {}
+  M.notify('focus_mode', {
+    enabled = opts.enabled,
+    width = opts.width,
+    dim = opts.dim,
+  })
+end
+
+--- Toggle the focus mode (see |gnvim-focus-mode|).
+function M.toggle_focus_mode()
+  M.focus_mode()
+end
+
+--- Accent colors of the tabpages, by tabpage handle.
+M.tab_accents = {}
+
+--- Sets the accent color of a tabpage. The accent tints the tabpage's
+--- tabline entry, and is drawn as a thin border at the top while the
+--- tabpage is the current one.
+---
+---@param color string|nil Color (e.g. `#ff8800`), nil removes the accent
+---@param tab integer|nil Tabpage handle, defaults to the current tabpage
+function M.set_tab_accent(color, tab)
+  if tab == nil or tab == 0 then
+    tab = vim.api.nvim_get_current_tabpage()
+  end
+
+  M.tab_accents[tab] = color
+
+  local accents = {}
+  for handle, accent in pairs(M.tab_accents) do
+    if vim.api.nvim_tabpage_is_valid(handle) then
+      table.insert(accents, { tab = handle, color = accent })
+    else
+      M.tab_accents[handle] = nil
+    end
+  end
+
+  M.notify('tab_accents', accents)
+end
+
+--- Toggle secure input mode (e.g. for editing secrets).
+---
+---@param enabled boolean
+function M.secure_input(enabled)
+  M.notify('secure_input', enabled)
+end
+
+--- Detach and reattach gnvim's ui, rebuilding the ui state from scratch.
+function M.reattach()
+  M.notify('reattach')
+end
+
+--- Set the text shown in the splash (e.g. while reattaching).
+---
+---@param markup string Text, in pango markup
+function M.set_splash_text(markup)
+  M.notify('splash_text', markup)
+end
+
+--- Set how the bell is rung.
+---
+---@param mode 'auto' (follow 'visualbell'), 'audible', 'visual' or 'off'
+function M.set_bell(mode)
+  M.notify('bell', mode)
+end
+
+--- Show gnvim's about dialog (e.g. for the version information needed in
+--- bug reports).
+function M.about()
+  M.notify('about')
+end
+
+--- Tell gnvim when its ui is attached or detached (`UIEnter` and `UILeave`),
+--- so it can rebuild its ui state when someone else does it.
+function M.track_ui_attach()
+  local group = vim.api.nvim_create_augroup('gnvim_ui_attach', {})
+  for event, fn in pairs({ UIEnter = 'ui_attached', UILeave = 'ui_detached' }) do
+    vim.api.nvim_create_autocmd(event, {
+      group = group,
+      callback = function()
+        local chan = vim.v.event.chan
+        -- Nothing to rebuild when nvim is exiting.
+        if vim.v.exiting ~= vim.NIL then
+          return
+        end
+
+        if vim.tbl_contains(M.find_gnvim_chans(), chan) then
+          vim.rpcnotify(chan, 'gnvim', { ['fn'] = fn, args = chan })
+        end
+      end,
+    })
+  end
+end
+
+--- Set the maximum number of redraws per second.
+---
+---@param fps Frames per second, nil or 0 to match the monitor's refresh rate
+function M.set_max_fps(fps)
+  M.notify('max_fps', fps or 0)
+end
+
+--- Notify gnvim when a window displays a file larger than `threshold`
+--- bytes, so gnvim can offer to disable its animations for the window.
+---
+---@param threshold Size in bytes (defaults to 1MB)
+function M.setup_large_file(threshold)
+  threshold = threshold or 1024 * 1024
+
+  vim.api.nvim_create_autocmd('BufWinEnter', {
+    group = vim.api.nvim_create_augroup('gnvim_large_file', {}),
+    callback = function(args)
+      local size = vim.fn.getfsize(args.file)
+      if size > threshold then
+        vim.w.gnvim_large_file = true
+        M.notify('large_file', {
+          win = vim.api.nvim_get_current_win(),
+          size = size,
+        })
+      elseif vim.w.gnvim_large_file then
+        -- The window switched to a smaller file.
+        vim.w.gnvim_large_file = nil
+        M.notify('large_file', {
+          win = vim.api.nvim_get_current_win(),
+          size = size,
+          large = false,
+        })
+      end
+    end,
+  })
+end
+
+--- Open gnvim's find bar.
+function M.open_find()
+  M.notify('open_find')
+end
+
+--- Save a copy of the current buffer to a file picked in a dialog.
+function M.export_buffer()
+  M.notify('export_buffer')
+end
+
+--- Search for `pattern`. Used by gnvim's find bar.
+---
+---@param pattern Pattern to search for
+---@param flags Flags for |search()|
+---@return Index of the current match and the total number of matches
+function M.find(pattern, flags)
+  local ok, count = pcall(function()
+    vim.fn.setreg('/', pattern)
+    vim.v.hlsearch = 1
+    vim.fn.search(pattern, flags)
+
+    return vim.fn.searchcount({ recompute = true, maxcount = 0 })
+  end)
+
+  if not ok then
+    return { 0, 0 }
+  end
+
+  return { count.current, count.total }
+end
+
+--- Remember closed windows, so they can be reopened with
+--- `reopen_closed_window`.
+function M.track_closed_windows()
+  vim.api.nvim_create_autocmd('WinClosed', {
+    group = vim.api.nvim_create_augroup('gnvim_closed_windows', {}),
+    callback = function(args)
+      local win = tonumber(args.match)
+      if not win or not vim.api.nvim_win_is_valid(win) then
+        return
+      end
+
+      local buf = vim.api.nvim_win_get_buf(win)
+      local file = vim.api.nvim_buf_get_name(buf)
+      if file == '' or vim.bo[buf].buftype ~= '' then
+        return
+      end
+
+      table.insert(M.closed_windows, {
+        file = file,
+        cursor = vim.api.nvim_win_get_cursor(win),
+      })
+
+      if #M.closed_windows > MAX_CLOSED_WINDOWS then
+        table.remove(M.closed_windows, 1)
+      end
+    end,
+  })
+end
+
+--- Background color of the highlight group overriding `Normal` in the
+--- window's 'winhighlight' (e.g. `Normal:MyBg`), if any.
+---
+---@param win integer Window handle
+---@return integer|nil Color as 24-bit RGB
+function M.normal_bg(win)
+  for _, pair in ipairs(vim.split(vim.wo[win].winhighlight, ',', { plain = true })) do
+    local from, group = pair:match('^([^:]+):(.+)$')
+    if from == 'Normal' then
+      -- nvim_get_hl is available from api level 11 (nvim 0.9) onwards.
+      if vim.version().api_level >= 11 then
+        return vim.api.nvim_get_hl(0, { name = group, link = false }).bg
+      end
+
+      local ok, hl = pcall(vim.api.nvim_get_hl_by_name, group, true)
+      return ok and hl.background or nil
+    end
+  end
+end
+
+--- Latest 'winblend' of the floating windows sent to gnvim, by window.
+local sent_blends = {}
+
+--- Send the 'winblend' of the floating windows whose blend changed to
+--- gnvim, in one notification.
+local function refresh_blends()
+  local changed = {}
+  local wins = {}
+  for _, win in ipairs(vim.api.nvim_list_wins()) do
+    if vim.api.nvim_win_get_config(win).relative ~= '' then
+      wins[win] = true
+      local blend = vim.wo[win].winblend
+      if blend ~= (sent_blends[win] or 0) then
+        table.insert(changed, { win = win, blend = blend })
+      end
+      sent_blends[win] = blend > 0 and blend or nil
+    end
+  end
+
+  for win, _ in pairs(sent_blends) do
+    if not wins[win] then
+      sent_blends[win] = nil
+    end
+  end
+
+  if #changed > 0 then
+    M.notify('blends', changed)
+  end
+end
+
+--- Tell gnvim when 'winhighlight', 'winblend' or the colorscheme changes, so
+--- it can update the backgrounds of the windows.
+function M.track_win_highlights()
+  local group = vim.api.nvim_create_augroup('gnvim_win_highlights', {})
+  vim.api.nvim_create_autocmd('OptionSet', {
+    group = group,
+    pattern = 'winhighlight',
+    callback = function()
+      M.notify('refresh_win_highlights')
+    end,
+  })
+  local pending = false
+  local function schedule_blends()
+    if pending then
+      return
+    end
+
+    pending = true
+    vim.schedule(function()
+      pending = false
+      refresh_blends()
+    end)
+  end
+
+  vim.api.nvim_create_autocmd('OptionSet', {
+    group = group,
+    pattern = 'winblend',
+    callback = schedule_blends,
+  })
+  vim.api.nvim_create_autocmd('WinNew', {
+    group = group,
+    callback = schedule_blends,
+  })
+  vim.api.nvim_create_autocmd('ColorScheme', {
+    group = group,
+    callback = function()
+      M.notify('refresh_win_highlights')
+    end,
+  })
+end
+
+--- Latest guide columns sent to gnvim, by window.
+local sent_guides = {}
+
+--- Send the guide columns of the windows whose columns changed to gnvim, in
+--- one notification.
+local function refresh_guides()
+  local changed = {}
+  local wins = {}
+  for _, win in ipairs(vim.api.nvim_list_wins()) do
+    wins[win] = true
+    local cols = M.guide_columns(win)
+    if not vim.deep_equal(cols, sent_guides[win] or {}) then
+      table.insert(changed, { win = win, cols = cols })
+    end
+    sent_guides[win] = #cols > 0 and cols or nil
+  end
+
+  for win, _ in pairs(sent_guides) do
+    if not wins[win] then
+      sent_guides[win] = nil
+    end
+  end
+
+  if #changed > 0 then
+    M.notify('guides', changed)
+  end
+end
+
+--- Draw the 'colorcolumn' also as thin guide lines (see |gnvim-guides|).
+---
+---@param enabled boolean|nil Defaults to true
+function M.setup_guides(enabled)
+  local group = vim.api.nvim_create_augroup('gnvim_guides', {})
+  if enabled == false then
+    local cleared = {}
+    for win, _ in pairs(sent_guides) do
+      table.insert(cleared, { win = win, cols = {} })
+    end
+    sent_guides = {}
+    if #cleared > 0 then
+      M.notify('guides', cleared)
+    end
+    return
+  end
+
+  local pending = false
+  local function schedule()
+    if pending then
+      return
+    end
+
+    pending = true
+    vim.schedule(function()
+      pending = false
+      refresh_guides()
+    end)
+  end
+
+  vim.api.nvim_create_autocmd('OptionSet', {
+    group = group,
+    pattern = {
+      'colorcolumn',
+      'textwidth',
+      'number',
+      'relativenumber',
+      'numberwidth',
+      'signcolumn',
+      'foldcolumn',
+    },
+    callback = schedule,
+  })
+  vim.api.nvim_create_autocmd({ 'BufWinEnter', 'WinNew', 'WinScrolled', 'VimResized' }, {
+    group = group,
+    callback = schedule,
+  })
+
+  schedule()
+end
+
+--- Grid columns (zero based) of a window's guide lines, i.e. its
+--- 'colorcolumn' adjusted for the number and sign columns, and the
+--- horizontal scroll.
+---
+---@param win integer Window handle
+---@return integer[]
+function M.guide_columns(win)
+  if not vim.api.nvim_win_is_valid(win) then
+    return {}
+  end
+
+  local textwidth = vim.bo[vim.api.nvim_win_get_buf(win)].textwidth
+  local info = vim.fn.getwininfo(win)[1]
+  local leftcol = vim.api.nvim_win_call(win, vim.fn.winsaveview).leftcol
+
+  local cols = {}
+  for _, item in ipairs(vim.split(vim.wo[win].colorcolumn, ',', { trimempty = true })) do
+    local col
+    if item:match('^[+-]') then
+      -- Relative to 'textwidth', and only when it's set.
+      if textwidth > 0 then
+        col = textwidth + tonumber(item)
+      end
+    else
+      col = tonumber(item)
+    end
+
+    if col and col > leftcol then
+      table.insert(cols, info.textoff + col - 1 - leftcol)
+    end
+  end
+
+  return cols
+end
+
+--- Reopen the latest closed window in a split.
+function M.reopen_closed_window()
+  local entry = table.remove(M.closed_windows)
+  if entry == nil then
+    vim.notify('No closed windows', vim.log.levels.WARN)
+    return
+  end
+
+  vim.cmd.split(vim.fn.fnameescape(entry.file))
+  pcall(vim.api.nvim_win_set_cursor, 0, entry.cursor)
+end
+
+--- Notify gnvim with `wait_done` once all the buffers are closed (i.e.
+--- hidden or deleted).
+local function notify_when_closed(bufs, wait)
+  local remaining = vim.tbl_count(bufs)
+  if remaining == 0 then
+    M.notify('wait_done', wait)
+    return
+  end
+
+  local group = vim.api.nvim_create_augroup('gnvim_wait_' .. wait, {})
+  for buf in pairs(bufs) do
+    -- Closing the last window of the buffer (e.g. with |:wq|) unloads it,
+    -- unless something else wants it kept around.
+    if vim.bo[buf].bufhidden == '' then
+      vim.bo[buf].bufhidden = 'unload'
+    end
+
+    vim.api.nvim_create_autocmd({ 'BufUnload', 'BufDelete', 'BufWipeout' }, {
+      group = group,
+      buffer = buf,
+      once = true,
+      callback = function()
+        -- When exiting, gnvim releases the waiting process with our exit
+        -- status instead.
+        if vim.v.exiting ~= vim.NIL then
+          return
+        end
+
+        remaining = remaining - 1
+        if remaining == 0 then
+          vim.api.nvim_del_augroup_by_id(group)
+          M.notify('wait_done', wait)
+        end
+      end,
+    })
+  end
+end
+
+--- Open files received by gnvim (e.g. from xdg-open). Called by gnvim.
+---
+---@param files List of `{ path, line, col }` tables, line and col optional
+---@param cmd Ex command used to open the files (e.g. 'tabedit')
+---@param wait Id sent with `wait_done` once the files are closed, or nil
+function M.open_files(files, cmd, wait)
+  local bufs = {}
+  for _, file in ipairs(files) do
+    if vim.startswith(file.path, '+') then
+      -- A `+cmd` argument, run once the files before it are opened.
+      vim.cmd(file.path:sub(2))
+    else
+      vim.cmd[cmd](vim.fn.fnameescape(file.path))
+      if file.line ~= nil then
+        vim.fn.cursor(file.line, file.col or 1)
+      end
+      bufs[vim.api.nvim_get_current_buf()] = true
+    end
+  end
+
+  if wait ~= nil then
+    notify_when_closed(bufs, wait)
+  end
+end
+
+--- Handle notifications sent to gnvim with `method` (e.g. through
+--- |rpcnotify()|) in `handler`. Without a handler, such notifications are
+--- only counted in |gnvim-stats|.
+---
+---@param method Notification method
+---@param handler Function called with the notification's params, or nil to
+---               remove the handler
+function M.subscribe(method, handler)
+  M.notification_handlers[method] = handler
+  if handler == nil then
+    M.notify('unsubscribe', method)
+  else
+    M.notify('subscribe', method)
+  end
+end
+
+--- Called by gnvim for subscribed notifications.
+function M.handle_notification(method, params)
+  local handler = M.notification_handlers[method]
+  if handler ~= nil then
+    handler(params)
+  end
+end
+
+--- Get the RPC call statistics: per method call counts, error counts and
+--- latency percentiles (see |gnvim-stats|).
+---
+---@return table|nil Statistics, or nil if there is no gnvim ui
+function M.stats()
+  return M.request('stats')
+end
+
+--- Get the render statistics of a grid.
+---
+---@param grid_id Grid id (1 being the global grid)
+---@return table|nil Statistics, or nil if there is no such grid
+function M.grid_stats(grid_id)
+  return M.request('grid_stats', grid_id)
+end
+--- Show or hide the RPC call statistics on top of the editor. Refreshed once
+--- a second while shown.
+---
+---@param enabled boolean
+function M.set_debug_hud(enabled)
+  M.notify('debug_hud', enabled)
+end
+
+--- Screen rows of the window's diff lines (including the filler lines), as
+--- `{start, end}` bands of continuous rows sharing a diff highlight. The end
+--- is exclusive, and the rows are zero based.
+---
+---@param win integer Window handle
+---@return table Bands, empty if the window isn't in diff mode
+function M.diff_bands(win)
+  if not vim.wo[win].diff then
+    return {}
+  end
+
+  local height = vim.api.nvim_win_get_height(win)
+  local winrow = vim.fn.win_screenpos(win)[1]
+  local kinds = {}
+
+  vim.api.nvim_win_call(win, function()
+    -- Start rows of the lines (after their filler lines), to know how many
+    -- rows wrapped lines take.
+    local lines = {}
+    for lnum = vim.fn.line('w0'), vim.fn.line('w$') do
+      local pos = vim.fn.screenpos(win, lnum, 1)
+      if pos.row > 0 then
+        table.insert(lines, { lnum = lnum, row = pos.row - winrow })
+      end
+    end
+
+    for i, line in ipairs(lines) do
+      local filler = vim.fn.diff_filler(line.lnum)
+      for row = line.row - filler, line.row - 1 do
+        kinds[row] = 'DiffDelete'
+      end
+
+      local hl = vim.fn.diff_hlID(line.lnum, 1)
+      if hl > 0 then
+        local name = vim.fn.synIDattr(hl, 'name')
+        -- DiffText is only the changed part of a changed line.
+        if name == 'DiffText' then
+          name = 'DiffChange'
+        end
+
+        local following = lines[i + 1]
+        local last = following and following.row - vim.fn.diff_filler(following.lnum)
+          or line.row + 1
+        for row = line.row, last - 1 do
+          kinds[row] = name
+        end
+      end
+    end
+  end)
+
+  local bands = {}
+  local prev
+  for row = 0, height - 1 do
+    local kind = kinds[row]
+    if kind and kind == prev then
+      bands[#bands][2] = row + 1
+    elseif kind then
+      table.insert(bands, { row, row + 1 })
+    end
+    prev = kind
+  end
+
+  return bands
+end
+
+--- Latest diff bands sent to gnvim, by window.
+local sent_diff_bands = {}
+
+--- Send the diff bands of the windows whose diff lines changed to gnvim.
+--- Only the windows in diff mode, and the ones that just left it, are
+--- looked at.
+local function refresh_diff_bands()
+  local changed = {}
+  local wins = {}
+  for _, win in ipairs(vim.api.nvim_list_wins()) do
+    wins[win] = true
+    if vim.wo[win].diff or sent_diff_bands[win] then
+      local bands = M.diff_bands(win)
+      if not vim.deep_equal(bands, sent_diff_bands[win] or {}) then
+        table.insert(changed, { win = win, bands = bands })
+      end
+      sent_diff_bands[win] = #bands > 0 and bands or nil
+    end
+  end
+
+  for win, _ in pairs(sent_diff_bands) do
+    if not wins[win] then
+      sent_diff_bands[win] = nil
+    end
+  end
+
+  if #changed > 0 then
+    M.notify('diff_bands', changed)
+  end
+end
+
+--- Tell gnvim which rows of the windows in diff mode are diff lines, so it
+--- can soften the edges of the diff highlights (see |gnvim-diff-bands|).
+---
+---@param enabled boolean|nil Defaults to true
+function M.track_diffs(enabled)
+  local group = vim.api.nvim_create_augroup('gnvim_diffs', {})
+  if enabled == false then
+    local cleared = {}
+    for win, _ in pairs(sent_diff_bands) do
+      table.insert(cleared, { win = win, bands = {} })
+    end
+    sent_diff_bands = {}
+    if #cleared > 0 then
+      M.notify('diff_bands', cleared)
+    end
+    return
+  end
+
+  local pending = false
+  local function schedule()
+    if pending then
+      return
+    end
+
+    pending = true
+    vim.schedule(function()
+      pending = false
+      refresh_diff_bands()
+    end)
+  end
+
+  vim.api.nvim_create_autocmd('OptionSet', {
+    group = group,
+    pattern = 'diff',
+    callback = schedule,
+  })
+  vim.api.nvim_create_autocmd({
+    'WinEnter',
+    'WinScrolled',
+    'BufWinEnter',
+    'DiffUpdated',
+    'TextChanged',
+    'TextChangedI',
+    'VimResized',
+  }, {
+    group = group,
+    callback = schedule,
+  })
+
+  schedule()
+end
+
+--- Record the latest messages from neovim, for `dump_events` (see
+--- |gnvim-event-log|). Turning the log off drops the recorded messages.
+---
+---@param enabled boolean
+function M.set_event_log(enabled)
+  M.notify('event_log', enabled)
+end
+
+--- Write the latest messages from neovim (i.e. the protocol traffic) to a
+--- file, for bug reports. The log has to be on (see `set_event_log`).
+---
+---@param path string File to write
+function M.dump_events(path)
+  local err = M.request('dump_events', vim.fn.expand(path))
+  if err ~= nil then
+    error(err)
+  end
+end
+
+--- Get the text of a grid, as currently rendered.
+---
+---@param grid_id Grid id (1 being the global grid)
+---@param start_row integer|nil First row (zero based), defaults to 0
+---@param end_row integer|nil Last row (exclusive), defaults to the grid's height
+---@return table|nil List of rows, or nil if there is no such grid
+function M.grid_text(grid_id, start_row, end_row)
+  return M.request('grid_text', {
+    grid = grid_id,
+    start = start_row,
+    ['end'] = end_row,
+  })
+end
+
 return M
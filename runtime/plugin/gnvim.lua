@@ -0,0 +1,7 @@
+require('gnvim').track_closed_windows()
+require('gnvim').track_ui_attach()
+require('gnvim').track_win_highlights()
+
+vim.api.nvim_create_user_command('GnvimExternalizeWin', function()
+  require('gnvim').externalize_win()
+end, { desc = 'Pop the floating window out into its own window' })
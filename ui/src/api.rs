@@ -1,5 +1,11 @@
+use std::collections::HashMap;
+
 use nvim::serde;
 
+use crate::components::toast::Severity;
+use crate::math::Easing;
+use crate::render::{Ambiwidth, EmojiPresentation};
+
 #[derive(Debug, serde::Deserialize)]
 #[serde(
     crate = "nvim::serde",
@@ -11,9 +17,98 @@ pub enum GnvimEvent {
     // NOTE(ville): Demo event.
     EchoRepeat(EchoRepeat),
     GtkDebugger,
+    /// Secure input mode, for editing secrets.
+    SecureInput(bool),
+    /// Detach and reattach the ui, rebuilding the ui state.
+    Reattach,
+    /// Text (pango markup) shown in the splash, e.g. while reattaching.
+    SplashText(String),
+    /// A gnvim ui was attached to nvim (`UIEnter`), with its channel.
+    UiAttached(i64),
+    /// A gnvim ui was detached from nvim (`UILeave`), with its channel.
+    UiDetached(i64),
     CursorBlinkTransition(f64),
     CursorPositionTransition(f64),
+    CursorPositionEasing(Easing),
     ScrollTransition(f64),
+    Ligatures(bool),
+    UndercurlAmplitude(f32),
+    EmojiPresentation(EmojiPresentation),
+    /// Draw a shadow and rounded corners for the floating windows.
+    FloatDecoration(bool),
+    /// Preview closed folds when hovered.
+    FoldPreview(bool),
+    /// Show a scrollbar in the windows.
+    Scrollbars(bool),
+    Ambiwidth(Ambiwidth),
+    /// Comma separated OpenType features, in addition to guifont's.
+    FontFeatures(String),
+    /// Family for bold text, empty for guifont's.
+    BoldFont(String),
+    /// Family for italic text, empty for guifont's.
+    ItalicFont(String),
+    PowerSaver(PowerSaver),
+    /// Render without caching and animations, for buggy graphics drivers.
+    SafeRendering(bool),
+    Bell(Bell),
+    /// Maximum flushes per second. Zero matches the monitor's refresh rate.
+    MaxFps(u32),
+    LargeFile(LargeFile),
+    OpenFind,
+    /// Save a copy of the current buffer to a file picked by the user.
+    ExportBuffer,
+    /// Show the about dialog.
+    About,
+    /// Mapping from gdk key names to nvim keys, replacing the previous one.
+    KeyRemap(HashMap<String, String>),
+    /// Mapping from mouse buttons (in nvim's notation, e.g. `X1Mouse` or
+    /// `C-MiddleMouse`) to nvim input, replacing the previous one.
+    MouseMap(HashMap<String, String>),
+    /// Notation for the Hyper modifier (e.g. `T` for `<T-...>`). Empty drops
+    /// the modifier, nil resets to the default.
+    HyperModifier(Option<String>),
+    /// Forward notifications with the given method to the runtime's handlers.
+    Subscribe(String),
+    Unsubscribe(String),
+    /// 'winhighlight' or the colorscheme changed.
+    RefreshWinHighlights,
+    /// Guide lines of the windows whose 'colorcolumn' (or something
+    /// affecting its placement) changed.
+    Guides(Vec<Guides>),
+    /// 'winblend' of the floating windows whose blend changed.
+    Blends(Vec<WinBlend>),
+    /// Show a start screen instead of closing when nvim exits cleanly.
+    KeepOpen(bool),
+    /// The files of a `--wait` invocation are closed.
+    WaitDone(u32),
+    /// Enable the cell inspector (hover a cell with ctrl+shift).
+    CellInspector(bool),
+    /// Show the highlight inspector window.
+    HlInspector,
+    /// Show or hide the message history panel.
+    ToggleMsgHistory,
+    /// Show the messages in gnvim's message area, instead of nvim's message
+    /// grid.
+    ExtMessages(bool),
+    Toast(ToastEvent),
+    /// How messages are shown as toasts, by message kind. Messages of other
+    /// kinds go to the message area.
+    MessageToasts(HashMap<String, MessageToast>),
+    /// Show the error messages as desktop notifications when unfocused.
+    DesktopNotifications(bool),
+    /// Check for new gnvim releases periodically.
+    UpdateCheck(bool),
+    /// The latest gnvim release, as fetched by the runtime.
+    LatestRelease(Release),
+    FocusMode(FocusMode),
+    /// Accent colors of the tabpages, replacing the previous ones.
+    TabAccents(Vec<TabAccent>),
+    /// Show or hide the rpc call statistics on top of the shell.
+    DebugHud(bool),
+    /// Diff bands of the windows whose diff lines changed.
+    DiffBands(Vec<DiffBands>),
+    /// Record the messages from nvim for `dump_events`.
+    EventLog(bool),
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -22,3 +117,183 @@ pub struct EchoRepeat {
     pub msg: String,
     pub times: usize,
 }
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct LargeFile {
+    /// The window displaying the large file.
+    pub win: i64,
+    /// Size of the file, in bytes.
+    pub size: i64,
+    /// False when the window switched from a large file to a smaller one.
+    #[serde(default = "default_true")]
+    pub large: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct MessageToast {
+    #[serde(default)]
+    pub severity: Severity,
+    /// Timeout in milliseconds. Nil keeps the toast until it's dismissed.
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct DiffBands {
+    pub win: i64,
+    /// Continuous rows sharing a diff highlight, as `(start, end)` (end
+    /// exclusive). Empty when the window isn't in diff mode.
+    pub bands: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct WinBlend {
+    pub win: i64,
+    /// The window's 'winblend', from 0 (opaque) to 100.
+    pub blend: u8,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct Guides {
+    pub win: i64,
+    /// Grid columns of the guide lines. Empty when the window has none.
+    pub cols: Vec<usize>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct ToastEvent {
+    pub text: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Timeout in milliseconds. Nil keeps the toast until it's dismissed.
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub actions: Vec<ToastEventAction>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct ToastEventAction {
+    pub label: String,
+    /// Id of the callback in the runtime.
+    pub id: i64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct Release {
+    /// The release's tag, e.g. `v0.4.0`.
+    pub tag: String,
+    /// The release's web page.
+    pub url: String,
+    /// The release notes.
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct FocusMode {
+    /// Nil toggles the focus mode.
+    pub enabled: Option<bool>,
+    /// Maximum text width, in columns.
+    pub width: Option<u32>,
+    /// Dim the windows other than the current one.
+    pub dim: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct TabAccent {
+    /// The tabpage's handle.
+    pub tab: i64,
+    /// The accent color, e.g. `#ff8800`.
+    pub color: String,
+}
+
+/// Preview of a closed fold, from the runtime's `fold_preview`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct FoldPreview {
+    /// First line of the fold.
+    pub lnum: i64,
+    /// The folded lines, as highlighted chunks.
+    pub lines: Vec<Vec<FoldPreviewChunk>>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct FoldPreviewChunk {
+    pub text: String,
+    /// Foreground color, if other than the default.
+    pub fg: Option<i64>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(crate = "nvim::serde")]
+pub struct GridText {
+    /// The grid's id.
+    pub grid: i64,
+    /// First row, inclusive. Defaults to the first row.
+    pub start: Option<usize>,
+    /// Last row, exclusive. Defaults to the grid's height.
+    pub end: Option<usize>,
+}
+
+/// Power saver mode.
+#[derive(Debug, Default, Clone, Copy, serde::Deserialize)]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum PowerSaver {
+    /// Follow the system's power profile.
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// How the bell is rung.
+#[derive(Debug, Default, Clone, Copy, serde::Deserialize)]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum Bell {
+    /// Flash with 'visualbell', beep otherwise.
+    #[default]
+    Auto,
+    Audible,
+    Visual,
+    Off,
+}
+
+/// Requests from nvim (e.g. `rpcrequest`) that expect a response.
+#[derive(Debug, serde::Deserialize)]
+#[serde(
+    crate = "nvim::serde",
+    rename_all = "snake_case",
+    tag = "fn",
+    content = "args"
+)]
+pub enum GnvimRequest {
+    Stats,
+    /// Render statistics of a grid, by grid id.
+    GridStats(i64),
+    /// Rendered text of a grid's rows.
+    GridText(GridText),
+    /// Write the latest messages from nvim to a file, by path. Returns an
+    /// error message on failure.
+    DumpEvents(String),
+    /// Ask the user whether to trust a project-local config (see 'exrc'), by
+    /// path. Returns "allow", "deny" or "ignore".
+    TrustPrompt(String),
+}
@@ -1,9 +1,56 @@
-use std::{ffi::OsString, io::IsTerminal};
+use std::{ffi::OsString, io::IsTerminal, path::PathBuf};
 
 use gtk::glib;
 
+use crate::file_arg::parse_files;
+use crate::slow_link::SlowLink;
+
+/// Where files opened from the desktop (e.g. with xdg-open) are opened.
+#[derive(clap::ValueEnum, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenIn {
+    /// New tab.
+    #[default]
+    Tab,
+    /// New split.
+    Split,
+    /// Current window.
+    Current,
+    /// New gnvim window.
+    Window,
+}
+
+impl OpenIn {
+    /// The ex command for opening a file, if the file is opened in the
+    /// current nvim.
+    pub fn ex_cmd(&self) -> Option<&'static str> {
+        match self {
+            OpenIn::Tab => Some("tabedit"),
+            OpenIn::Split => Some("split"),
+            OpenIn::Current => Some("edit"),
+            OpenIn::Window => None,
+        }
+    }
+}
+
+/// The gdk backend (i.e. windowing system).
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    X11,
+    Wayland,
+}
+
+impl Backend {
+    /// Name of the backend, as for `gdk::set_allowed_backends`.
+    pub fn gdk_backend(&self) -> &'static str {
+        match self {
+            Backend::X11 => "x11",
+            Backend::Wayland => "wayland",
+        }
+    }
+}
+
 #[derive(clap::Parser, Default, Debug, Clone, glib::Boxed)]
-#[clap(author, version)]
+#[clap(author, version = crate::VERSION)]
 #[boxed_type(name = "Arguments")]
 pub struct Arguments {
     /// Neovim binary.
@@ -19,7 +66,8 @@ pub struct Arguments {
     )]
     pub rtp: String,
 
-    /// Files to open.
+    /// Files to open. `+N` before a file, or a `:line[:col]` suffix sets the
+    /// cursor position (only for the first file).
     #[clap(name = "FILES")]
     pub files: Vec<OsString>,
 
@@ -27,6 +75,99 @@ pub struct Arguments {
     #[clap(name = "ARGS", last = true)]
     pub nvim_args: Vec<OsString>,
 
+    /// Run neovim through a login shell (`$SHELL -lc`), so the environment
+    /// (e.g. PATH) is the same as in a terminal when gnvim is launched from
+    /// a desktop icon.
+    #[clap(long)]
+    pub login_shell: bool,
+
+    /// Open the files in an already running gnvim (one started with this
+    /// flag), instead of starting a new one.
+    #[clap(long)]
+    pub single_instance: bool,
+
+    /// Open a small window with a scratch buffer for quick notes, e.g. from
+    /// a global hotkey. With a file, the notes are saved to it.
+    #[clap(long, name = "NOTES", conflicts_with_all = &["FILES", "single-instance"])]
+    pub scratch: Option<Option<PathBuf>>,
+
+    /// Wait until the files are closed before exiting, e.g. for $GIT_EDITOR.
+    /// With --single-instance, only the files opened in the running gnvim
+    /// are waited for. Also set by GNVIM_EDITOR=1.
+    #[clap(long)]
+    pub wait: bool,
+
+    /// Attach to a neovim listening on a TCP address (`HOST:PORT`, e.g. one
+    /// started with `nvim --headless --listen 127.0.0.1:6666` on another
+    /// machine, with the port forwarded by `ssh -L`), instead of running
+    /// nvim.
+    #[clap(
+        long,
+        name = "ADDR",
+        conflicts_with_all = &["FILES", "ARGS", "login-shell", "NOTES", "STREAM"]
+    )]
+    pub connect: Option<String>,
+
+    /// Name of this gnvim, for `--remote-send` and `--remote-expr`. If a
+    /// running gnvim already has the name, this one doesn't get it.
+    #[clap(long, name = "NAME", default_value = "gnvim")]
+    pub servername: String,
+
+    /// Send keys (as with `nvim_input`) to the gnvim named by `--servername`,
+    /// and exit.
+    #[clap(long, name = "KEYS", conflicts_with = "EXPR")]
+    pub remote_send: Option<String>,
+
+    /// Evaluate an expression in the gnvim named by `--servername`, print
+    /// the result, and exit.
+    #[clap(long, name = "EXPR")]
+    pub remote_expr: Option<String>,
+
+    /// Where files are opened when gnvim is already running.
+    #[clap(long, value_enum, name = "WHERE", default_value = "tab")]
+    pub open_in: OpenIn,
+
+    /// Use the simplest rendering path: the cairo renderer, and no caching
+    /// or animations. For graphics drivers that cause rendering artifacts.
+    #[clap(long)]
+    pub safe_rendering: bool,
+
+    /// Windowing system to use, instead of gtk's choice. For compositors
+    /// that misbehave with one of them. Takes precedence over GDK_BACKEND,
+    /// which is still passed on to neovim.
+    #[clap(long, value_enum, name = "BACKEND")]
+    pub backend: Option<Backend>,
+
+    /// Background color (e.g. `#1e1e2e`) painted until neovim has set its
+    /// colors. Defaults to the background of the previous session.
+    #[clap(long, name = "COLOR")]
+    pub background: Option<String>,
+
+    /// Text (pango markup) shown while waiting for neovim to start.
+    #[clap(long, name = "MARKUP")]
+    pub splash: Option<String>,
+
+    /// Replay a recorded nvim msgpack-rpc stream (i.e. the output of
+    /// `nvim --embed`) instead of running nvim. Used for visual regression
+    /// tests.
+    #[clap(long, name = "STREAM", hide = true)]
+    pub replay: Option<PathBuf>,
+
+    /// Write the last frame of the replayed stream to this PNG file, and
+    /// exit.
+    #[clap(long, name = "PNG", hide = true, requires = "STREAM")]
+    pub screenshot: Option<PathBuf>,
+
+    /// Font used for the replay, instead of the stream's guifont.
+    #[clap(long, name = "FONT", hide = true, default_value = "Monospace 12")]
+    pub replay_font: String,
+
+    /// Simulate a slow connection to neovim: `LATENCY[:JITTER[:BANDWIDTH]]`,
+    /// with the latencies in milliseconds and the bandwidth (from neovim) in
+    /// kilobytes per second. For testing the behavior with remote neovims.
+    #[clap(long, name = "LINK", hide = true, value_parser = SlowLink::parse)]
+    pub slow_link: Option<SlowLink>,
+
     #[clap(skip)]
     pub stdin_fd: Option<i32>,
 }
@@ -38,10 +179,43 @@ impl Arguments {
             OsString::from("--embed"),
             OsString::from("--cmd"),
             OsString::from(format!("let &rtp.=',{}'", self.rtp)),
+            // Before the configs are sourced, so it covers 'exrc'.
+            OsString::from("--cmd"),
+            OsString::from("lua require('gnvim').setup_trust_prompt()"),
         ];
 
         args.extend_from_slice(&self.nvim_args);
-        args.extend_from_slice(&self.files);
+
+        if let Some(ref notes) = self.scratch {
+            args.push(OsString::from("-c"));
+            args.push(OsString::from(format!(
+                "lua require('gnvim').setup_scratch({})",
+                notes.is_some()
+            )));
+            args.extend(notes.iter().map(|notes| notes.clone().into_os_string()));
+        } else {
+            // Nvim's `+cmd` only applies to the first file.
+            let files = parse_files(&self.files);
+            if let Some(cmd) = files.first().and_then(|file| file.cursor_cmd()) {
+                args.push(cmd);
+            }
+            args.extend(files.into_iter().map(|file| file.path));
+        }
+
+        if self.login_shell {
+            let shell = std::env::var_os("SHELL").unwrap_or_else(|| OsString::from("/bin/sh"));
+            let cmd = args
+                .iter()
+                .map(|arg| shell_quote(&arg.to_string_lossy()))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            return vec![
+                shell,
+                OsString::from("-lc"),
+                OsString::from(format!("exec {}", cmd)),
+            ];
+        }
 
         args
     }
@@ -50,6 +224,10 @@ impl Arguments {
     pub fn parse() -> Self {
         let mut args: Self = clap::Parser::parse();
 
+        if std::env::var_os("GNVIM_EDITOR").map_or(false, |v| v == "1") {
+            args.wait = true;
+        }
+
         if !std::io::stdin().is_terminal() {
             args.stdin_fd = dup_stdin();
         }
@@ -58,6 +236,11 @@ impl Arguments {
     }
 }
 
+/// Single quotes `arg` for a shell. Works with posix shells and fish.
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
 fn dup_stdin() -> Option<i32> {
     cfg_if::cfg_if! {
         if #[cfg(unix)] {
@@ -93,3 +276,38 @@ fn dup_stdin() -> Option<i32> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use clap::CommandFactory;
+
+    use super::Arguments;
+
+    #[test]
+    fn test_command() {
+        Arguments::command().debug_assert();
+    }
+
+    #[test]
+    fn test_login_shell_args() {
+        let args = Arguments {
+            nvim: OsString::from("nvim"),
+            rtp: String::from("/rtp"),
+            files: vec![OsString::from("it's.txt")],
+            login_shell: true,
+            ..Default::default()
+        };
+
+        let args = args.nvim_cmd_args();
+        assert_eq!(&args[1], "-lc");
+        assert_eq!(
+            &args[2],
+            concat!(
+                r"exec 'nvim' '--embed' '--cmd' 'let &rtp.='\'',/rtp'\''' ",
+                r"'--cmd' 'lua require('\''gnvim'\'').setup_trust_prompt()' 'it'\''s.txt'"
+            )
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Custom rendering for box drawing characters (U+2500–U+257F) and block
+//! elements (U+2580–U+259F). Glyphs from fonts rarely fill the whole cell,
+//! which leaves gaps between cells (e.g. in window separators). Drawing the
+//! characters ourselves makes them connect seamlessly.
+
+use gtk::{cairo, gdk, graphene, gsk, prelude::*};
+
+use crate::colors::Color;
+use crate::font::Font;
+use crate::SCALE;
+
+/// Line weight of a box drawing character's arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    None,
+    Light,
+    Heavy,
+    Double,
+}
+
+/// Arms (up, right, down, left) of the line drawing characters.
+fn lines(c: char) -> Option<[Line; 4]> {
+    use Line::{Double as D, Heavy as H, Light as L, None as N};
+
+    let lines = match c {
+        '─' => [N, L, N, L],
+        '━' => [N, H, N, H],
+        '│' => [L, N, L, N],
+        '┃' => [H, N, H, N],
+        '┌' => [N, L, L, N],
+        '┍' => [N, H, L, N],
+        '┎' => [N, L, H, N],
+        '┏' => [N, H, H, N],
+        '┐' => [N, N, L, L],
+        '┑' => [N, N, L, H],
+        '┒' => [N, N, H, L],
+        '┓' => [N, N, H, H],
+        '└' => [L, L, N, N],
+        '┕' => [L, H, N, N],
+        '┖' => [H, L, N, N],
+        '┗' => [H, H, N, N],
+        '┘' => [L, N, N, L],
+        '┙' => [L, N, N, H],
+        '┚' => [H, N, N, L],
+        '┛' => [H, N, N, H],
+        '├' => [L, L, L, N],
+        '┝' => [L, H, L, N],
+        '┞' => [H, L, L, N],
+        '┟' => [L, L, H, N],
+        '┠' => [H, L, H, N],
+        '┡' => [H, H, L, N],
+        '┢' => [L, H, H, N],
+        '┣' => [H, H, H, N],
+        '┤' => [L, N, L, L],
+        '┥' => [L, N, L, H],
+        '┦' => [H, N, L, L],
+        '┧' => [L, N, H, L],
+        '┨' => [H, N, H, L],
+        '┩' => [H, N, L, H],
+        '┪' => [L, N, H, H],
+        '┫' => [H, N, H, H],
+        '┬' => [N, L, L, L],
+        '┭' => [N, L, L, H],
+        '┮' => [N, H, L, L],
+        '┯' => [N, H, L, H],
+        '┰' => [N, L, H, L],
+        '┱' => [N, L, H, H],
+        '┲' => [N, H, H, L],
+        '┳' => [N, H, H, H],
+        '┴' => [L, L, N, L],
+        '┵' => [L, L, N, H],
+        '┶' => [L, H, N, L],
+        '┷' => [L, H, N, H],
+        '┸' => [H, L, N, L],
+        '┹' => [H, L, N, H],
+        '┺' => [H, H, N, L],
+        '┻' => [H, H, N, H],
+        '┼' => [L, L, L, L],
+        '┽' => [L, L, L, H],
+        '┾' => [L, H, L, L],
+        '┿' => [L, H, L, H],
+        '╀' => [H, L, L, L],
+        '╁' => [L, L, H, L],
+        '╂' => [H, L, H, L],
+        '╃' => [H, L, L, H],
+        '╄' => [H, H, L, L],
+        '╅' => [L, L, H, H],
+        '╆' => [L, H, H, L],
+        '╇' => [H, H, L, H],
+        '╈' => [L, H, H, H],
+        '╉' => [H, L, H, H],
+        '╊' => [H, H, H, L],
+        '╋' => [H, H, H, H],
+        '═' => [N, D, N, D],
+        '║' => [D, N, D, N],
+        '╒' => [N, D, L, N],
+        '╓' => [N, L, D, N],
+        '╔' => [N, D, D, N],
+        '╕' => [N, N, L, D],
+        '╖' => [N, N, D, L],
+        '╗' => [N, N, D, D],
+        '╘' => [L, D, N, N],
+        '╙' => [D, L, N, N],
+        '╚' => [D, D, N, N],
+        '╛' => [L, N, N, D],
+        '╜' => [D, N, N, L],
+        '╝' => [D, N, N, D],
+        '╞' => [L, D, L, N],
+        '╟' => [D, L, D, N],
+        '╠' => [D, D, D, N],
+        '╡' => [L, N, L, D],
+        '╢' => [D, N, D, L],
+        '╣' => [D, N, D, D],
+        '╤' => [N, D, L, D],
+        '╥' => [N, L, D, L],
+        '╦' => [N, D, D, D],
+        '╧' => [L, D, N, D],
+        '╨' => [D, L, N, L],
+        '╩' => [D, D, N, D],
+        '╪' => [L, D, L, D],
+        '╫' => [D, L, D, L],
+        '╬' => [D, D, D, D],
+        '╴' => [N, N, N, L],
+        '╵' => [L, N, N, N],
+        '╶' => [N, L, N, N],
+        '╷' => [N, N, L, N],
+        '╸' => [N, N, N, H],
+        '╹' => [H, N, N, N],
+        '╺' => [N, H, N, N],
+        '╻' => [N, N, H, N],
+        '╼' => [N, H, N, L],
+        '╽' => [L, N, H, N],
+        '╾' => [N, L, N, H],
+        '╿' => [H, N, L, N],
+        _ => return None,
+    };
+
+    Some(lines)
+}
+
+/// Renders `text` if it's a box drawing character or a block element.
+/// Returns `None` for other text (and for the few box drawing characters we
+/// leave for the font, e.g. the dashed lines).
+///
+/// The node covers the cell starting at `x`.
+pub fn render(text: &str, color: &Color, font: &Font, x: f32) -> Option<gsk::RenderNode> {
+    let mut chars = text.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+
+    let cell = graphene::Rect::new(x, 0.0, font.char_width() / SCALE, font.height() / SCALE);
+    let thickness = (font.underline_thickness() / SCALE).round().max(1.0);
+
+    if let Some(lines) = lines(c) {
+        return Some(render_lines(lines, color, &cell, thickness));
+    }
+
+    match c {
+        '╭' | '╮' | '╯' | '╰' => Some(render_rounded(c, color, &cell, thickness)),
+        '╱' | '╲' | '╳' => Some(render_diagonal(c, color, &cell, thickness)),
+        '\u{2580}'..='\u{259F}' => render_block(c, color, &cell),
+        _ => None,
+    }
+}
+
+fn rect(color: &gdk::RGBA, x: f32, y: f32, width: f32, height: f32) -> gsk::RenderNode {
+    gsk::ColorNode::new(color, &graphene::Rect::new(x, y, width, height)).upcast()
+}
+
+fn render_lines(
+    lines: [Line; 4],
+    color: &Color,
+    cell: &graphene::Rect,
+    thickness: f32,
+) -> gsk::RenderNode {
+    let [up, right, down, left] = lines;
+
+    let weight = |line: Line| match line {
+        Line::Heavy => thickness * 2.0,
+        _ => thickness,
+    };
+    // Offsets of the parallel lines from the center line.
+    let offsets = |line: Line| match line {
+        Line::None => vec![],
+        Line::Double => vec![-thickness, thickness],
+        _ => vec![0.0],
+    };
+
+    // Snap the center lines to pixels, so the lines are crisp.
+    let cx = (cell.x() + cell.width() / 2.0).round();
+    let cy = (cell.height() / 2.0).round();
+    let (x0, x1) = (cell.x(), cell.x() + cell.width());
+    let (y0, y1) = (cell.y(), cell.y() + cell.height());
+
+    // Horizontal arms extend over the vertical lines (and vice versa), so
+    // the arms join without gaps.
+    let reach_v = [up, down].into_iter().map(weight).fold(0.0, f32::max) / 2.0 + thickness;
+    let reach_h = [left, right].into_iter().map(weight).fold(0.0, f32::max) / 2.0 + thickness;
+
+    let mut nodes = vec![];
+    for offset in offsets(left) {
+        let t = weight(left);
+        nodes.push(rect(color, x0, cy + offset - t / 2.0, cx + reach_v - x0, t));
+    }
+    for offset in offsets(right) {
+        let t = weight(right);
+        let start = cx - reach_v;
+        nodes.push(rect(color, start, cy + offset - t / 2.0, x1 - start, t));
+    }
+    for offset in offsets(up) {
+        let t = weight(up);
+        nodes.push(rect(color, cx + offset - t / 2.0, y0, t, cy + reach_h - y0));
+    }
+    for offset in offsets(down) {
+        let t = weight(down);
+        let start = cy - reach_h;
+        nodes.push(rect(color, cx + offset - t / 2.0, start, t, y1 - start));
+    }
+
+    gsk::ClipNode::new(&gsk::ContainerNode::new(&nodes), cell).upcast()
+}
+
+fn cairo_node(
+    color: &Color,
+    cell: &graphene::Rect,
+    thickness: f32,
+    f: impl FnOnce(&cairo::Context),
+) -> gsk::RenderNode {
+    let node = gsk::CairoNode::new(cell);
+    let ctx = node.draw_context();
+
+    ctx.set_line_width(thickness as f64);
+    ctx.set_source_rgba(
+        color.red() as f64,
+        color.green() as f64,
+        color.blue() as f64,
+        color.alpha() as f64,
+    );
+    f(&ctx);
+    ctx.stroke().expect("failed to draw with cairo");
+
+    node.upcast()
+}
+
+fn render_rounded(
+    c: char,
+    color: &Color,
+    cell: &graphene::Rect,
+    thickness: f32,
+) -> gsk::RenderNode {
+    let cx = (cell.x() + cell.width() / 2.0).round() as f64;
+    let cy = (cell.height() / 2.0).round() as f64;
+    let (x0, x1) = (cell.x() as f64, (cell.x() + cell.width()) as f64);
+    let (y0, y1) = (cell.y() as f64, (cell.y() + cell.height()) as f64);
+
+    // The ends of the horizontal and vertical arms.
+    let (hx, vy) = match c {
+        '╭' => (x1, y1),
+        '╮' => (x0, y1),
+        '╯' => (x0, y0),
+        _ => (x1, y0),
+    };
+
+    cairo_node(color, cell, thickness, |ctx| {
+        let r = (cell.width() / 2.0) as f64;
+        let sx = (hx - cx).signum();
+        let sy = (vy - cy).signum();
+
+        ctx.move_to(hx, cy);
+        ctx.line_to(cx + r * sx, cy);
+        ctx.curve_to(cx, cy, cx, cy, cx, cy + r * sy);
+        ctx.line_to(cx, vy);
+    })
+}
+
+fn render_diagonal(
+    c: char,
+    color: &Color,
+    cell: &graphene::Rect,
+    thickness: f32,
+) -> gsk::RenderNode {
+    let (x0, x1) = (cell.x() as f64, (cell.x() + cell.width()) as f64);
+    let (y0, y1) = (cell.y() as f64, (cell.y() + cell.height()) as f64);
+
+    cairo_node(color, cell, thickness, |ctx| {
+        if c != '╲' {
+            ctx.move_to(x0, y1);
+            ctx.line_to(x1, y0);
+        }
+        if c != '╱' {
+            ctx.move_to(x0, y0);
+            ctx.line_to(x1, y1);
+        }
+    })
+}
+
+fn render_block(c: char, color: &Color, cell: &graphene::Rect) -> Option<gsk::RenderNode> {
+    let (x, w, h) = (cell.x(), cell.width(), cell.height());
+
+    let nodes = match c {
+        // Upper half.
+        '\u{2580}' => vec![rect(color, x, 0.0, w, h / 2.0)],
+        // Lower eighths, from one eighth to the full block.
+        '\u{2581}'..='\u{2588}' => {
+            let n = (c as u32 - 0x2580) as f32;
+            vec![rect(color, x, h * (1.0 - n / 8.0), w, h * n / 8.0)]
+        }
+        // Left eighths, from seven eighths to one.
+        '\u{2589}'..='\u{258F}' => {
+            let n = (0x2590 - c as u32) as f32;
+            vec![rect(color, x, 0.0, w * n / 8.0, h)]
+        }
+        // Right half.
+        '\u{2590}' => vec![rect(color, x + w / 2.0, 0.0, w / 2.0, h)],
+        // Shades.
+        '\u{2591}'..='\u{2593}' => {
+            let alpha = (c as u32 - 0x2590) as f32 * 0.25;
+            let color = gdk::RGBA::new(
+                color.red(),
+                color.green(),
+                color.blue(),
+                color.alpha() * alpha,
+            );
+            vec![rect(&color, x, 0.0, w, h)]
+        }
+        // Upper eighth.
+        '\u{2594}' => vec![rect(color, x, 0.0, w, h / 8.0)],
+        // Right eighth.
+        '\u{2595}' => vec![rect(color, x + w * 7.0 / 8.0, 0.0, w / 8.0, h)],
+        // Quadrants.
+        '\u{2596}'..='\u{259F}' => {
+            const UL: u8 = 1;
+            const UR: u8 = 2;
+            const LL: u8 = 4;
+            const LR: u8 = 8;
+
+            let quadrants = match c {
+                '▖' => LL,
+                '▗' => LR,
+                '▘' => UL,
+                '▙' => UL | LL | LR,
+                '▚' => UL | LR,
+                '▛' => UL | UR | LL,
+                '▜' => UL | UR | LR,
+                '▝' => UR,
+                '▞' => UR | LL,
+                _ => UR | LL | LR,
+            };
+
+            [
+                (UL, 0.0, 0.0),
+                (UR, w / 2.0, 0.0),
+                (LL, 0.0, h / 2.0),
+                (LR, w / 2.0, h / 2.0),
+            ]
+            .into_iter()
+            .filter(|(q, _, _)| quadrants & q != 0)
+            .map(|(_, qx, qy)| rect(color, x + qx, qy, w / 2.0, h / 2.0))
+            .collect()
+        }
+        _ => return None,
+    };
+
+    Some(gsk::ContainerNode::new(&nodes).upcast())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        use Line::{Double as D, Heavy as H, Light as L, None as N};
+
+        assert_eq!(lines('┼'), Some([L, L, L, L]));
+        assert_eq!(lines('┏'), Some([N, H, H, N]));
+        assert_eq!(lines('╡'), Some([L, N, L, D]));
+        assert_eq!(lines('╼'), Some([N, H, N, L]));
+        // Dashed lines are left for the font.
+        assert_eq!(lines('┄'), None);
+        assert_eq!(lines('a'), None);
+    }
+}
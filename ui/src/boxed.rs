@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::ops::Deref;
+use std::rc::Rc;
 
 #[derive(Debug, Default, Clone, glib::Boxed)]
 #[boxed_type(name = "ModeInfo")]
@@ -53,3 +55,22 @@ impl From<nvim::types::Tabpage> for Tabpage {
         Self(s)
     }
 }
+
+/// User's mapping from mouse buttons (e.g. `C-X1Mouse`) to nvim input.
+#[derive(Debug, Default, Clone, glib::Boxed)]
+#[boxed_type(name = "MouseMap")]
+pub struct MouseMap(pub Rc<HashMap<String, String>>);
+
+impl Deref for MouseMap {
+    type Target = HashMap<String, String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<HashMap<String, String>> for MouseMap {
+    fn from(map: HashMap<String, String>) -> Self {
+        Self(Rc::new(map))
+    }
+}
@@ -9,10 +9,18 @@ pub enum HlGroup {
     PmenuSel,
     PmenuSbar,
     PmenuThumb,
+    PmenuKind,
+    PmenuKindSel,
+    PmenuExtra,
+    PmenuExtraSel,
     TabLine,
     TabLineFill,
     TabLineSel,
     Menu,
+    WarningMsg,
+    ErrorMsg,
+    ColorColumn,
+    WinSeparator,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -23,6 +31,8 @@ pub struct Colors {
 
     pub hls: HashMap<i64, HlAttr>,
     pub hl_groups: HashMap<HlGroup, i64>,
+    /// All the highlight groups from `hl_group_set`, by name.
+    pub hl_group_names: HashMap<String, i64>,
 }
 
 impl Colors {
@@ -47,6 +57,16 @@ impl Colors {
             hl_attr,
         }
     }
+
+    /// Like `get_hl_group`, but uses `fallback` if `group` isn't set (e.g.
+    /// the group doesn't exist in older nvim versions).
+    pub fn get_hl_group_or<'a>(&'a self, group: &HlGroup, fallback: &HlGroup) -> Highlight<'a> {
+        if self.hl_groups.contains_key(group) {
+            self.get_hl_group(group)
+        } else {
+            self.get_hl_group(fallback)
+        }
+    }
 }
 
 pub struct Highlight<'a> {
@@ -89,6 +109,10 @@ impl<'a> Highlight<'a> {
         self.hl_attr
     }
 
+    pub fn url(&self) -> Option<&str> {
+        self.hl_attr.and_then(|hl| hl.url.as_deref())
+    }
+
     pub fn pango_markup(&self, text: &str) -> String {
         let weight = if self.hl_attr.and_then(|hl| hl.bold).unwrap_or(false) {
             "bold"
@@ -97,7 +121,8 @@ impl<'a> Highlight<'a> {
         };
         let underline = if self.hl_attr.and_then(|hl| hl.undercurl).unwrap_or(false) {
             "error"
-        } else if self.hl_attr.and_then(|hl| hl.underline).unwrap_or(false) {
+        } else if self.hl_attr.and_then(|hl| hl.underline).unwrap_or(false) || self.url().is_some()
+        {
             "single"
         } else if self
             .hl_attr
@@ -105,6 +130,13 @@ impl<'a> Highlight<'a> {
             .unwrap_or(false)
         {
             "double"
+        } else if self
+            .hl_attr
+            .map(|hl| hl.underdot.unwrap_or(false) || hl.underdash.unwrap_or(false))
+            .unwrap_or(false)
+        {
+            // Pango markup doesn't have dotted or dashed underlines.
+            "single"
         } else {
             "none"
         };
@@ -144,7 +176,7 @@ impl<'a> Highlight<'a> {
 }
 
 /// Mapping from `nvim::HlAttr` that has the color fields converted to `Color`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct HlAttr {
     pub foreground: Option<Color>,
     pub background: Option<Color>,
@@ -159,6 +191,29 @@ pub struct HlAttr {
     pub underdot: Option<bool>,
     pub underdash: Option<bool>,
     pub blend: Option<Color>,
+    /// Url attached to the highlight (e.g. through `nvim_buf_set_extmark`).
+    pub url: Option<String>,
+}
+
+impl HlAttr {
+    /// Names of the style flags that are set.
+    pub fn styles(&self) -> Vec<&'static str> {
+        [
+            ("reverse", self.reverse),
+            ("italic", self.italic),
+            ("bold", self.bold),
+            ("strikethrough", self.strikethrough),
+            ("underline", self.underline),
+            ("underlineline", self.underlineline),
+            ("undercurl", self.undercurl),
+            ("underdot", self.underdot),
+            ("underdash", self.underdash),
+        ]
+        .into_iter()
+        .filter(|(_, set)| set.unwrap_or(false))
+        .map(|(name, _)| name)
+        .collect()
+    }
 }
 
 impl From<nvim::types::HlAttr> for HlAttr {
@@ -177,6 +232,7 @@ impl From<nvim::types::HlAttr> for HlAttr {
             underdot: from.underdotted,
             underdash: from.underdashed,
             blend: from.blend.map(From::from),
+            url: from.url,
         }
     }
 }
@@ -216,6 +272,12 @@ impl From<i64> for Color {
     }
 }
 
+impl From<gdk::RGBA> for Color {
+    fn from(from: gdk::RGBA) -> Self {
+        Self(from)
+    }
+}
+
 impl Deref for Color {
     type Target = gdk::RGBA;
 
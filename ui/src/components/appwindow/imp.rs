@@ -1,10 +1,19 @@
 use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use futures::StreamExt;
 
 use nvim::dict;
+use nvim::into_value::MapBuilder;
 use nvim::rpc::message::Message;
 use nvim::serde::Deserialize;
-use nvim::types::uievents::{DefaultColorsSet, HlGroupSet, PopupmenuSelect, PopupmenuShow};
+use nvim::types::uievents::{
+    DefaultColorsSet, GridCursorGoto, HlGroupSet, MsgShow, PopupmenuSelect, PopupmenuShow,
+};
 use nvim::types::UiEvent;
 use nvim::types::{OptionSet, UiOptions};
 use nvim::NeovimApi;
@@ -14,20 +23,61 @@ use gtk::prelude::*;
 use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 use gtk::{
-    gdk,
+    gdk, gio,
     glib::{self, clone},
 };
 
-use nvim::rpc::{message::Notification, RpcReader};
+use nvim::rpc::{
+    message::{Notification, Request},
+    RpcReader,
+};
 
-use crate::api::GnvimEvent;
-use crate::boxed::{ModeInfo, ShowTabline};
+use crate::api::{
+    Bell, FocusMode, GnvimEvent, GnvimRequest, LargeFile, MessageToast, PowerSaver, Release,
+    TabAccent, ToastEvent,
+};
+use crate::boxed::{ModeInfo, MouseMap, ShowTabline};
 use crate::colors::{Color, Colors, HlGroup};
-use crate::components::{Omnibar, Overflower, Shell, Tabline};
-use crate::font::Font;
+use crate::components::toast::{Severity, ToastAction};
+use crate::components::{
+    FindBar, HlInspector, MsgArea, MsgHistory, MsgStatus, Omnibar, Overflower, Shell, Tabline,
+    Toast,
+};
+use crate::event_log::{self, EventLog};
+use crate::file_arg::FileArg;
+use crate::font::{self, Font};
 use crate::nvim::Neovim;
-use crate::warn;
+use crate::render::Ambiwidth;
+use crate::renderer_info::RendererInfo;
+use crate::slow_link::SlowReader;
+use crate::updater;
 use crate::{arguments::Arguments, spawn_local, SCALE};
+use crate::{debug, warn};
+
+/// Smallest font size (in points) allowed when changing the size from gnvim.
+const MIN_FONT_SIZE: f32 = 4.0;
+/// Largest font size (in points) allowed when changing the size from gnvim.
+const MAX_FONT_SIZE: f32 = 72.0;
+/// Window title when nvim doesn't set one.
+const DEFAULT_TITLE: &str = "Gnvim";
+/// Window icon when nvim doesn't set one (from the icon theme).
+const DEFAULT_ICON: &str = "gnvim";
+/// Maximum length of the payload summary in the unexpected notification logs.
+const PAYLOAD_SUMMARY_LEN: usize = 120;
+/// Maximum number of message toasts shown at once.
+const MAX_MESSAGE_TOASTS: usize = 5;
+/// Message kinds (see `:h ui-messages`) shown as desktop notifications.
+const ERROR_MESSAGE_KINDS: &[&str] = &["emsg", "echoerr", "lua_error", "rpc_error"];
+/// Number of release notes' lines shown in the update toast.
+const RELEASE_NOTES_LINES: usize = 6;
+/// Text width in focus mode (in columns), unless set by the user.
+const DEFAULT_FOCUS_WIDTH: u32 = 100;
+/// How often the debug HUD is refreshed.
+const DEBUG_HUD_INTERVAL: Duration = Duration::from_secs(1);
+/// Number of rpc methods (with the most calls) shown in the debug HUD.
+const DEBUG_HUD_METHODS: usize = 10;
+/// Number of lines fetched from nvim at a time when exporting a buffer.
+const EXPORT_CHUNK_LINES: i64 = 5000;
 
 #[derive(CompositeTemplate, Default, glib::Properties)]
 #[properties(wrapper_type = super::AppWindow)]
@@ -39,10 +89,101 @@ pub struct AppWindow {
     event_controller_key: RefCell<gtk::EventControllerKey>,
     #[template_child(id = "shell")]
     shell: TemplateChild<Shell>,
+    #[template_child(id = "headerbar")]
+    headerbar: TemplateChild<gtk::HeaderBar>,
     #[template_child(id = "tabline")]
     tabline: TemplateChild<Tabline>,
+    /// Hides the tabline in focus mode.
+    #[template_child(id = "tabline-revealer")]
+    tabline_revealer: TemplateChild<gtk::Revealer>,
     #[template_child(id = "omnibar")]
     omnibar: TemplateChild<Omnibar>,
+    #[template_child(id = "findbar")]
+    findbar: TemplateChild<FindBar>,
+    #[template_child(id = "msg-area")]
+    msg_area: TemplateChild<MsgArea>,
+    #[template_child(id = "msg-status")]
+    msg_status: TemplateChild<MsgStatus>,
+    #[template_child(id = "msg-history")]
+    msg_history: TemplateChild<MsgHistory>,
+    #[template_child(id = "toast")]
+    toast: TemplateChild<Toast>,
+    /// Container of the message toasts, on top of the shell.
+    #[template_child(id = "toasts")]
+    toasts: TemplateChild<gtk::Box>,
+    /// How messages are shown as toasts, by message kind.
+    message_toasts: RefCell<HashMap<String, MessageToast>>,
+    /// Show error messages as desktop notifications when unfocused.
+    desktop_notifications: Cell<bool>,
+    /// Text width in focus mode set by the user, in columns.
+    focus_width: Cell<Option<u32>>,
+    /// Timer for the next update check, if the checks are enabled.
+    update_check: RefCell<Option<glib::SourceId>>,
+    /// Monitor for the system's power profile.
+    power_profile_monitor: RefCell<Option<gio::PowerProfileMonitor>>,
+    /// Power saver mode set by the user.
+    power_saver: Cell<PowerSaver>,
+    /// If the simplest rendering path is used. Implies the power saver.
+    safe_rendering: Cell<bool>,
+    /// The detected renderer, once we're realized.
+    renderer_info: RefCell<Option<RendererInfo>>,
+    /// If the messages are shown by us (i.e. ext_messages), instead of in
+    /// nvim's message grid.
+    ext_messages: Cell<bool>,
+    /// How ambiguous width characters are laid out, if set by the user.
+    ambiwidth: Cell<Option<Ambiwidth>>,
+    /// The 'ambiwidth' value from nvim.
+    nvim_ambiwidth: RefCell<String>,
+    /// How the bell is rung.
+    bell: Cell<Bell>,
+    /// Maximum redraws per second set by the user. Zero means the refresh
+    /// rate of the monitor(s) we're on.
+    max_fps: Cell<u32>,
+    /// Monitors the window is currently on.
+    monitors: RefCell<Vec<gdk::Monitor>>,
+    /// Monotonic time of the latest redraw, in microseconds.
+    last_draw: Cell<i64>,
+    /// Redraw delayed due to the frame rate cap.
+    pending_draw: RefCell<Option<glib::SourceId>>,
+    #[template_child(id = "debug-hud")]
+    debug_hud: TemplateChild<gtk::Label>,
+    /// The export dialog, while it's open.
+    export_dialog: RefCell<Option<gtk::FileChooserNative>>,
+    /// Timer refreshing the debug HUD, while it's shown.
+    debug_hud_timer: RefCell<Option<glib::SourceId>>,
+    /// Latest cursor goto since the previous flush. Applied on flush.
+    pending_cursor_goto: RefCell<Option<GridCursorGoto>>,
+    /// Latest mode (index to `mode_infos`) since the previous flush. Applied
+    /// on flush.
+    pending_mode: Cell<Option<usize>>,
+    /// Notification methods subscribed by plugins.
+    subscriptions: RefCell<HashSet<String>>,
+    /// Counts of the notifications we didn't know how to handle, by method.
+    unexpected_notifications: RefCell<HashMap<String, u64>>,
+    /// User's mapping from gdk key names to nvim keys.
+    key_remap: RefCell<HashMap<String, String>>,
+    /// User's notation for the Hyper modifier. `None` for the default.
+    hyper_modifier: RefCell<Option<String>>,
+    /// If we've asked nvim to attach the ui, and haven't heard of it yet.
+    ui_attaching: Cell<bool>,
+    /// If we've asked nvim to detach the ui, and haven't heard of it yet.
+    ui_detaching: Cell<bool>,
+    /// Our rpc channel in nvim, once known.
+    channel: Cell<Option<i64>>,
+    /// If secure input mode is on (e.g. when editing secrets).
+    secure_input: Cell<bool>,
+    /// Latest messages from nvim, for bug reports.
+    event_log: EventLog,
+    /// Show the start screen instead of closing when nvim exits cleanly.
+    keep_open: Cell<bool>,
+    /// Command lines of the `--wait` invocations, released once their files
+    /// are closed (or nvim exits).
+    waits: RefCell<HashMap<u32, gio::ApplicationCommandLine>>,
+    next_wait: Cell<u32>,
+    /// Highlight inspector window, created when first shown.
+    hl_inspector: RefCell<Option<HlInspector>>,
+    /// Set when the highlights changed, and the inspector needs updating.
+    hls_on_flush: Cell<bool>,
 
     css_provider: gtk::CssProvider,
 
@@ -53,7 +194,7 @@ pub struct AppWindow {
 
     colors: RefCell<Colors>,
 
-    #[property(get, set)]
+    #[property(get, set = Self::set_font)]
     font: RefCell<Font>,
     mode_infos: RefCell<Vec<ModeInfo>>,
     #[property(get, set)]
@@ -65,19 +206,52 @@ pub struct AppWindow {
     /// Set when attributes affecting our CSS changed, and we need to regenerate
     /// the css.
     css_on_flush: Cell<bool>,
+    /// The 'pumblend' value, applied to the popupmenu's backgrounds in our
+    /// CSS.
+    pumblend: Cell<i64>,
+
+    /// The guifont value from nvim (or from the previous session, until nvim
+    /// sets it).
+    guifont: RefCell<Option<String>>,
+    /// The guifontwide value from nvim.
+    guifontwide: RefCell<String>,
+    /// Incremented for each font load, so a finished load can tell if it
+    /// was superseded.
+    font_loads: Cell<u32>,
+    /// The guifont and guifontwide remembered from the previous session.
+    cached_font: RefCell<Option<(String, String)>>,
+    /// Font size chosen by the user (e.g. through zooming). Persists over
+    /// guifont changes.
+    font_size: Cell<Option<f32>>,
+    /// Scroll controller for changing the font size with ctrl+scroll.
+    event_controller_scroll: gtk::EventControllerScroll,
+    /// Gesture for zooming (i.e. changing the font size).
+    gesture_zoom: gtk::GestureZoom,
+    /// Font size when the zoom gesture started.
+    zoom_start_size: Cell<f32>,
+    /// Families for bold and italic text. Persist over font changes.
+    bold_font: RefCell<String>,
+    italic_font: RefCell<String>,
 }
 
 impl AppWindow {
     fn process_nvim_event(&self, msg: Message) {
+        if !self.secure_input.get() {
+            self.event_log.record(&msg);
+        }
+
         match msg {
             Message::Response(res) => {
                 self.nvim
                     .handle_response(res)
                     .expect("failed to handle nvim response");
             }
-            Message::Request(req) => {
-                println!("Got request from nvim: {:?}", req);
-            }
+            Message::Request(Request {
+                msgid,
+                method,
+                params,
+                ..
+            }) => self.handle_request(msgid, &method, params),
             Message::Notification(Notification { method, params, .. }) => match method.as_ref() {
                 "redraw" => {
                     let events = nvim::decode_redraw_params(params)
@@ -97,20 +271,283 @@ impl AppWindow {
                         }),
                     params => warn!("unexpected gnvim params: {:?}", params),
                 },
-                _ => {
-                    println!("Unexpected notification: {}", method);
+                _ => self.handle_custom_notification(method, params),
+            },
+        }
+    }
+
+    /// Forwards notifications to the runtime, if subscribed. Otherwise, the
+    /// notification is counted and logged.
+    fn handle_custom_notification(&self, method: String, params: rmpv::Value) {
+        if !self.subscriptions.borrow().contains(&method) {
+            debug!(
+                "Unexpected notification '{}': {}",
+                method,
+                summarize_payload(&params)
+            );
+            *self
+                .unexpected_notifications
+                .borrow_mut()
+                .entry(method)
+                .or_default() += 1;
+            return;
+        }
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_exec_lua(
+                    "require('gnvim').handle_notification(...)",
+                    vec![rmpv::Value::from(method.as_str()), params],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
                 }
+            };
+
+            if let Err(err) = res.await {
+                warn!("notification handler for '{}' failed: {:?}", method, err);
+            }
+        }));
+    }
+
+    fn handle_request(&self, msgid: u32, method: &str, params: rmpv::Value) {
+        let req = match method {
+            "gnvim" => match params {
+                rmpv::Value::Array(params) => params
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| String::from("missing gnvim request"))
+                    .and_then(|req| {
+                        GnvimRequest::deserialize(req)
+                            .map_err(|err| format!("failed to parse gnvim request: {:?}", err))
+                    }),
+                params => Err(format!("unexpected gnvim params: {:?}", params)),
+            },
+            method => Err(format!("unexpected request: {}", method)),
+        };
+
+        let res = match req {
+            // The dialog is answered asynchronously.
+            Ok(GnvimRequest::TrustPrompt(path)) => {
+                let obj = self.obj();
+                spawn_local!(clone!(@weak obj => async move {
+                    let choice = obj.imp().trust_prompt(&path).await;
+                    if let Err(err) = obj
+                        .imp()
+                        .nvim
+                        .write_response(msgid, None, Some(rmpv::Value::from(choice)))
+                        .await
+                    {
+                        warn!("failed to write response: {:?}", err);
+                    }
+                }));
+                return;
+            }
+            req => req.map(|req| self.handle_gnvim_request(req)),
+        };
+
+        let (error, result) = match res {
+            Ok(result) => (None, Some(result)),
+            Err(err) => {
+                warn!("{}", err);
+                (Some(rmpv::Value::from(err)), None)
+            }
+        };
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            if let Err(err) = nvim.write_response(msgid, error, result).await {
+                warn!("failed to write response: {:?}", err);
+            }
+        }));
+    }
+
+    fn handle_gnvim_request(&self, req: GnvimRequest) -> rmpv::Value {
+        match req {
+            GnvimRequest::Stats => {
+                let metrics = self.nvim.metrics();
+                let ms = |v: Option<std::time::Duration>| {
+                    v.map(|v| rmpv::Value::from(v.as_secs_f64() * 1000.0))
+                        .unwrap_or(rmpv::Value::Nil)
+                };
+
+                let calls = metrics
+                    .iter()
+                    .map(|(method, m)| {
+                        (
+                            rmpv::Value::from(method.as_str()),
+                            rmpv::Value::Map(vec![
+                                (rmpv::Value::from("calls"), rmpv::Value::from(m.calls)),
+                                (rmpv::Value::from("errors"), rmpv::Value::from(m.errors)),
+                                (rmpv::Value::from("p50"), ms(m.percentile(0.5))),
+                                (rmpv::Value::from("p90"), ms(m.percentile(0.9))),
+                                (rmpv::Value::from("p99"), ms(m.percentile(0.99))),
+                            ]),
+                        )
+                    })
+                    .collect();
+
+                let unexpected = self
+                    .unexpected_notifications
+                    .borrow()
+                    .iter()
+                    .map(|(method, count)| {
+                        (
+                            rmpv::Value::from(method.as_str()),
+                            rmpv::Value::from(*count),
+                        )
+                    })
+                    .collect();
+
+                rmpv::Value::Map(vec![
+                    (rmpv::Value::from("calls"), rmpv::Value::Map(calls)),
+                    (
+                        rmpv::Value::from("unexpected_notifications"),
+                        rmpv::Value::Map(unexpected),
+                    ),
+                ])
+            }
+            GnvimRequest::GridStats(id) => {
+                let grid = match self.shell.find_grid(id) {
+                    Some(grid) => grid,
+                    None => return rmpv::Value::Nil,
+                };
+
+                let stats = grid.render_stats();
+                let (width, height) = grid.grid_size();
+                let window = grid
+                    .nvim_window_handle()
+                    .map(rmpv::Value::from)
+                    .unwrap_or(rmpv::Value::Nil);
+
+                rmpv::Value::Map(vec![
+                    (rmpv::Value::from("window"), window),
+                    (rmpv::Value::from("width"), rmpv::Value::from(width)),
+                    (rmpv::Value::from("height"), rmpv::Value::from(height)),
+                    (
+                        rmpv::Value::from("rows_rendered"),
+                        rmpv::Value::from(stats.rows_rendered),
+                    ),
+                    (
+                        rmpv::Value::from("segments_rendered"),
+                        rmpv::Value::from(stats.segments_rendered),
+                    ),
+                    (
+                        rmpv::Value::from("cache_hits"),
+                        rmpv::Value::from(stats.cache_hits),
+                    ),
+                    (
+                        rmpv::Value::from("segments_skipped"),
+                        rmpv::Value::from(stats.segments_skipped),
+                    ),
+                    (
+                        rmpv::Value::from("flushes"),
+                        rmpv::Value::from(stats.flushes),
+                    ),
+                    (
+                        rmpv::Value::from("dropped_frames"),
+                        rmpv::Value::from(stats.dropped_frames),
+                    ),
+                ])
+            }
+            GnvimRequest::GridText(args) => {
+                let grid = match self.shell.find_grid(args.grid) {
+                    Some(grid) => grid,
+                    None => return rmpv::Value::Nil,
+                };
+
+                let start = args.start.unwrap_or(0);
+                let end = args.end.unwrap_or(usize::MAX);
+
+                rmpv::Value::Array(
+                    grid.text(start, end)
+                        .into_iter()
+                        .map(rmpv::Value::from)
+                        .collect(),
+                )
+            }
+            GnvimRequest::DumpEvents(_) if !self.event_log.is_enabled() => {
+                rmpv::Value::from("the protocol log is off, see :h gnvim-event-log")
+            }
+            GnvimRequest::DumpEvents(path) => match self.event_log.dump(Path::new(&path)) {
+                Ok(()) => rmpv::Value::Nil,
+                Err(err) => rmpv::Value::from(format!("failed to write {}: {}", path, err)),
             },
+            // Needs the dialog, which `handle_request` answers asynchronously.
+            // Without it, the file is ignored this time.
+            GnvimRequest::TrustPrompt(path) => {
+                warn!("trust prompt for {} can't be answered synchronously", path);
+                rmpv::Value::from("ignore")
+            }
+        }
+    }
+
+    /// Asks whether to trust the project-local config at `path`. Returns the
+    /// choice for the runtime.
+    async fn trust_prompt(&self, path: &str) -> &'static str {
+        let dialog = gtk::MessageDialog::builder()
+            .transient_for(&*self.obj())
+            .modal(true)
+            .message_type(gtk::MessageType::Question)
+            .text("Trust the project's config?")
+            .secondary_text(format!(
+                "{}\n\nThe config is sourced on startup and can run any code. \
+                The choice is remembered until the file changes.",
+                path
+            ))
+            .build();
+        dialog.add_buttons(&[
+            ("Ignore", gtk::ResponseType::Cancel),
+            ("Deny", gtk::ResponseType::Reject),
+            ("Allow", gtk::ResponseType::Accept),
+        ]);
+
+        let res = dialog.run_future().await;
+        dialog.close();
+
+        match res {
+            gtk::ResponseType::Accept => "allow",
+            gtk::ResponseType::Reject => "deny",
+            _ => "ignore",
         }
     }
 
     async fn io_loop<R: futures::AsyncRead + Unpin>(&self, reader: R) {
         let mut reader: RpcReader<R> = reader.into();
+        let replay = self.args.borrow().replay.is_some();
 
         loop {
-            match reader.recv().await {
+            let msg = if replay {
+                crate::nvim::recv_replayed(&mut reader).await
+            } else {
+                reader.recv().await
+            };
+
+            match msg {
                 Ok(msg) => self.process_nvim_event(msg),
+                Err(_) if replay => {
+                    self.finish_replay();
+                    break;
+                }
                 Err(_) => {
+                    // Anything still sent to the exited nvim goes nowhere.
+                    self.nvim.close();
+
+                    let status = self.nvim.exit_status().await;
+                    crate::NVIM_EXIT_STATUS.store(status, Ordering::Relaxed);
+                    // The waiting processes exit with nvim's status too.
+                    for (_, cmdline) in self.waits.borrow_mut().drain() {
+                        cmdline.set_exit_status(status);
+                    }
+
+                    if status == 0 && self.keep_open.get() {
+                        self.show_start_screen("Neovim exited.");
+                        break;
+                    }
+
                     self.obj()
                         .application()
                         .expect("application not set")
@@ -121,17 +558,362 @@ impl AppWindow {
         }
     }
 
+    fn ui_options(&self) -> UiOptions {
+        UiOptions {
+            rgb: true,
+            ext_messages: self.ext_messages.get(),
+            ext_linegrid: true,
+            ext_multigrid: true,
+            ext_popupmenu: true,
+            ext_tabline: true,
+            ext_cmdline: true,
+            ..Default::default()
+        }
+    }
+
+    /// Starts nvim (or connects to a remote one) and attaches to it as a ui.
+    fn attach_nvim(&self, args: &Arguments) {
+        if let Some(addr) = args.connect.clone() {
+            let obj = self.obj();
+            let args = args.clone();
+            spawn_local!(clone!(@weak obj => async move {
+                let imp = obj.imp();
+                match imp.nvim.connect(&addr).await {
+                    Ok(reader) => imp.start_nvim(reader, &args),
+                    Err(err) => {
+                        warn!("failed to connect to {}: {}", addr, err);
+                        imp.show_start_screen(&format!("Couldn't connect to {}: {}", addr, err));
+                    }
+                }
+            }));
+
+            return;
+        }
+
+        let cmd_args = args.nvim_cmd_args();
+        let cmd_args: Vec<&OsStr> = cmd_args.iter().map(|a| a.as_ref()).collect();
+        let reader = self.nvim.open(&cmd_args, args.stdin_fd.is_some());
+        self.start_nvim(reader, args);
+    }
+
+    /// Starts the io loop on `reader`, and attaches to nvim as a ui.
+    fn start_nvim(
+        &self,
+        reader: gio::InputStreamAsyncRead<gio::PollableInputStream>,
+        args: &Arguments,
+    ) {
+        let obj = self.obj();
+        // Our own attach, see `GnvimEvent::UiAttached`.
+        self.ui_attaching.set(true);
+
+        let uiopts = UiOptions {
+            stdin_fd: args.stdin_fd,
+            ..self.ui_options()
+        };
+        let slow_link = args.slow_link;
+        if let Some(link) = slow_link {
+            self.nvim.set_slow_link(link);
+        }
+
+        // Start io loop.
+        spawn_local!(clone!(@strong obj as app => async move {
+            match slow_link {
+                Some(link) => app.imp().io_loop(SlowReader::new(reader, link)).await,
+                None => app.imp().io_loop(reader).await,
+            }
+        }));
+
+        // The server would be on the remote machine.
+        let server = args
+            .connect
+            .is_none()
+            .then(|| crate::remote::server_path(&args.servername));
+
+        // Call nvim_ui_attach.
+        spawn_local!(clone!(@weak obj => async move {
+            let imp = obj.imp();
+            let nvim = &imp.nvim;
+            let res = match nvim
+                .nvim_set_client_info(
+                    "gnvim",
+                    // TODO(ville): Tell the version in client info.
+                    &dict![],
+                    "ui",
+                    &dict![],
+                    &dict![],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    imp.ui_attaching.set(false);
+                    imp.show_start_screen("Lost the connection to Neovim.");
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("nvim_set_client_info failed: {:?}", err);
+                imp.ui_attaching.set(false);
+                imp.show_start_screen("Lost the connection to Neovim.");
+                return;
+            }
+
+            // To tell our `UIEnter` and `UILeave` from the other uis'.
+            match nvim.nvim_get_api_info().await {
+                Ok(res) => match res.await {
+                    Ok(info) => imp.channel.set(info.first().and_then(|chan| chan.as_i64())),
+                    Err(err) => warn!("failed to get nvim api info: {:?}", err),
+                },
+                Err(err) => warn!("call to nvim failed: {:?}", err),
+            }
+
+            let res = match nvim.nvim_ui_attach(80, 30, uiopts).await {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    imp.ui_attaching.set(false);
+                    imp.show_start_screen("Lost the connection to Neovim.");
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("nvim_ui_attach failed: {:?}", err);
+                imp.ui_attaching.set(false);
+                imp.show_start_screen("Lost the connection to Neovim.");
+                return;
+            }
+
+            // For `--remote-send` and `--remote-expr`.
+            if let Some(server) = server {
+                match nvim
+                    .nvim_exec_lua(
+                        "require('gnvim').start_server(...)",
+                        vec![rmpv::Value::from(&*server.to_string_lossy())],
+                    )
+                    .await
+                {
+                    Ok(res) => {
+                        if let Err(err) = res.await {
+                            warn!("failed to start server: {:?}", err);
+                        }
+                    }
+                    Err(err) => warn!("call to nvim failed: {:?}", err),
+                }
+            }
+        }));
+    }
+
+    /// Shows the start screen with `msg`, after nvim has exited (or we
+    /// couldn't connect to it).
+    fn show_start_screen(&self, msg: &str) {
+        self.reset_ui_state();
+
+        let obj = self.obj();
+        let mut actions = vec![ToastAction::new(
+            "Relaunch",
+            clone!(@weak obj => move || {
+                let args = Arguments {
+                    // Stdin was for the first nvim.
+                    stdin_fd: None,
+                    ..obj.imp().args.borrow().clone()
+                };
+                obj.imp().attach_nvim(&args);
+            }),
+        )];
+        let session = session_path(&self.args.borrow().servername);
+        if session.exists() && self.args.borrow().connect.is_none() {
+            actions.push(ToastAction::new(
+                "Reopen last session",
+                clone!(@weak obj => move || {
+                    let mut args = Arguments {
+                        files: vec![],
+                        stdin_fd: None,
+                        ..obj.imp().args.borrow().clone()
+                    };
+                    args.nvim_args.push("-S".into());
+                    args.nvim_args.push(session.clone().into_os_string());
+                    obj.imp().attach_nvim(&args);
+                }),
+            ));
+        }
+        actions.push(ToastAction::new(
+            "Quit",
+            clone!(@weak obj => move || {
+                obj.application().expect("application not set").quit();
+            }),
+        ));
+
+        self.shell.show_start_screen(msg, actions);
+    }
+
+    /// Detaches the ui and attaches it again. The ui state is torn down in
+    /// between, and rebuilt from the full redraw nvim sends on attach.
+    fn reattach_nvim(&self) {
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            let imp = obj.imp();
+
+            imp.ui_detaching.set(true);
+            match obj.nvim().nvim_ui_detach().await {
+                Ok(res) => {
+                    // Fails if we weren't attached, which is fine.
+                    if let Err(err) = res.await {
+                        warn!("nvim_ui_detach failed: {:?}", err);
+                        imp.ui_detaching.set(false);
+                    }
+                }
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    imp.ui_detaching.set(false);
+                    return;
+                }
+            }
+
+            imp.reset_ui_state();
+            imp.attach_ui().await;
+        }));
+    }
+
+    /// Attaches the (detached) ui again, with the root grid's size.
+    async fn attach_ui(&self) {
+        let (cols, rows) = self.shell.root_grid_size();
+
+        self.ui_attaching.set(true);
+        let res = match self
+            .nvim
+            .nvim_ui_attach(cols as i64, rows as i64, self.ui_options())
+            .await
+        {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("call to nvim failed: {:?}", err);
+                self.ui_attaching.set(false);
+                return;
+            }
+        };
+
+        if let Err(err) = res.await {
+            warn!("nvim_ui_attach failed: {:?}", err);
+            self.ui_attaching.set(false);
+        }
+    }
+
+    /// Forgets the state received from nvim.
+    fn reset_ui_state(&self) {
+        if let Some(id) = self.pending_draw.take() {
+            id.remove();
+        }
+        self.pending_cursor_goto.replace(None);
+        self.pending_mode.set(None);
+        self.mode_infos.replace(vec![]);
+        self.colors.replace(Colors::default());
+        self.shell.reset();
+        self.tabline.clear();
+        self.omnibar.reset();
+        self.msg_area.clear();
+        self.msg_status.clear();
+        self.msg_history.clear();
+        self.msg_history.close();
+        self.toast.dismiss();
+        while let Some(child) = self.toasts.first_child() {
+            self.toasts.remove(&child);
+        }
+        // The full redraw will set the options again.
+        self.resize_on_flush.set(true);
+        self.css_on_flush.set(true);
+    }
+
+    /// Renders the final state of the replayed stream, and writes the
+    /// screenshot (if requested).
+    fn finish_replay(&self) {
+        // Don't wait for the frame rate cap.
+        if let Some(id) = self.pending_draw.take() {
+            id.remove();
+        }
+        self.flush();
+        self.draw();
+
+        let path = match self.args.borrow().screenshot.clone() {
+            Some(path) => path,
+            None => return,
+        };
+
+        // Wait for the window to be mapped, and for one more frame so the
+        // flushed state has been laid out.
+        let frames = Cell::new(0);
+        self.obj().add_tick_callback(move |obj, _| {
+            if !obj.is_mapped() {
+                return glib::ControlFlow::Continue;
+            }
+
+            frames.set(frames.get() + 1);
+            if frames.get() < 2 {
+                return glib::ControlFlow::Continue;
+            }
+
+            let res = obj
+                .imp()
+                .shell
+                .render_texture()
+                .map_err(|err| err.to_string())
+                .and_then(|texture| texture.save_to_png(&path).map_err(|err| err.to_string()));
+            if let Err(err) = res {
+                warn!("failed to write screenshot {:?}: {}", path, err);
+            }
+
+            obj.application().expect("application not set").quit();
+            glib::ControlFlow::Break
+        });
+    }
+
+    /// Paints the background before neovim has set its colors, so there's no
+    /// flash of the theme's color on startup.
+    fn paint_initial_background(&self) {
+        let color = match self.args.borrow().background.clone() {
+            Some(color) => color,
+            None => match read_cached_background() {
+                Some(color) => color,
+                None => return,
+            },
+        };
+
+        match gdk::RGBA::parse(color.as_str()) {
+            Ok(rgba) => {
+                self.colors.borrow_mut().bg = Color::from(rgba);
+                self.load_css();
+            }
+            Err(err) => warn!("invalid background color {:?}: {}", color, err),
+        }
+    }
+
     fn handle_hl_group_set(&self, event: HlGroupSet) {
+        self.colors
+            .borrow_mut()
+            .hl_group_names
+            .insert(event.name.clone(), event.id);
+        self.hls_on_flush.set(true);
+
         if let Some(group) = match event.name.as_ref() {
             "MsgSeparator" => Some(HlGroup::MsgSeparator),
             "Pmenu" => Some(HlGroup::Pmenu),
             "PmenuSel" => Some(HlGroup::PmenuSel),
             "PmenuSbar" => Some(HlGroup::PmenuSbar),
             "PmenuThumb" => Some(HlGroup::PmenuThumb),
+            "PmenuKind" => Some(HlGroup::PmenuKind),
+            "PmenuKindSel" => Some(HlGroup::PmenuKindSel),
+            "PmenuExtra" => Some(HlGroup::PmenuExtra),
+            "PmenuExtraSel" => Some(HlGroup::PmenuExtraSel),
             "TabLine" => Some(HlGroup::TabLine),
             "TabLineFill" => Some(HlGroup::TabLineFill),
             "TabLineSel" => Some(HlGroup::TabLineSel),
             "Menu" => Some(HlGroup::Menu),
+            "WarningMsg" => Some(HlGroup::WarningMsg),
+            "ErrorMsg" => Some(HlGroup::ErrorMsg),
+            "ColorColumn" => Some(HlGroup::ColorColumn),
+            "WinSeparator" => Some(HlGroup::WinSeparator),
             _ => None,
         } {
             self.colors.borrow_mut().set_hl_group(group, event.id);
@@ -141,11 +923,18 @@ impl AppWindow {
 
     fn handle_default_colors_set(&self, event: DefaultColorsSet) {
         let mut colors = self.colors.borrow_mut();
+        let bg = Color::from_i64(event.rgb_bg);
+        // Remember the background for the next startup.
+        if *colors.bg != *bg && self.args.borrow().replay.is_none() {
+            write_cached_background(&bg);
+        }
+
         colors.fg = Color::from_i64(event.rgb_fg);
-        colors.bg = Color::from_i64(event.rgb_bg);
+        colors.bg = bg;
         colors.sp = Color::from_i64(event.rgb_sp);
 
         self.css_on_flush.set(true);
+        self.hls_on_flush.set(true);
     }
 
     fn handle_popupmenu_show(&self, event: PopupmenuShow) {
@@ -186,11 +975,41 @@ impl AppWindow {
                         .await
                         .unwrap();
 
-                    res.await.expect("nvim_echo failed");
+                    if let Err(err) = res.await {
+                        warn!("nvim_echo failed: {:?}", err);
+                    }
                 }));
             }
             GnvimEvent::GtkDebugger => {
-                self.enable_debugging(true);
+                if self.secure_input.get() {
+                    warn!("gtk debugger is disabled in secure input mode");
+                } else {
+                    self.enable_debugging(true);
+                }
+            }
+            GnvimEvent::SecureInput(enabled) => self.set_secure_input(enabled),
+            GnvimEvent::Reattach => self.reattach_nvim(),
+            GnvimEvent::SplashText(text) => self.shell.set_splash_text(text.as_str()),
+            // Other uis coming and going are none of our business.
+            GnvimEvent::UiAttached(chan) | GnvimEvent::UiDetached(chan)
+                if self.channel.get() != Some(chan) => {}
+            GnvimEvent::UiAttached(_) => {
+                // Attached by someone else. Whatever was drawn got mixed
+                // with our stale state, so rebuild it.
+                if !self.ui_attaching.replace(false) {
+                    self.reattach_nvim();
+                }
+            }
+            GnvimEvent::UiDetached(_) => {
+                // Detached by someone else (e.g. a session manager). Rebuild
+                // the state from the full redraw of a new attach.
+                if !self.ui_detaching.replace(false) {
+                    self.reset_ui_state();
+                    let obj = self.obj();
+                    spawn_local!(clone!(@weak obj => async move {
+                        obj.imp().attach_ui().await;
+                    }));
+                }
             }
             GnvimEvent::CursorBlinkTransition(t) => {
                 self.shell.set_cursor_blink_transition(t);
@@ -198,19 +1017,671 @@ impl AppWindow {
             GnvimEvent::CursorPositionTransition(t) => {
                 self.shell.set_cursor_position_transition(t);
             }
+            GnvimEvent::CursorPositionEasing(easing) => {
+                self.shell.set_cursor_position_easing(easing);
+            }
             GnvimEvent::ScrollTransition(t) => {
                 self.shell.set_scroll_transition(t);
             }
+            GnvimEvent::Ligatures(enabled) => {
+                self.shell.set_ligatures(enabled);
+                // Re-render the grids.
+                self.shell.handle_flush(&self.colors.borrow());
+                self.shell.queue_draw_grids();
+            }
+            GnvimEvent::CellInspector(enabled) => {
+                self.shell.set_cell_inspector(enabled);
+                // Give the grids the colors for the inspector.
+                self.shell.handle_flush(&self.colors.borrow());
+            }
+            GnvimEvent::HlInspector => self.show_hl_inspector(),
+            GnvimEvent::ToggleMsgHistory => self.toggle_msg_history(),
+            GnvimEvent::ExtMessages(enabled) => self.set_ext_messages(enabled),
+            GnvimEvent::Toast(event) => self.show_toast(event),
+            GnvimEvent::MessageToasts(toasts) => {
+                self.message_toasts.replace(toasts);
+            }
+            GnvimEvent::DesktopNotifications(enabled) => {
+                self.desktop_notifications.set(enabled);
+            }
+            GnvimEvent::UpdateCheck(enabled) => self.set_update_check(enabled),
+            GnvimEvent::LatestRelease(release) => self.handle_latest_release(release),
+            GnvimEvent::FocusMode(event) => self.handle_focus_mode(event),
+            GnvimEvent::TabAccents(accents) => self.handle_tab_accents(accents),
+            GnvimEvent::FontFeatures(features) => {
+                let font = {
+                    let font = self.font.borrow();
+                    Font::new_with_features(
+                        &font.guifont(),
+                        &font.guifontwide(),
+                        font.linespace() / SCALE,
+                        &features,
+                    )
+                };
+                self.apply_font(font);
+            }
+            GnvimEvent::BoldFont(family) => {
+                self.bold_font.replace(family);
+                // Recreate the font, so the change reaches its users.
+                let font = self.font.borrow().with_size(self.font.borrow().size());
+                self.apply_font(font);
+            }
+            GnvimEvent::ItalicFont(family) => {
+                self.italic_font.replace(family);
+                // Recreate the font, so the change reaches its users.
+                let font = self.font.borrow().with_size(self.font.borrow().size());
+                self.apply_font(font);
+            }
+            GnvimEvent::UndercurlAmplitude(amplitude) => {
+                self.shell.set_undercurl_amplitude(amplitude);
+                // Re-render the grids.
+                self.shell.handle_flush(&self.colors.borrow());
+                self.shell.queue_draw_grids();
+            }
+            GnvimEvent::EmojiPresentation(presentation) => {
+                self.shell.set_emoji_presentation(presentation);
+                // Re-render the grids.
+                self.shell.handle_flush(&self.colors.borrow());
+                self.shell.queue_draw_grids();
+            }
+            GnvimEvent::Ambiwidth(ambiwidth) => {
+                self.ambiwidth.set(Some(ambiwidth));
+                self.sync_ambiwidth();
+            }
+            GnvimEvent::PowerSaver(mode) => {
+                self.power_saver.set(mode);
+                self.update_power_saver();
+            }
+            GnvimEvent::SafeRendering(enabled) => self.set_safe_rendering(enabled),
+            GnvimEvent::Bell(bell) => self.bell.set(bell),
+            GnvimEvent::MaxFps(fps) => self.max_fps.set(fps),
+            GnvimEvent::LargeFile(event) => self.handle_large_file(event),
+            GnvimEvent::OpenFind => self.findbar.open(),
+            GnvimEvent::ExportBuffer => self.export_buffer(),
+            GnvimEvent::About => self.show_about(),
+            GnvimEvent::FloatDecoration(enabled) => self.shell.set_float_decoration(enabled),
+            GnvimEvent::FoldPreview(enabled) => self.shell.set_fold_preview(enabled),
+            GnvimEvent::Scrollbars(enabled) => self.shell.set_scrollbars(enabled),
+            GnvimEvent::RefreshWinHighlights => self.shell.refresh_normal_bgs(),
+            GnvimEvent::Guides(guides) => self.shell.set_guides(guides),
+            GnvimEvent::Blends(blends) => self.shell.set_blends(blends),
+            GnvimEvent::KeepOpen(enabled) => self.set_keep_open(enabled),
+            GnvimEvent::WaitDone(id) => {
+                // Releasing the command line lets its process exit.
+                if let Some(cmdline) = self.waits.borrow_mut().remove(&id) {
+                    cmdline.set_exit_status(0);
+                }
+            }
+            GnvimEvent::KeyRemap(remap) => {
+                self.key_remap.replace(remap);
+            }
+            GnvimEvent::MouseMap(map) => self.shell.set_mouse_map(MouseMap::from(map)),
+            GnvimEvent::HyperModifier(hyper) => {
+                self.hyper_modifier.replace(hyper);
+            }
+            GnvimEvent::Subscribe(method) => {
+                self.subscriptions.borrow_mut().insert(method);
+            }
+            GnvimEvent::Unsubscribe(method) => {
+                self.subscriptions.borrow_mut().remove(&method);
+            }
+            GnvimEvent::DebugHud(enabled) => self.set_debug_hud(enabled),
+            GnvimEvent::DiffBands(bands) => self.shell.set_diff_bands(bands),
+            GnvimEvent::EventLog(enabled) => self.event_log.set_enabled(enabled),
+        }
+    }
+
+    /// Minimum time between redraws, in microseconds. `None` if there is no
+    /// limit.
+    fn draw_interval(&self) -> Option<i64> {
+        // Refresh rates are in millihertz.
+        let rate = match self.max_fps.get() {
+            // On multiple monitors, follow the fastest one.
+            0 => self
+                .monitors
+                .borrow()
+                .iter()
+                .map(|monitor| monitor.refresh_rate() as i64)
+                .max()?,
+            fps => fps as i64 * 1000,
+        };
+
+        (rate > 0).then(|| 1_000_000_000 / rate)
+    }
+
+    /// Redraws now, or later if the previous redraw was too recent. The
+    /// state is always applied on nvim's flush, so a delayed redraw never
+    /// shows half applied batches.
+    fn schedule_draw(&self) {
+        if self.pending_draw.borrow().is_some() {
+            // The pending redraw will draw the latest flush.
+            return;
+        }
+
+        let elapsed = glib::monotonic_time() - self.last_draw.get();
+        match self.draw_interval() {
+            Some(interval) if elapsed < interval => {
+                let obj = self.obj();
+                let id = glib::timeout_add_local_once(
+                    Duration::from_micros((interval - elapsed) as u64),
+                    clone!(@weak obj => move || {
+                        let imp = obj.imp();
+                        imp.pending_draw.take();
+                        imp.draw();
+                    }),
+                );
+                self.pending_draw.replace(Some(id));
+            }
+            _ => self.draw(),
+        }
+    }
+
+    fn draw(&self) {
+        self.last_draw.set(glib::monotonic_time());
+        self.shell.queue_draw_grids();
+    }
+
+    fn flush(&self) {
+        // Only the final mode and cursor position of the batch matter.
+        if let Some(idx) = self.pending_mode.take() {
+            let modes = self.mode_infos.borrow();
+            let mode = modes.get(idx).expect("invalid mode_idx");
+            self.shell.handle_mode_change(mode);
+        }
+        if let Some(event) = self.pending_cursor_goto.take() {
+            self.shell.handle_grid_cursor_goto(event);
+        }
+
+        self.shell.handle_flush(&self.colors.borrow());
+        self.tabline.flush();
+
+        if self.resize_on_flush.take() {
+            self.shell.resize_nvim();
+        }
+
+        if self.css_on_flush.take() {
+            self.load_css();
+        }
+
+        if self.hls_on_flush.take() {
+            if let Some(ref inspector) = *self.hl_inspector.borrow() {
+                if inspector.is_visible() {
+                    inspector.update(&self.colors.borrow());
+                }
+            }
+        }
+    }
+
+    /// Shows a toast from the runtime. The actions call back to the runtime.
+    fn show_toast(&self, event: ToastEvent) {
+        let obj = self.obj();
+        let actions = event
+            .actions
+            .into_iter()
+            .map(|action| {
+                let id = action.id;
+                ToastAction::new(
+                    &action.label,
+                    clone!(@weak obj => move || {
+                        spawn_local!(clone!(@weak obj => async move {
+                            let res = obj
+                                .nvim()
+                                .nvim_exec_lua(
+                                    "require('gnvim').toast_action(...)",
+                                    vec![id.into()],
+                                )
+                                .await;
+                            let res = match res {
+                                Ok(res) => res,
+                                Err(err) => {
+                                    warn!("call to nvim failed: {:?}", err);
+                                    return;
+                                }
+                            };
+
+                            if let Err(err) = res.await {
+                                warn!("toast action failed: {:?}", err);
+                            }
+                        }));
+                    }),
+                )
+            })
+            .collect();
+
+        self.toast.show(
+            &event.text,
+            event.severity,
+            event.timeout.map(Duration::from_millis),
+            actions,
+        );
+    }
+
+    /// Shows a message from nvim in a toast if set so for its kind, otherwise
+    /// in the message area.
+    fn handle_msg_show(&self, event: MsgShow) {
+        let text = || {
+            event
+                .content
+                .iter()
+                .map(|chunk| chunk.text_chunk.as_str())
+                .collect::<String>()
+        };
+
+        if ERROR_MESSAGE_KINDS.contains(&event.kind.as_str()) {
+            self.notify_error(text().trim());
+        }
+
+        let toast = self.message_toasts.borrow().get(&event.kind).copied();
+        match toast {
+            Some(toast) => self.show_message_toast(text().trim(), toast, event.replace_last),
+            None => self
+                .msg_area
+                .show(&event.content, event.replace_last, &self.colors.borrow()),
         }
     }
 
+    /// Shows a message in a new toast on top of the shell. The oldest toasts
+    /// are dropped when there are more than `MAX_MESSAGE_TOASTS` of them.
+    fn show_message_toast(&self, text: &str, options: MessageToast, replace_last: bool) {
+        let toasts = self.toasts.get();
+        if replace_last {
+            if let Some(last) = toasts.last_child() {
+                toasts.remove(&last);
+            }
+        }
+
+        let toast = Toast::default();
+        toast.connect_dismissed(clone!(@weak toasts => move |toast| {
+            toasts.remove(toast);
+        }));
+
+        toasts.append(&toast);
+        toast.show(
+            text,
+            options.severity,
+            options.timeout.map(Duration::from_millis),
+            vec![],
+        );
+
+        let mut count = 0;
+        let mut child = toasts.last_child();
+        while let Some(widget) = child {
+            child = widget.prev_sibling();
+            count += 1;
+            if count > MAX_MESSAGE_TOASTS {
+                toasts.remove(&widget);
+            }
+        }
+    }
+
+    /// Shows `msg` as a desktop notification, if enabled and the window isn't
+    /// focused. Clicking the notification focuses the window.
+    fn notify_error(&self, msg: &str) {
+        let obj = self.obj();
+        if !self.desktop_notifications.get() || obj.is_active() {
+            return;
+        }
+
+        let app = match obj.application() {
+            Some(app) => app,
+            None => return,
+        };
+
+        let notification = gio::Notification::new(&obj.title().unwrap_or_default());
+        notification.set_body(Some(msg));
+        notification.set_priority(gio::NotificationPriority::High);
+        notification.set_default_action_and_target_value(
+            "app.present-window",
+            Some(&obj.id().to_variant()),
+        );
+        app.send_notification(Some(&error_notification_id(&obj)), &notification);
+    }
+
+    fn set_update_check(&self, enabled: bool) {
+        if let Some(id) = self.update_check.take() {
+            id.remove();
+        }
+
+        if !enabled {
+            return;
+        }
+
+        if updater::is_packaged() {
+            debug!("gnvim is installed by a package manager, not checking for updates");
+            return;
+        }
+
+        self.schedule_update_check(updater::next_check_in());
+    }
+
+    fn schedule_update_check(&self, after: Duration) {
+        let obj = self.obj();
+        let id = glib::timeout_add_local_once(
+            after,
+            clone!(@weak obj => move || {
+                let imp = obj.imp();
+                // The timer is done, don't remove it.
+                imp.update_check.take();
+                imp.check_for_updates();
+                imp.schedule_update_check(updater::CHECK_INTERVAL);
+            }),
+        );
+
+        self.update_check.replace(Some(id));
+    }
+
+    /// Asks the runtime to fetch the latest release. It's sent back with the
+    /// `latest_release` event, if the fetch succeeds.
+    fn check_for_updates(&self) {
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            let res = match obj
+                .nvim()
+                .nvim_exec_lua("require('gnvim').check_for_updates()", vec![])
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to check for updates: {:?}", err);
+            }
+        }));
+    }
+
+    fn handle_latest_release(&self, release: Release) {
+        // Only a successful fetch counts, so a failed one is retried on the
+        // next start.
+        updater::mark_checked();
+
+        if !updater::is_newer(&release.tag, env!("CARGO_PKG_VERSION")) {
+            debug!("no updates, latest release is {}", release.tag);
+            return;
+        }
+
+        let mut text = format!("Gnvim {} is available.", release.tag);
+        let notes = release
+            .notes
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .take(RELEASE_NOTES_LINES)
+            .collect::<Vec<_>>();
+        if !notes.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&notes.join("\n"));
+        }
+
+        let obj = self.obj();
+        let url = release.url;
+        self.toast.show(
+            &text,
+            Severity::Info,
+            None,
+            vec![ToastAction::new(
+                "Open release page",
+                clone!(@weak obj => move || {
+                    gtk::show_uri(Some(&obj), &url, gdk::CURRENT_TIME);
+                }),
+            )],
+        );
+    }
+
+    /// Tells about the event logs of crashed gnvims (see `event_log`), so
+    /// they can be attached to a bug report.
+    fn report_crashes(&self) {
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            let dumps = match gio::spawn_blocking(event_log::take_crash_dumps).await {
+                Ok(Ok(dumps)) => dumps,
+                Ok(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => return,
+                Ok(Err(err)) => {
+                    warn!("failed to collect the crash logs: {}", err);
+                    return;
+                }
+                Err(_) => return,
+            };
+
+            let dir = match dumps.first().and_then(|path| path.parent()) {
+                Some(dir) => gio::File::for_path(dir),
+                None => return,
+            };
+            obj.imp().toast.show(
+                "Gnvim crashed earlier. The latest messages from nvim were saved for a bug report.",
+                Severity::Warning,
+                None,
+                vec![ToastAction::new(
+                    "Open Folder",
+                    clone!(@weak obj => move || {
+                        gtk::show_uri(Some(&obj), &dir.uri(), gdk::CURRENT_TIME);
+                    }),
+                )],
+            );
+        }));
+    }
+
+    /// Sets if the messages are shown in gnvim's message area and status
+    /// strip (i.e. ext_messages), or drawn by nvim in its message grid.
+    fn set_ext_messages(&self, enabled: bool) {
+        if self.ext_messages.replace(enabled) == enabled {
+            return;
+        }
+
+        if !enabled {
+            self.msg_area.clear();
+            self.msg_status.clear();
+        }
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_ui_set_option("ext_messages", &rmpv::Value::from(enabled))
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to set ext_messages: {:?}", err);
+            }
+        }));
+    }
+
+    /// Shows the message history panel, or hides it if it's open. Nvim
+    /// sends the history (i.e. msg_history_show) for `:messages`, when the
+    /// messages are shown by us.
+    fn toggle_msg_history(&self) {
+        if self.msg_history.is_visible() {
+            self.msg_history.close();
+            return;
+        }
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim.nvim_command("messages").await {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to get the message history: {:?}", err);
+            }
+        }));
+    }
+
+    fn show_hl_inspector(&self) {
+        let inspector = self
+            .hl_inspector
+            .borrow_mut()
+            .get_or_insert_with(|| HlInspector::new(self.obj().upcast_ref()))
+            .clone();
+
+        inspector.update(&self.colors.borrow());
+        inspector.present();
+    }
+
+    /// Toggles secure input mode. While on, the input method is asked not to
+    /// learn from the input (e.g. for suggestions), the gtk debugger (which
+    /// can show the key events) is closed and disabled, and the keys are not
+    /// logged.
+    fn set_secure_input(&self, enabled: bool) {
+        self.secure_input.set(enabled);
+
+        let im_context = self.im_context.borrow();
+        // Drop any state the input method has gathered so far.
+        im_context.reset();
+        if enabled {
+            // Don't keep anything shown before secure input in the log.
+            self.event_log.clear();
+
+            im_context.set_input_purpose(gtk::InputPurpose::Password);
+            im_context.set_input_hints(
+                gtk::InputHints::PRIVATE
+                    | gtk::InputHints::NO_SPELLCHECK
+                    | gtk::InputHints::NO_EMOJI,
+            );
+
+            gtk::Window::set_interactive_debugging(false);
+        } else {
+            im_context.set_input_purpose(gtk::InputPurpose::FreeForm);
+            im_context.set_input_hints(gtk::InputHints::NONE);
+        }
+    }
+
+    /// Rings the bell. `visual` tells if nvim asked for the visual bell
+    /// (i.e. 'visualbell' is set).
+    fn ring_bell(&self, visual: bool) {
+        let visual = match self.bell.get() {
+            Bell::Auto => visual,
+            Bell::Audible => false,
+            Bell::Visual => true,
+            Bell::Off => return,
+        };
+
+        if visual {
+            self.shell.flash();
+        } else {
+            self.obj().error_bell();
+        }
+    }
+
+    /// Updates the shell's power saver state based on the user's setting and
+    /// the system's power profile.
+    fn update_power_saver(&self) {
+        let enabled = match self.power_saver.get() {
+            PowerSaver::Auto => self
+                .power_profile_monitor
+                .borrow()
+                .as_ref()
+                .map(|monitor| monitor.is_power_saver_enabled())
+                .unwrap_or(false),
+            PowerSaver::On => true,
+            PowerSaver::Off => false,
+        };
+
+        self.shell
+            .set_power_saver(enabled || self.safe_rendering.get());
+    }
+
+    /// Detects the renderer, and applies the workarounds it needs.
+    fn detect_renderer(&self) {
+        let info = RendererInfo::detect(&*self.obj());
+        let workarounds = info.workarounds();
+        debug!("renderer: {}, workarounds: {}", info, workarounds);
+
+        if workarounds.safe_rendering && !self.safe_rendering.get() {
+            warn!("using safe rendering to work around issues with {}", info);
+            self.set_safe_rendering(true);
+        }
+
+        self.renderer_info.replace(Some(info));
+    }
+
+    /// Toggles the safe rendering, i.e. rendering without caching and
+    /// animations. Switching to the cairo renderer needs `--safe-rendering`.
+    fn set_safe_rendering(&self, enabled: bool) {
+        self.safe_rendering.set(enabled);
+        self.shell.set_safe_rendering(enabled);
+        self.update_power_saver();
+    }
+
+    /// Sets the window icon from 'iconstring'. Only names from the icon
+    /// theme can be used, other values reset the icon to the default.
+    fn set_icon(&self, icon: &str) {
+        let obj = self.obj();
+        let theme = gtk::IconTheme::for_display(&obj.display());
+        let name = if !icon.is_empty() && theme.has_icon(icon) {
+            icon
+        } else {
+            DEFAULT_ICON
+        };
+
+        obj.set_icon_name(Some(name));
+    }
+
+    fn handle_large_file(&self, event: LargeFile) {
+        if !event.large {
+            if let Some(grid) = self.shell.find_grid_by_window(event.win) {
+                grid.set_large_file(false);
+            }
+            return;
+        }
+
+        // Already disabled for the window.
+        if self
+            .shell
+            .find_grid_by_window(event.win)
+            .map(|grid| grid.large_file())
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let obj = self.obj();
+        let win = event.win;
+        self.toast.show(
+            &format!(
+                "Large file ({:.1} MB). Animations can be disabled for the window. \
+                Consider also `:setlocal syntax=off nowrap`.",
+                event.size as f64 / (1024.0 * 1024.0)
+            ),
+            Severity::Info,
+            None,
+            vec![ToastAction::new(
+                "Disable animations",
+                clone!(@weak obj => move || {
+                    if let Some(grid) = obj.imp().shell.find_grid_by_window(win) {
+                        grid.set_large_file(true);
+                    }
+                }),
+            )],
+        );
+    }
+
     fn handle_ui_event(&self, event: UiEvent) {
         match event {
             // Global events
             UiEvent::SetTitle(events) => events.into_iter().for_each(|event| {
-                self.obj().set_title(Some(&event.title));
+                // Nvim sends an empty title when 'title' is turned off.
+                let title = if event.title.is_empty() {
+                    DEFAULT_TITLE
+                } else {
+                    &event.title
+                };
+                self.obj().set_title(Some(title));
+            }),
+            UiEvent::SetIcon(events) => events.into_iter().for_each(|event| {
+                self.set_icon(&event.icon);
             }),
-            UiEvent::SetIcon(_) => {}
             UiEvent::ModeInfoSet(events) => events.into_iter().for_each(|event| {
                 self.mode_infos
                     .replace(event.cursor_styles.into_iter().map(Into::into).collect());
@@ -218,13 +1689,11 @@ impl AppWindow {
             UiEvent::OptionSet(events) => events.into_iter().for_each(|event| {
                 self.handle_option_set(event);
             }),
-            UiEvent::ModeChange(events) => events.into_iter().for_each(|event| {
-                let modes = self.mode_infos.borrow();
-                let mode = modes
-                    .get(event.mode_idx as usize)
-                    .expect("invalid mode_idx");
-                self.shell.handle_mode_change(mode);
-            }),
+            UiEvent::ModeChange(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.pending_mode.set(Some(event.mode_idx as usize));
+                }
+            }
             UiEvent::MouseOn => {}
             UiEvent::MouseOff => {}
             UiEvent::BusyStart => {
@@ -235,57 +1704,11 @@ impl AppWindow {
             }
             UiEvent::Suspend => {}
             UiEvent::UpdateMenu => {}
-            UiEvent::Bell => {}
-            UiEvent::VisualBell => {}
+            UiEvent::Bell => self.ring_bell(false),
+            UiEvent::VisualBell => self.ring_bell(true),
             UiEvent::Flush => {
-                self.shell.handle_flush(&self.colors.borrow());
-                self.tabline.flush();
-
-                if self.resize_on_flush.take() {
-                    self.shell.resize_nvim();
-                }
-
-                if self.css_on_flush.take() {
-                    let colors = self.colors.borrow();
-                    let linespace = self.font.borrow().linespace() / SCALE;
-                    let pmenu = colors.get_hl_group(&HlGroup::Pmenu);
-                    let pmenu_sel = colors.get_hl_group(&HlGroup::PmenuSel);
-                    let pmenu_thumb = colors.get_hl_group(&HlGroup::PmenuThumb);
-                    let pmenu_bar = colors.get_hl_group(&HlGroup::PmenuSbar);
-                    let msgsep = colors.get_hl_group(&HlGroup::MsgSeparator);
-                    let tablinefill = colors.get_hl_group(&HlGroup::TabLineFill);
-                    let tabline = colors.get_hl_group(&HlGroup::TabLine);
-                    let tablinesel = colors.get_hl_group(&HlGroup::TabLineSel);
-                    // TODO(ville): Figure out better headerbar colors.
-                    let menu = colors.get_hl_group(&HlGroup::Menu);
-                    // TODO(ville): It might be possible to make the font
-                    // be set in CSS, instead of through custom property.
-                    // Tho' at least linespace value (e.g. line-height css
-                    // property) was added as recently as gtk version 4.6.
-                    self.css_provider.load_from_data(&format!(
-                        include_str!("style.css"),
-                        bg = colors.bg.as_hex(),
-                        fg = colors.fg.as_hex(),
-                        msgsep = msgsep.fg().as_hex(),
-                        pmenu_fg = pmenu.fg().as_hex(),
-                        pmenu_bg = pmenu.bg().as_hex(),
-                        pmenu_sel_fg = pmenu_sel.fg().as_hex(),
-                        pmenu_sel_bg = pmenu_sel.bg().as_hex(),
-                        pmenusbar_bg = pmenu_bar.bg().as_hex(),
-                        pmenuthumb_bg = pmenu_thumb.bg().as_hex(),
-                        tabline_bg = tabline.bg().as_hex(),
-                        tabline_fg = tabline.fg().as_hex(),
-                        tablinefill_bg = tablinefill.bg().as_hex(),
-                        tablinesel_bg = tablinesel.bg().as_hex(),
-                        tablinesel_fg = tablinesel.fg().as_hex(),
-                        linespace_top = (linespace / 2.0).ceil().max(0.0),
-                        linespace_bottom = (linespace / 2.0).floor().max(0.0),
-                        menu_bg = menu.bg().as_hex(),
-                        menu_fg = menu.fg().as_hex(),
-                        omnibar_pad = 5,
-                        font = self.font.borrow().to_css(),
-                    ));
-                }
+                self.flush();
+                self.schedule_draw();
             }
 
             // linegrid events
@@ -298,6 +1721,7 @@ impl AppWindow {
             UiEvent::HlAttrDefine(events) => events.into_iter().for_each(|event| {
                 let mut colors = self.colors.borrow_mut();
                 colors.hls.insert(event.id, event.rgb_attrs.into());
+                self.hls_on_flush.set(true);
             }),
             UiEvent::HlGroupSet(events) => events.into_iter().for_each(|event| {
                 self.handle_hl_group_set(event);
@@ -311,9 +1735,11 @@ impl AppWindow {
             UiEvent::GridDestroy(events) => events
                 .into_iter()
                 .for_each(|event| self.shell.handle_grid_destroy(event)),
-            UiEvent::GridCursorGoto(events) => events.into_iter().for_each(|event| {
-                self.shell.handle_grid_cursor_goto(event);
-            }),
+            UiEvent::GridCursorGoto(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.pending_cursor_goto.replace(Some(event));
+                }
+            }
             UiEvent::GridScroll(events) => events
                 .into_iter()
                 .for_each(|event| self.shell.handle_grid_scroll(event)),
@@ -335,10 +1761,37 @@ impl AppWindow {
             UiEvent::WinClose(events) => events
                 .into_iter()
                 .for_each(|event| self.shell.handle_win_close(event)),
+            UiEvent::MsgShow(events) => events
+                .into_iter()
+                .for_each(|event| self.handle_msg_show(event)),
+            UiEvent::MsgClear => self.msg_area.clear(),
+            UiEvent::MsgHistoryShow(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.msg_history.show(&event.entries, &self.colors.borrow());
+                }
+            }
+            UiEvent::MsgHistoryClear => self.msg_history.clear(),
+            UiEvent::MsgShowmode(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.msg_status
+                        .set_showmode(&event.content, &self.colors.borrow());
+                }
+            }
+            UiEvent::MsgShowcmd(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.msg_status
+                        .set_showcmd(&event.content, &self.colors.borrow());
+                }
+            }
+            UiEvent::MsgRuler(events) => {
+                if let Some(event) = events.into_iter().last() {
+                    self.msg_status
+                        .set_ruler(&event.content, &self.colors.borrow());
+                }
+            }
             UiEvent::MsgSetPos(events) => events
                 .into_iter()
                 .for_each(|event| self.shell.handle_msg_set_pos(event, &self.font.borrow())),
-            // TODO(ville): Scrollbars?
             UiEvent::WinViewport(events) => events
                 .into_iter()
                 .for_each(|event| self.shell.handle_win_viewport(event)),
@@ -353,9 +1806,10 @@ impl AppWindow {
             UiEvent::PopupmenuHide => self.handle_popupmenu_hide(),
 
             // tabline events
-            UiEvent::TablineUpdate(events) => events
-                .into_iter()
-                .for_each(|event| self.tabline.handle_tabline_update(event)),
+            UiEvent::TablineUpdate(events) => events.into_iter().for_each(|event| {
+                self.tabline.handle_tabline_update(event);
+                self.shell.set_accent(self.tabline.current_accent());
+            }),
 
             // cmdline events
             UiEvent::CmdlineShow(events) => events.into_iter().for_each(|event| {
@@ -386,9 +1840,28 @@ impl AppWindow {
     }
 
     fn handle_option_set(&self, event: OptionSet) {
+        // Replays use a fixed font, so the output doesn't depend on the
+        // fonts installed.
+        if self.args.borrow().replay.is_some()
+            && matches!(
+                event,
+                OptionSet::Linespace(_) | OptionSet::Guifont(_) | OptionSet::Guifontwide(_)
+            )
+        {
+            return;
+        }
+
         match event {
             OptionSet::Linespace(linespace) => {
-                let font = Font::new(&self.font.borrow().guifont(), linespace as f32);
+                let font = {
+                    let font = self.font.borrow();
+                    Font::new_with_features(
+                        &font.guifont(),
+                        &font.guifontwide(),
+                        linespace as f32,
+                        &font.features(),
+                    )
+                };
                 self.obj().set_property("font", &font);
 
                 self.resize_on_flush.set(true);
@@ -397,11 +1870,12 @@ impl AppWindow {
                 self.omnibar.set_cmdline_linespace(linespace as f32);
             }
             OptionSet::Guifont(guifont) => {
-                let font = Font::new(&guifont, self.font.borrow().linespace() / SCALE);
-                self.obj().set_property("font", &font);
-
-                self.resize_on_flush.set(true);
-                self.css_on_flush.set(true);
+                self.guifont.replace(Some(guifont));
+                self.load_font(true);
+            }
+            OptionSet::Guifontwide(guifontwide) => {
+                self.guifontwide.replace(guifontwide);
+                self.load_font(true);
             }
             OptionSet::ShowTabline(show) => {
                 self.obj()
@@ -410,10 +1884,647 @@ impl AppWindow {
                 self.resize_on_flush.set(true);
                 self.css_on_flush.set(true);
             }
+            OptionSet::Pumblend(blend) => {
+                self.pumblend.set(blend.clamp(0, 100));
+                self.css_on_flush.set(true);
+            }
+            OptionSet::Ambiwidth(ambiwidth) => {
+                self.nvim_ambiwidth.replace(ambiwidth);
+            }
             OptionSet::Unknown(_) => {}
         }
     }
 
+    fn load_css(&self) {
+        let colors = self.colors.borrow();
+        let linespace = self.font.borrow().linespace() / SCALE;
+        let pmenu = colors.get_hl_group(&HlGroup::Pmenu);
+        let pmenu_sel = colors.get_hl_group(&HlGroup::PmenuSel);
+        let pmenu_thumb = colors.get_hl_group(&HlGroup::PmenuThumb);
+        let pmenu_bar = colors.get_hl_group(&HlGroup::PmenuSbar);
+        let pmenu_kind = colors.get_hl_group_or(&HlGroup::PmenuKind, &HlGroup::Pmenu);
+        let pmenu_kind_sel = colors.get_hl_group_or(&HlGroup::PmenuKindSel, &HlGroup::PmenuSel);
+        let pmenu_extra = colors.get_hl_group_or(&HlGroup::PmenuExtra, &HlGroup::Pmenu);
+        let pmenu_extra_sel = colors.get_hl_group_or(&HlGroup::PmenuExtraSel, &HlGroup::PmenuSel);
+        let msgsep = colors.get_hl_group(&HlGroup::MsgSeparator);
+        let tablinefill = colors.get_hl_group(&HlGroup::TabLineFill);
+        let tabline = colors.get_hl_group(&HlGroup::TabLine);
+        let tablinesel = colors.get_hl_group(&HlGroup::TabLineSel);
+        // TODO(ville): Figure out better headerbar colors.
+        let menu = colors.get_hl_group(&HlGroup::Menu);
+        let warning = colors.get_hl_group(&HlGroup::WarningMsg);
+        let error = colors.get_hl_group(&HlGroup::ErrorMsg);
+        // TODO(ville): It might be possible to make the font
+        // be set in CSS, instead of through custom property.
+        // Tho' at least linespace value (e.g. line-height css
+        // property) was added as recently as gtk version 4.6.
+        self.css_provider.load_from_data(&format!(
+            include_str!("style.css"),
+            bg = colors.bg.as_hex(),
+            fg = colors.fg.as_hex(),
+            msgsep_fg = msgsep.fg().as_hex(),
+            msgsep_bg = msgsep.bg().as_hex(),
+            pmenu_fg = pmenu.fg().as_hex(),
+            pmenu_bg = pmenu.bg().as_hex(),
+            pmenu_sel_fg = pmenu_sel.fg().as_hex(),
+            pmenu_sel_bg = pmenu_sel.bg().as_hex(),
+            pmenusbar_bg = pmenu_bar.bg().as_hex(),
+            pmenuthumb_bg = pmenu_thumb.bg().as_hex(),
+            pmenu_alpha = 1.0 - self.pumblend.get() as f64 / 100.0,
+            pmenu_kind_fg = pmenu_kind.fg().as_hex(),
+            pmenu_kind_sel_fg = pmenu_kind_sel.fg().as_hex(),
+            pmenu_extra_fg = pmenu_extra.fg().as_hex(),
+            pmenu_extra_sel_fg = pmenu_extra_sel.fg().as_hex(),
+            tabline_bg = tabline.bg().as_hex(),
+            tabline_fg = tabline.fg().as_hex(),
+            tablinefill_bg = tablinefill.bg().as_hex(),
+            tablinesel_bg = tablinesel.bg().as_hex(),
+            tablinesel_fg = tablinesel.fg().as_hex(),
+            linespace_top = (linespace / 2.0).ceil().max(0.0),
+            linespace_bottom = (linespace / 2.0).floor().max(0.0),
+            menu_bg = menu.bg().as_hex(),
+            menu_fg = menu.fg().as_hex(),
+            warning_fg = warning.fg().as_hex(),
+            error_fg = error.fg().as_hex(),
+            omnibar_pad = 5,
+            font = self.font.borrow().to_css(),
+        ));
+    }
+
+    /// Sets the font size (in points), keeping the rest of the font as is.
+    fn set_font_size(&self, size: f32) {
+        // Round to half points to avoid excessive re-rendering (e.g. when
+        // the pinch gesture is used).
+        let size = ((size * 2.0).round() / 2.0).clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+        if size == self.font.borrow().size() {
+            return;
+        }
+
+        self.font_size.set(Some(size));
+        let font = self.font.borrow().with_size(size);
+        self.apply_font(font);
+    }
+
+    /// Resets the font size back to the size set through guifont.
+    fn reset_font_size(&self) {
+        self.font_size.set(None);
+
+        let guifont = self
+            .guifont
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| Font::default().guifont().clone());
+        let font = {
+            let font = self.font.borrow();
+            Font::new_with_features(
+                &guifont,
+                &font.guifontwide(),
+                font.linespace() / SCALE,
+                &font.features(),
+            )
+        };
+        self.apply_font(font);
+    }
+
+    fn set_font(&self, font: Font) {
+        font.set_style_families(&self.bold_font.borrow(), &self.italic_font.borrow());
+        self.font.replace(font);
+    }
+
+    /// Reads the previous session's font while nvim is starting, and loads
+    /// it in case nvim doesn't beat us to it. Nvim's guifont is likely the
+    /// same, so it's quick to load after this.
+    fn preload_font(&self) {
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            let cached = match gio::spawn_blocking(read_cached_font).await {
+                Ok(Some(cached)) => cached,
+                _ => return,
+            };
+
+            let imp = obj.imp();
+            imp.cached_font.replace(Some(cached.clone()));
+            // Nvim was faster.
+            if imp.guifont.borrow().is_some() {
+                return;
+            }
+
+            let (guifont, guifontwide) = cached;
+            imp.guifont.replace(Some(guifont));
+            imp.guifontwide.replace(guifontwide);
+            imp.load_font(false);
+        }));
+    }
+
+    /// Loads the guifont and guifontwide off the main thread, and swaps the
+    /// font once it's ready. Until then, the current font (at first, the
+    /// system's monospace font) is kept, so slow fonts (e.g. large CJK
+    /// fallback fonts) don't stall the ui.
+    ///
+    /// With `from_nvim`, the font is remembered for the next session.
+    fn load_font(&self, from_nvim: bool) {
+        let guifont = self
+            .guifont
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| Font::default().guifont().clone());
+        let guifontwide = self.guifontwide.borrow().clone();
+        let font_map = self.obj().pango_context().font_map().map(|fm| fm.type_());
+
+        let load = self.font_loads.get().wrapping_add(1);
+        self.font_loads.set(load);
+
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            let (family, wide) = (guifont.clone(), guifontwide.clone());
+            let res = gio::spawn_blocking(move || {
+                let start = std::time::Instant::now();
+                if let Some(font_map) = font_map {
+                    font::warm_up(font_map, &family, &wide);
+                }
+                debug!("loaded font {:?} in {:?}", family, start.elapsed());
+            })
+            .await;
+            if res.is_err() {
+                warn!("failed to load font {:?}", guifont);
+            }
+
+            let imp = obj.imp();
+            // Superseded by a later guifont or guifontwide.
+            if imp.font_loads.get() != load {
+                return;
+            }
+
+            let mut font = {
+                let font = imp.font.borrow();
+                Font::new_with_features(
+                    &guifont,
+                    &guifontwide,
+                    font.linespace() / SCALE,
+                    &font.features(),
+                )
+            };
+            if let Some(size) = imp.font_size.get() {
+                font = font.with_size(size);
+            }
+            imp.apply_font(font);
+
+            if from_nvim {
+                imp.write_cached_font(guifont, guifontwide);
+            }
+        }));
+    }
+
+    /// Remembers the font for the next session, if it changed.
+    fn write_cached_font(&self, guifont: String, guifontwide: String) {
+        let font = Some((guifont, guifontwide));
+        if *self.cached_font.borrow() == font {
+            return;
+        }
+
+        self.cached_font.replace(font.clone());
+        if let Some((guifont, guifontwide)) = font {
+            gio::spawn_blocking(move || write_cached_font(&guifont, &guifontwide));
+        }
+    }
+
+    fn apply_font(&self, font: Font) {
+        self.obj().set_property("font", &font);
+
+        // The font change is initiated by us, so we can't wait for the
+        // next flush to apply it.
+        self.shell.handle_flush(&self.colors.borrow());
+        self.shell.queue_draw_grids();
+        self.shell.resize_nvim();
+        self.load_css();
+        // The new font might have different width glyphs.
+        self.sync_ambiwidth();
+    }
+
+    /// Sets nvim's 'ambiwidth' as set by the user, so nvim's cell widths
+    /// match the glyphs of ambiguous width characters.
+    fn sync_ambiwidth(&self) {
+        let double = match self.ambiwidth.get() {
+            Some(Ambiwidth::Auto) => self.font.borrow().has_wide_ambiguous(),
+            Some(Ambiwidth::Double) => true,
+            Some(Ambiwidth::Single) => false,
+            None => return,
+        };
+        let value = if double { "double" } else { "single" };
+        if *self.nvim_ambiwidth.borrow() == value {
+            return;
+        }
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_set_option_value("ambiwidth", &rmpv::Value::from(value), &dict![])
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            // E.g. 'listchars' or 'fillchars' has ambiguous width characters.
+            if let Err(err) = res.await {
+                warn!("failed to set ambiwidth: {:?}", err);
+            }
+        }));
+    }
+
+    /// Handles the font size shortcuts (ctrl +/-/0).
+    fn font_size_shortcut(&self, keyval: gdk::Key, state: gdk::ModifierType) -> bool {
+        if !state.contains(gdk::ModifierType::CONTROL_MASK) {
+            return false;
+        }
+
+        let size = self.font.borrow().size();
+        match keyval {
+            gdk::Key::plus | gdk::Key::equal | gdk::Key::KP_Add => {
+                self.set_font_size(size + 1.0);
+            }
+            gdk::Key::minus | gdk::Key::KP_Subtract => {
+                self.set_font_size(size - 1.0);
+            }
+            gdk::Key::_0 | gdk::Key::KP_0 => {
+                self.reset_font_size();
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Handles gnvim's own ctrl+shift shortcuts.
+    fn gui_shortcut(&self, keyval: gdk::Key, state: gdk::ModifierType) -> bool {
+        if !state.contains(gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK) {
+            return false;
+        }
+
+        match keyval {
+            gdk::Key::F => self.findbar.open(),
+            gdk::Key::M => self.toggle_msg_history(),
+            gdk::Key::T => self.reopen_closed_window(),
+            gdk::Key::Z => self.set_focus_mode(self.shell.focus_width() == 0),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn handle_focus_mode(&self, event: FocusMode) {
+        if let Some(width) = event.width {
+            self.focus_width.set(Some(width));
+        }
+        if let Some(dim) = event.dim {
+            self.shell.set_focus_dim(dim);
+        }
+
+        let enabled = event.enabled.unwrap_or(self.shell.focus_width() == 0);
+        self.set_focus_mode(enabled);
+    }
+
+    fn handle_tab_accents(&self, accents: Vec<TabAccent>) {
+        let accents = accents
+            .into_iter()
+            .filter_map(|accent| match gdk::RGBA::parse(accent.color.as_str()) {
+                Ok(rgba) => Some((accent.tab, Color::from(rgba))),
+                Err(err) => {
+                    warn!("invalid accent color {:?}: {}", accent.color, err);
+                    None
+                }
+            })
+            .collect();
+
+        self.tabline.set_accents(accents);
+        self.shell.set_accent(self.tabline.current_accent());
+    }
+
+    /// Focus mode centers the text, and hides everything around it (i.e.
+    /// the header bar, the tabline and the message toasts).
+    fn set_focus_mode(&self, enabled: bool) {
+        let width = if enabled {
+            self.focus_width.get().unwrap_or(DEFAULT_FOCUS_WIDTH)
+        } else {
+            0
+        };
+
+        self.shell.set_focus_width(width);
+        self.headerbar.set_visible(!enabled);
+        self.tabline_revealer.set_reveal_child(!enabled);
+        self.toasts.set_visible(!enabled);
+    }
+
+    /// Shows the about dialog, with the information needed for bug reports.
+    fn show_about(&self) {
+        let obj = self.obj();
+        spawn_local!(clone!(@weak obj => async move {
+            // Without nvim, the dialog still has gnvim's information.
+            let nvim_version = match obj.nvim().nvim_get_api_info().await {
+                Ok(res) => match res.await {
+                    Ok(info) => nvim_version(&info),
+                    Err(err) => {
+                        warn!("failed to get nvim api info: {:?}", err);
+                        None
+                    }
+                },
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    None
+                }
+            };
+
+            let uiopts = obj.imp().ui_options();
+            let extensions = [
+                ("ext_cmdline", uiopts.ext_cmdline),
+                ("ext_hlstate", uiopts.ext_hlstate),
+                ("ext_linegrid", uiopts.ext_linegrid),
+                ("ext_messages", uiopts.ext_messages),
+                ("ext_multigrid", uiopts.ext_multigrid),
+                ("ext_popupmenu", uiopts.ext_popupmenu),
+                ("ext_tabline", uiopts.ext_tabline),
+                ("ext_termcolors", uiopts.ext_termcolors),
+            ]
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+            let (renderer, workarounds) = match *obj.imp().renderer_info.borrow() {
+                Some(ref info) => (info.to_string(), info.workarounds().to_string()),
+                None => (obj.renderer().type_().name().to_string(), String::from("none")),
+            };
+
+            let info = format!(
+                "Neovim: {}\nUi extensions: {}\nRenderer: {}\nWorkarounds: {}\nGTK: {}.{}.{}\n",
+                nvim_version.as_deref().unwrap_or("unknown"),
+                extensions,
+                renderer,
+                workarounds,
+                gtk::major_version(),
+                gtk::minor_version(),
+                gtk::micro_version(),
+            );
+
+            gtk::AboutDialog::builder()
+                .transient_for(&obj)
+                .modal(true)
+                .program_name("GNvim")
+                .version(crate::VERSION)
+                .logo_icon_name("gnvim")
+                .website("https://github.com/vhakulinen/gnvim")
+                .license_type(gtk::License::MitX11)
+                .system_information(info)
+                .build()
+                .present();
+        }));
+    }
+
+    pub fn open_files(
+        &self,
+        files: Vec<FileArg>,
+        cmd: &str,
+        wait: Option<gio::ApplicationCommandLine>,
+    ) {
+        let files = files
+            .into_iter()
+            .map(|file| {
+                MapBuilder::new()
+                    .insert("path", &*file.path.to_string_lossy())
+                    .insert_some("line", file.line)
+                    .insert_some("col", file.col)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let cmd = rmpv::Value::from(cmd);
+        let wait = wait.map_or(rmpv::Value::Nil, |cmdline| {
+            rmpv::Value::from(self.hold_command_line(cmdline))
+        });
+
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_exec_lua(
+                    "require('gnvim').open_files(...)",
+                    vec![rmpv::Value::Array(files), cmd, wait],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to open files: {:?}", err);
+            }
+        }));
+    }
+
+    fn set_keep_open(&self, enabled: bool) {
+        self.keep_open.set(enabled);
+
+        // Have nvim save the session for the start screen.
+        let path = session_path(&self.args.borrow().servername);
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_exec_lua(
+                    "require('gnvim').save_session_on_exit(...)",
+                    vec![rmpv::Value::from(&*path.to_string_lossy()), rmpv::Value::from(enabled)],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to set up session saving: {:?}", err);
+            }
+        }));
+    }
+
+    /// Holds on to `cmdline` until `wait_done` with the returned id, or until
+    /// nvim exits.
+    pub fn hold_command_line(&self, cmdline: gio::ApplicationCommandLine) -> u32 {
+        let id = self.next_wait.get();
+        self.next_wait.set(id.wrapping_add(1));
+        self.waits.borrow_mut().insert(id, cmdline);
+        id
+    }
+
+    /// Saves a copy of the current buffer to a file picked by the user.
+    fn export_buffer(&self) {
+        let obj = self.obj();
+        let dialog = gtk::FileChooserNative::new(
+            Some("Export Buffer"),
+            Some(&*obj),
+            gtk::FileChooserAction::Save,
+            Some("Export"),
+            None,
+        );
+        dialog.connect_response(clone!(@weak obj => move |dialog, response| {
+            let imp = obj.imp();
+            imp.export_dialog.take();
+
+            let file = match dialog.file() {
+                Some(file) if response == gtk::ResponseType::Accept => file,
+                _ => return,
+            };
+
+            spawn_local!(clone!(@weak obj => async move {
+                let imp = obj.imp();
+                match imp.write_buffer(&file).await {
+                    Ok(lines) => imp.toast.show(
+                        &format!("Exported {} lines to {}", lines, file.parse_name()),
+                        Severity::Info,
+                        Some(Duration::from_secs(3)),
+                        vec![],
+                    ),
+                    Err(err) => {
+                        warn!("failed to export the buffer: {}", err);
+                        imp.toast.show(
+                            &format!("Failed to export the buffer: {}", err),
+                            Severity::Error,
+                            None,
+                            vec![],
+                        );
+                    }
+                }
+            }));
+        }));
+
+        dialog.show();
+        self.export_dialog.replace(Some(dialog));
+    }
+
+    /// Writes the lines of the current buffer to `file`. The lines are
+    /// fetched in chunks, so huge buffers don't stall the ui. Returns the
+    /// number of lines written.
+    async fn write_buffer(&self, file: &gio::File) -> Result<usize, String> {
+        let buffer = self
+            .nvim
+            .nvim_get_current_buf()
+            .await
+            .map_err(|err| format!("{:?}", err))?
+            .await
+            .map_err(|err| format!("{:?}", err))?;
+
+        let stream = file
+            .replace_future(
+                None,
+                false,
+                gio::FileCreateFlags::REPLACE_DESTINATION,
+                glib::Priority::default(),
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let chunks =
+            nvim::lines::buf_get_lines_chunked(&self.nvim, buffer, 0, -1, EXPORT_CHUNK_LINES);
+        futures::pin_mut!(chunks);
+
+        let mut count = 0;
+        while let Some(lines) = chunks.next().await {
+            let lines = lines.map_err(|err| format!("{:?}", err))?;
+            count += lines.len();
+
+            let mut text = lines.join("\n");
+            text.push('\n');
+            stream
+                .write_all_future(text.into_bytes(), glib::Priority::default())
+                .await
+                .map_err(|(_, err)| err.to_string())?;
+        }
+
+        stream
+            .close_future(glib::Priority::default())
+            .await
+            .map_err(|err| err.to_string())?;
+
+        Ok(count)
+    }
+
+    fn reopen_closed_window(&self) {
+        spawn_local!(clone!(@weak self.nvim as nvim => async move {
+            let res = match nvim
+                .nvim_exec_lua("require('gnvim').reopen_closed_window()", vec![])
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to reopen closed window: {:?}", err);
+            }
+        }));
+    }
+
+    fn set_debug_hud(&self, enabled: bool) {
+        if let Some(id) = self.debug_hud_timer.take() {
+            id.remove();
+        }
+
+        self.debug_hud.set_visible(enabled);
+        if !enabled {
+            return;
+        }
+
+        self.update_debug_hud();
+
+        let obj = self.obj();
+        let id = glib::timeout_add_local(
+            DEBUG_HUD_INTERVAL,
+            clone!(@weak obj => @default-return glib::ControlFlow::Break, move || {
+                obj.imp().update_debug_hud();
+                glib::ControlFlow::Continue
+            }),
+        );
+        self.debug_hud_timer.replace(Some(id));
+    }
+
+    /// Renders the busiest rpc methods' statistics into the debug HUD.
+    fn update_debug_hud(&self) {
+        let metrics = self.nvim.metrics();
+        let ms = |v: Option<Duration>| {
+            v.map(|v| format!("{:.1}", v.as_secs_f64() * 1000.0))
+                .unwrap_or_else(|| String::from("-"))
+        };
+
+        let mut methods = metrics.iter().collect::<Vec<_>>();
+        methods.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then_with(|| a.0.cmp(b.0)));
+
+        let mut text = format!(
+            "{:<28} {:>7} {:>7} {:>7} {:>6}",
+            "method", "calls", "p50", "p99", "errors"
+        );
+        for (method, m) in methods.into_iter().take(DEBUG_HUD_METHODS) {
+            text.push_str(&format!(
+                "\n{:<28} {:>7} {:>7} {:>7} {:>6}",
+                method,
+                m.calls,
+                ms(m.percentile(0.5)),
+                ms(m.percentile(0.99)),
+                m.errors
+            ));
+        }
+
+        let unexpected: u64 = self.unexpected_notifications.borrow().values().sum();
+        text.push_str(&format!("\nunexpected notifications: {}", unexpected));
+
+        self.debug_hud.set_text(&text);
+    }
+
     async fn send_nvim_input(&self, input: String) {
         let res = self
             .nvim
@@ -422,7 +2533,9 @@ impl AppWindow {
             .expect("call to nvim failed");
 
         // TODO(ville): nvim_input handle the returned bytes written value.
-        res.await.expect("nvim_input failed");
+        if let Err(err) = res.await {
+            warn!("nvim_input failed: {:?}", err);
+        }
     }
 }
 
@@ -457,20 +2570,38 @@ impl AppWindow {
             return glib::Propagation::Proceed;
         }
 
-        if self.im_context.borrow().filter_keypress(&evt) {
+        // Let the find bar handle its own input.
+        if self.findbar.has_focus_within() {
+            return glib::Propagation::Proceed;
+        }
+
+        if self.font_size_shortcut(keyval, state) || self.gui_shortcut(keyval, state) {
+            return glib::Propagation::Stop;
+        }
+
+        // Don't blink while typing.
+        self.shell.reset_cursor_blink();
+
+        // Keypad keys bypass the input method, so they're sent with their own
+        // notation instead of the characters they produce.
+        if !crate::input::is_keypad(keyval) && self.im_context.borrow().filter_keypress(&evt) {
             glib::Propagation::Stop
         } else {
-            if let Some(input) = event_to_nvim_input(keyval, state) {
+            let hyper = self.hyper_modifier.borrow();
+            let hyper = hyper.as_deref().unwrap_or(crate::input::DEFAULT_HYPER);
+            if let Some(input) =
+                crate::input::event_to_nvim_input(keyval, state, &self.key_remap.borrow(), hyper)
+            {
                 spawn_local!(clone!(@weak self as this => async move {
                     this.send_nvim_input(input).await;
                 }));
 
                 return glib::Propagation::Stop;
-            } else {
-                println!(
-                    "Failed to turn input event into nvim key (keyval: {})",
-                    keyval,
-                )
+            } else if !self.secure_input.get() {
+                warn!(
+                    "failed to turn input event into nvim key (keyval: {})",
+                    keyval
+                );
             }
 
             glib::Propagation::Proceed
@@ -499,6 +2630,11 @@ impl ObjectSubclass for AppWindow {
         Omnibar::ensure_type();
         Shell::ensure_type();
         Tabline::ensure_type();
+        FindBar::ensure_type();
+        MsgArea::ensure_type();
+        MsgHistory::ensure_type();
+        MsgStatus::ensure_type();
+        Toast::ensure_type();
 
         klass.bind_template();
         klass.bind_template_callbacks();
@@ -521,45 +2657,69 @@ impl ObjectImpl for AppWindow {
             gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
 
-        let uiopts = UiOptions {
-            rgb: true,
-            ext_linegrid: true,
-            ext_multigrid: true,
-            ext_popupmenu: true,
-            ext_tabline: true,
-            ext_cmdline: true,
-            stdin_fd: self.args.borrow().stdin_fd,
-            ..Default::default()
-        };
-        let args = self.args.borrow().nvim_cmd_args();
-        let args: Vec<&OsStr> = args.iter().map(|a| a.as_ref()).collect();
-        let reader = self.nvim.open(&args, uiopts.stdin_fd.is_some());
+        self.paint_initial_background();
 
-        // Start io loop.
-        spawn_local!(clone!(@strong obj as app => async move {
-            app.imp().io_loop(reader).await;
-        }));
+        if let Some(ref text) = self.args.borrow().splash {
+            self.shell.set_splash_text(text.as_str());
+        }
 
-        // Call nvim_ui_attach.
-        spawn_local!(clone!(@weak self.nvim as nvim => async move {
-            let res = nvim
-                .nvim_set_client_info(
-                    "gnvim",
-                    // TODO(ville): Tell the version in client info.
-                    &dict![],
-                    "ui",
-                    &dict![],
-                    &dict![],
-                ).await.expect("call to nvim failed");
+        if self.args.borrow().safe_rendering {
+            self.set_safe_rendering(true);
+        }
+
+        if let Some(path) = self.args.borrow().replay.clone() {
+            let reader = match self.nvim.open_replay(&path) {
+                Ok(reader) => reader,
+                Err(err) => {
+                    warn!("failed to open replay {:?}: {}", path, err);
+                    std::process::exit(1);
+                }
+            };
 
-            res.await.expect("nvim_set_client_info failed");
+            obj.set_property("font", Font::new(&self.args.borrow().replay_font, 0.0));
+            // Animations would make the output depend on timing.
+            self.shell.set_cursor_blink_transition(0.0);
+            self.shell.set_cursor_position_transition(0.0);
+            self.shell.set_scroll_transition(0.0);
 
-            let res = nvim
-                .nvim_ui_attach(80, 30, uiopts)
-                .await.expect("call to nvim failed");
+            spawn_local!(clone!(@strong obj as app => async move {
+                app.imp().io_loop(reader).await;
+            }));
+        } else {
+            let args = self.args.borrow().clone();
+            self.attach_nvim(&args);
+            self.preload_font();
+            self.report_crashes();
+        }
 
-            res.await.expect("nvim_ui_attach failed");
+        // Follow the system's power profile.
+        let monitor = gio::PowerProfileMonitor::dup_default();
+        monitor.connect_power_saver_enabled_notify(clone!(@weak obj => move |_| {
+            obj.imp().update_power_saver();
         }));
+        self.power_profile_monitor.replace(Some(monitor));
+        self.update_power_saver();
+
+        // Error notifications are stale once the user is back.
+        obj.connect_is_active_notify(|obj| {
+            if let Some(app) = obj.application().filter(|_| obj.is_active()) {
+                app.withdraw_notification(&error_notification_id(obj));
+            }
+        });
+
+        // Keep track of the monitors we're on, so flushes can match their
+        // refresh rate.
+        obj.connect_realize(|obj| {
+            let surface = obj.surface();
+            surface.connect_enter_monitor(clone!(@weak obj => move |_, monitor| {
+                obj.imp().monitors.borrow_mut().push(monitor.clone());
+            }));
+            surface.connect_leave_monitor(clone!(@weak obj => move |_, monitor| {
+                obj.imp().monitors.borrow_mut().retain(|m| m != monitor);
+            }));
+
+            obj.imp().detect_renderer();
+        });
 
         // TODO(ville): Figure out if we should use preedit or not.
         self.im_context.borrow().set_use_preedit(false);
@@ -569,6 +2729,45 @@ impl ObjectImpl for AppWindow {
             .set_im_context(Some(&*self.im_context.borrow()));
 
         obj.add_controller(self.event_controller_key.borrow().clone());
+
+        self.gesture_zoom
+            .connect_begin(clone!(@weak obj => move |_, _| {
+                let imp = obj.imp();
+                imp.zoom_start_size.set(imp.font.borrow().size());
+            }));
+        self.gesture_zoom
+            .connect_scale_changed(clone!(@weak obj => move |_, scale| {
+                let imp = obj.imp();
+                imp.set_font_size(imp.zoom_start_size.get() * scale as f32);
+            }));
+        self.shell.add_controller(self.gesture_zoom.clone());
+
+        // Ctrl+scroll changes the font size. Use capture phase so we get the
+        // event before the grids.
+        let mut flags = gtk::EventControllerScrollFlags::empty();
+        flags.insert(gtk::EventControllerScrollFlags::VERTICAL);
+        flags.insert(gtk::EventControllerScrollFlags::DISCRETE);
+        self.event_controller_scroll.set_flags(flags);
+        self.event_controller_scroll
+            .set_propagation_phase(gtk::PropagationPhase::Capture);
+        self.event_controller_scroll.connect_scroll(
+            clone!(@weak obj => @default-return glib::Propagation::Proceed, move |evt, _, dy| {
+                if !evt
+                    .current_event_state()
+                    .contains(gdk::ModifierType::CONTROL_MASK)
+                {
+                    return glib::Propagation::Proceed;
+                }
+
+                let imp = obj.imp();
+                let size = imp.font.borrow().size();
+                imp.set_font_size(size - dy as f32);
+
+                glib::Propagation::Stop
+            }),
+        );
+        self.shell
+            .add_controller(self.event_controller_scroll.clone());
     }
 }
 
@@ -584,16 +2783,106 @@ impl WindowImpl for AppWindow {}
 
 impl ApplicationWindowImpl for AppWindow {}
 
-fn event_to_nvim_input(keyval: gdk::Key, state: gdk::ModifierType) -> Option<String> {
-    let mut input = crate::input::modifier_to_nvim(&state);
-    let keyname = keyval.name()?;
+/// Truncated string representation of a notification's payload, for logging.
+fn summarize_payload(params: &rmpv::Value) -> String {
+    let payload = params.to_string();
+    match payload.char_indices().nth(PAYLOAD_SUMMARY_LEN) {
+        Some((idx, _)) => format!("{}... ({} bytes)", &payload[..idx], payload.len()),
+        None => payload,
+    }
+}
+
+/// Formats nvim's version (and api level) from `nvim_get_api_info`'s result.
+fn nvim_version(info: &[rmpv::Value]) -> Option<String> {
+    let version = info
+        .get(1)?
+        .as_map()?
+        .iter()
+        .find(|(k, _)| k.as_str() == Some("version"))?
+        .1
+        .as_map()?;
+    let get = |key: &str| {
+        version
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .and_then(|(_, v)| v.as_i64())
+    };
+
+    Some(format!(
+        "{}.{}.{} (api level {})",
+        get("major")?,
+        get("minor")?,
+        get("patch")?,
+        get("api_level")?,
+    ))
+}
+
+/// Id of the window's error notification. Each window replaces its previous
+/// notification.
+fn error_notification_id(window: &super::AppWindow) -> String {
+    format!("error-{}", window.id())
+}
+
+/// File for remembering the guifont and guifontwide between sessions.
+fn font_cache_path() -> PathBuf {
+    glib::user_cache_dir().join("gnvim").join("font")
+}
+
+fn read_cached_font() -> Option<(String, String)> {
+    let cached = std::fs::read_to_string(font_cache_path()).ok()?;
+    let mut lines = cached.lines();
+    let guifont = lines.next().filter(|guifont| !guifont.is_empty())?;
+
+    Some((
+        guifont.to_string(),
+        lines.next().unwrap_or_default().to_string(),
+    ))
+}
+
+fn write_cached_font(guifont: &str, guifontwide: &str) {
+    if guifont.is_empty() {
+        return;
+    }
 
-    if keyname.chars().count() > 1 {
-        let n = crate::input::keyname_to_nvim_key(keyname.as_str())?;
-        input.push_str(n);
-    } else {
-        input.push(keyval.to_unicode()?);
+    let path = font_cache_path();
+    let res = path
+        .parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::write(&path, format!("{}\n{}\n", guifont, guifontwide)));
+    if let Err(err) = res {
+        warn!("failed to write {:?}: {}", path, err);
     }
+}
+
+/// Session saved by nvim on exit, for the start screen. Each gnvim (see
+/// `--servername`) has its own.
+fn session_path(servername: &str) -> PathBuf {
+    glib::user_cache_dir()
+        .join("gnvim")
+        .join("sessions")
+        .join(format!("{}.vim", servername))
+}
+
+/// File for remembering the background color between sessions.
+fn background_cache_path() -> PathBuf {
+    glib::user_cache_dir().join("gnvim").join("background")
+}
+
+fn read_cached_background() -> Option<String> {
+    std::fs::read_to_string(background_cache_path())
+        .ok()
+        .map(|color| color.trim().to_string())
+}
 
-    Some(format!("<{}>", input))
+fn write_cached_background(bg: &Color) {
+    let path = background_cache_path();
+    let res = path
+        .parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::write(&path, format!("#{}", bg.as_hex())));
+    if let Err(err) = res {
+        warn!("failed to write {:?}: {}", path, err);
+    }
 }
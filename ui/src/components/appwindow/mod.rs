@@ -1,9 +1,10 @@
 mod imp;
 
-use glib::Object;
+use glib::{subclass::prelude::*, Object};
 use gtk::{gio, glib};
 
 use crate::arguments::Arguments;
+use crate::file_arg::FileArg;
 
 glib::wrapper! {
     pub struct AppWindow(ObjectSubclass<imp::AppWindow>)
@@ -19,4 +20,20 @@ impl AppWindow {
             .property("args", args)
             .build()
     }
+
+    /// Opens `files` in nvim, using the ex command `cmd` (e.g. `tabedit`).
+    /// `wait` is released once the files are closed.
+    pub fn open_files(
+        &self,
+        files: Vec<FileArg>,
+        cmd: &str,
+        wait: Option<gio::ApplicationCommandLine>,
+    ) {
+        self.imp().open_files(files, cmd, wait);
+    }
+
+    /// Holds on to `cmdline` until nvim exits, keeping its process waiting.
+    pub fn hold_command_line(&self, cmdline: gio::ApplicationCommandLine) {
+        self.imp().hold_command_line(cmdline);
+    }
 }
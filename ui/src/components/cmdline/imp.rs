@@ -15,6 +15,9 @@ pub struct Cmdline {
     pub main: TemplateChild<gtk::TextView>,
     #[template_child(id = "block")]
     pub block: TemplateChild<gtk::TextView>,
+    /// Scrolls the block when it doesn't fit in our max height.
+    #[template_child(id = "block-scroll")]
+    pub block_scroll: TemplateChild<gtk::ScrolledWindow>,
     #[template_child(id = "completion")]
     pub popupmenu: TemplateChild<Popupmenu>,
 
@@ -83,10 +86,16 @@ impl WidgetImpl for Cmdline {
     }
 
     fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+        let max_h = self.max_height.get();
+
+        // Let the block grow until only the main line fits below it.
+        let (_, main_h, _, _) = self.main.measure(gtk::Orientation::Vertical, width);
+        self.block_scroll
+            .set_max_content_height((max_h - main_h).max(0));
+
         self.container.allocate(width, height, baseline, None);
 
         let (_, req) = self.top.preferred_size();
-        let max_h = self.max_height.get();
         self.popupmenu.set_max_height(max_h - req.height());
         self.popupmenu.set_max_width(req.width());
     }
@@ -84,7 +84,8 @@ impl Cmdline {
 
     pub fn set_linespace(&self, space: f32) {
         let imp = self.imp();
-        let space = space / 2.0;
+        // Text views can't have negative padding.
+        let space = space.max(0.0) / 2.0;
         let above = space.ceil() as i32;
         let below = space.floor() as i32;
 
@@ -115,7 +116,8 @@ impl Cmdline {
             buf.insert_markup(&mut iter, &line);
         });
 
-        imp.block.show();
+        imp.block_scroll.set_visible(true);
+        self.scroll_block_to_end();
     }
 
     pub fn block_append(&self, event: CmdlineBlockAppend, colors: &Colors) {
@@ -132,15 +134,50 @@ impl Cmdline {
 
         buf.insert(&mut iter, "\n");
         buf.insert_markup(&mut iter, &content);
+
+        self.scroll_block_to_end();
     }
 
     pub fn block_hide(&self) {
-        self.imp().block.hide();
+        let imp = self.imp();
+        imp.block_scroll.set_visible(false);
+        imp.block.buffer().set_text("");
+    }
+
+    /// Keeps the latest lines of the block visible when it overflows.
+    fn scroll_block_to_end(&self) {
+        let imp = self.imp();
+        let buf = imp.block.buffer();
+
+        let iter = buf.end_iter();
+        let mark = buf
+            .mark("end")
+            .unwrap_or_else(|| buf.create_mark(Some("end"), &iter, false));
+        buf.move_mark(&mark, &iter);
+
+        imp.block.scroll_to_mark(&mark, 0.0, false, 0.0, 0.0);
     }
 
     pub fn popupmenu_show(&self, event: PopupmenuShow) {
         let imp = self.imp();
 
+        // The completion base is the text between the popupmenu's anchor and
+        // the cursor.
+        let buf = imp.main.buffer();
+        let base = buf
+            .mark("cursor")
+            .map(|mark| {
+                let start = buf.iter_at_offset(imp.prompt_len.get() + event.col as i32);
+                let end = buf.iter_at_mark(&mark);
+                if start < end {
+                    buf.text(&start, &end, false).to_string()
+                } else {
+                    String::new()
+                }
+            })
+            .unwrap_or_default();
+        imp.popupmenu.set_base(base);
+
         imp.popupmenu.set_items(event.items);
         imp.popupmenu.set_visible(true);
     }
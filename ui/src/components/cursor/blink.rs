@@ -1,6 +1,6 @@
 use crate::warn;
 
-use super::ease_out_cubic;
+use crate::math::ease_out_cubic;
 
 #[derive(Default, Debug, Clone, glib::Boxed)]
 #[boxed_type(name = "CursorBlink", nullable)]
@@ -38,8 +38,10 @@ impl Stage {
 }
 
 impl Blink {
+    /// Creates new blink. Returns `None` if any of `wait`, `on` or `off` is
+    /// zero, which means no blinking (see `:h guicursor`).
     pub fn new(wait: f64, on: f64, off: f64, transition: f64, t: f64) -> Option<Self> {
-        if wait == 0.0 && on == 0.0 && off == 0.0 {
+        if wait == 0.0 || on == 0.0 || off == 0.0 {
             None
         } else {
             let mut blink = Self {
@@ -81,9 +83,8 @@ impl Blink {
             return;
         }
 
-        if self.stage_end < t {
+        if self.stage_end <= t {
             self.set_stage(self.stage.next(), t);
-            return;
         }
 
         match self.stage {
@@ -105,6 +106,17 @@ impl Blink {
         }
     }
 
+    /// If the cursor is fading in or out (i.e. needs to be updated every
+    /// frame).
+    pub fn is_animating(&self) -> bool {
+        matches!(self.stage, Stage::GoingOff | Stage::GoingOn)
+    }
+
+    /// Time left in the current stage.
+    pub fn remaining(&self, t: f64) -> f64 {
+        (self.stage_end - t).max(0.0)
+    }
+
     /// Get the cursor blink's wait.
     pub fn wait(&self) -> f64 {
         self.wait
@@ -125,3 +137,39 @@ impl Blink {
         self.transition
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Blink;
+
+    #[test]
+    fn test_blink_disabled() {
+        assert!(Blink::new(700.0, 400.0, 250.0, 0.0, 0.0).is_some());
+        assert!(Blink::new(0.0, 400.0, 250.0, 0.0, 0.0).is_none());
+        assert!(Blink::new(700.0, 0.0, 250.0, 0.0, 0.0).is_none());
+        assert!(Blink::new(700.0, 400.0, 0.0, 0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_blink_cycle() {
+        let mut blink = Blink::new(700.0, 400.0, 250.0, 100.0, 0.0).unwrap();
+        assert!(!blink.is_animating());
+        assert_eq!(blink.remaining(200.0), 500.0);
+
+        // Wait is over, start fading out.
+        blink.tick(700.0);
+        assert!(blink.is_animating());
+        assert_eq!(blink.alpha, 1.0);
+
+        // Faded out.
+        blink.tick(800.0);
+        assert!(!blink.is_animating());
+        assert_eq!(blink.alpha, 0.0);
+        assert_eq!(blink.remaining(800.0), 250.0);
+
+        // Typing resets the cycle.
+        blink.reset_to_wait(900.0);
+        assert!(!blink.is_animating());
+        assert_eq!(blink.alpha, 1.0);
+    }
+}
@@ -1,9 +1,15 @@
 use std::cell::{Cell, RefCell};
+use std::time::Duration;
 
 use gtk::subclass::prelude::*;
-use gtk::{glib, graphene, gsk, prelude::*};
+use gtk::{
+    glib::{self, clone},
+    graphene, gsk,
+    prelude::*,
+};
 
 use crate::font::Font;
+use crate::math::Easing;
 use crate::SCALE;
 
 use super::blink::Blink;
@@ -16,6 +22,8 @@ pub struct Position {
     pub pos: (f64, f64),
     /// Transition time in milliseconds.
     pub transition: f64,
+    /// Easing of the transition.
+    pub easing: Easing,
     /// Is the positon already set once.
     ///
     /// If not, we must skip the transition animation to avoid jumpy cursor
@@ -30,15 +38,22 @@ pub struct Position {
 pub struct Cursor {
     #[property(set, name ="position-transition", member = transition, type = f64)]
     #[property(set, name ="y-offset", member = y_offset, type = f64)]
+    #[property(set, name = "position-easing", member = easing, type = Easing)]
     pub pos: RefCell<Position>,
 
     pub text: RefCell<String>,
     pub double_width: RefCell<bool>,
+    /// Highlight of the cell under the cursor.
+    pub cell_hl_id: RefCell<i64>,
 
     pub node: RefCell<Option<gsk::RenderNode>>,
 
+    /// Width of the cursor, relative to the cell (for the vertical bar).
     #[property(set, default = 1.0)]
     pub width_percentage: RefCell<f32>,
+    /// Height of the cursor, relative to the cell (for the horizontal bar).
+    #[property(set, default = 1.0)]
+    pub height_percentage: RefCell<f32>,
     #[property(set)]
     pub attr_id: RefCell<i64>,
 
@@ -46,13 +61,18 @@ pub struct Cursor {
     pub active: Cell<bool>,
     #[property(get, set, default = false)]
     pub busy: Cell<bool>,
+    /// If ligatures are rendered (see `GridBuffer`'s `ligatures`).
+    #[property(get, set, default = true)]
+    pub ligatures: Cell<bool>,
 
     #[property(get, set)]
     pub font: RefCell<Font>,
     #[property(set = Self::set_blink)]
     pub blink: RefCell<Option<Blink>>,
-    /// Callback id to our function that makes the cursor blink.
+    /// Callback id to our function that fades the cursor in and out.
     pub blink_tick: RefCell<Option<gtk::TickCallbackId>>,
+    /// Timeout for the end of the current (steady) blink stage.
+    pub blink_timeout: RefCell<Option<glib::SourceId>>,
     pub pos_tick: RefCell<Option<gtk::TickCallbackId>>,
 }
 
@@ -64,31 +84,90 @@ impl ObjectSubclass for Cursor {
 }
 
 impl Cursor {
-    /// Set the cursor's blink. Removes earlier tick callback, and adds the
-    /// new one if needed.
+    /// Set the cursor's blink, and starts blinking.
     fn set_blink(&self, blink: Option<Blink>) {
+        self.blink.replace(blink);
+        self.schedule_blink();
+    }
+
+    fn stop_blink(&self) {
         if let Some(id) = self.blink_tick.borrow_mut().take() {
             id.remove();
         }
+        if let Some(id) = self.blink_timeout.borrow_mut().take() {
+            id.remove();
+        }
+    }
 
-        self.blink.replace(blink);
-        if self.blink.borrow().is_none() {
+    /// Drives the blink: the steady stages (wait, on, off) are waited out
+    /// with a timeout, and only the fades are updated every frame.
+    pub fn schedule_blink(&self) {
+        self.stop_blink();
+
+        if !self.active.get() {
             return;
         }
 
-        let new_id = self.obj().add_tick_callback(|this, clock| {
-            let imp = this.imp();
-            if imp.active.get() || !imp.busy.get() {
-                if let Some(ref mut blink) = imp.blink.borrow_mut().as_mut() {
-                    blink.tick(clock.frame_time() as f64);
+        let blink = self.blink.borrow();
+        let blink = match blink.as_ref() {
+            Some(blink) => blink,
+            None => return,
+        };
+
+        let obj = self.obj();
+        if blink.is_animating() {
+            let id = obj.add_tick_callback(|this, clock| {
+                let imp = this.imp();
+                let animating = match imp.blink.borrow_mut().as_mut() {
+                    Some(blink) => {
+                        blink.tick(clock.frame_time() as f64);
+                        blink.is_animating()
+                    }
+                    None => false,
+                };
+
+                this.queue_draw();
+
+                if animating {
+                    glib::ControlFlow::Continue
+                } else {
+                    imp.blink_tick.take();
+                    // Schedule the steady stage (on or off) we ended up in.
+                    let this = this.clone();
+                    glib::idle_add_local_once(move || this.imp().schedule_blink());
+                    glib::ControlFlow::Break
                 }
-            }
+            });
+            self.blink_tick.replace(Some(id));
+        } else {
+            let remaining = blink.remaining(glib::monotonic_time() as f64);
+            // The timeout has millisecond precision. Round up, so we don't
+            // wake up just before the stage ends (and re-arm at 0ms).
+            let remaining = ((remaining / 1000.0).ceil() as u64).max(1);
+            let id = glib::timeout_add_local_once(
+                Duration::from_millis(remaining),
+                clone!(@weak obj => move || {
+                    let imp = obj.imp();
+                    imp.blink_timeout.take();
+                    if let Some(blink) = imp.blink.borrow_mut().as_mut() {
+                        blink.tick(glib::monotonic_time() as f64);
+                    }
+                    obj.queue_draw();
+                    imp.schedule_blink();
+                }),
+            );
+            self.blink_timeout.replace(Some(id));
+        }
+    }
 
-            this.queue_draw();
-            glib::ControlFlow::Continue
-        });
+    /// Restarts the blink cycle with the cursor visible (e.g. when typing).
+    pub fn reset_blink(&self) {
+        if let Some(blink) = self.blink.borrow_mut().as_mut() {
+            blink.reset_to_wait(glib::monotonic_time() as f64);
+        }
 
-        self.blink_tick.replace(Some(new_id));
+        self.obj().queue_draw();
+        self.schedule_blink();
     }
 }
 
@@ -100,7 +179,8 @@ impl ObjectImpl for Cursor {
         // TODO(ville): Use custom setter instead of signals.
 
         self.obj().connect_font_notify(|this| this.queue_draw());
-        self.obj().connect_active_notify(|this| this.queue_draw());
+        self.obj()
+            .connect_active_notify(|this| this.imp().reset_blink());
         self.obj().connect_busy_notify(|this| this.queue_draw());
 
         self.obj().connect_blink_notify(|this| {
@@ -1,6 +1,6 @@
 use gtk::{glib, graphene, gsk, prelude::*, subclass::prelude::*};
 
-use crate::{colors::Colors, math::ease_out_cubic, warn, SCALE};
+use crate::{colors::Colors, warn, SCALE};
 
 use super::grid_buffer::row::Cell;
 
@@ -28,11 +28,27 @@ impl Cursor {
 
         let font = imp.font.borrow();
         let hl_id = imp.attr_id.borrow();
-        let hl = colors.get_hl(&hl_id);
-        let fg = hl.fg();
-        let bg = hl.bg();
-        // For hl id zero, we need to flip fg and bg.
-        let (fg, bg) = if *hl_id == 0 { (bg, fg) } else { (fg, bg) };
+        let cell_hl_id = imp.cell_hl_id.borrow();
+        let cell_hl = colors.get_hl(&cell_hl_id);
+        // Use the cursor's highlight (e.g. the `Cursor` group), falling back
+        // to the cell's colors for the missing ones.
+        let (fg, bg) = match colors.get_hl(&hl_id).hl_attr() {
+            Some(hl)
+                if hl.foreground.is_some()
+                    || hl.background.is_some()
+                    || hl.reverse.unwrap_or(false) =>
+            {
+                let fg = hl.foreground.as_ref().unwrap_or_else(|| cell_hl.fg());
+                let bg = hl.background.as_ref().unwrap_or_else(|| cell_hl.bg());
+                if hl.reverse.unwrap_or(false) {
+                    (bg, fg)
+                } else {
+                    (fg, bg)
+                }
+            }
+            // Without colors of its own, the cursor inverts the cell.
+            _ => (cell_hl.bg(), cell_hl.fg()),
+        };
 
         let height = font.height();
         let ch = font.char_width();
@@ -43,19 +59,52 @@ impl Cursor {
             ch / SCALE
         };
         let width = width * *imp.width_percentage.borrow();
-        let rect = graphene::Rect::new(0.0, 0.0, width, height / SCALE);
+        // The horizontal bar is at the bottom of the cell.
+        let bar_height = height / SCALE * *imp.height_percentage.borrow();
+        let rect = graphene::Rect::new(0.0, height / SCALE - bar_height, width, bar_height);
 
         let bg_node = gsk::ColorNode::new(bg, &rect).upcast();
 
-        let attrs = crate::render::create_hl_attrs(&hl_id, colors, &font);
-        let fg_node = crate::render::render_text(
-            &self.pango_context(),
-            &imp.text.borrow(),
-            fg,
-            &attrs,
-            0.0,
+        // The text keeps the cell's style (e.g. bold).
+        let attrs = crate::render::create_hl_attrs(&cell_hl_id, colors, &font, imp.ligatures.get());
+        let fg_node = crate::box_drawing::render(&imp.text.borrow(), fg, &font, 0.0)
+            .unwrap_or_else(|| {
+                crate::render::render_text(
+                    &self.pango_context(),
+                    &imp.text.borrow(),
+                    fg,
+                    &attrs,
+                    0.0,
+                    font.baseline() / SCALE,
+                )
+            });
+        let fg_node = crate::render::synthesize_style(
+            fg_node,
+            &cell_hl_id,
+            colors,
+            &font,
             font.baseline() / SCALE,
         );
+        let fg_node = if *imp.double_width.borrow() {
+            crate::render::fit_to_cells(fg_node, &font, 0.0, ch * 2.0 / SCALE)
+        } else {
+            fg_node
+        };
+
+        // Keep the cell's strikethrough visible under the cursor.
+        let fg_node = if cell_hl
+            .hl_attr()
+            .and_then(|hl| hl.strikethrough)
+            .unwrap_or(false)
+        {
+            gsk::ContainerNode::new(&[
+                fg_node,
+                crate::render::render_strikethrough(&font, fg, 0.0, font.baseline() / SCALE, width),
+            ])
+            .upcast()
+        } else {
+            fg_node
+        };
 
         // Clip the area where we're drawing. This avoids a issue when the cursor
         // is narrow, yet we're drawing our own _whole_ cell. Clipping clips
@@ -83,13 +132,12 @@ impl Cursor {
             .frame_clock()
             .expect("failed to get frame clock")
             .frame_time() as f64;
-        if let Some(ref mut blink) = *imp.blink.borrow_mut() {
-            blink.reset_to_wait(start);
-        }
+        imp.reset_blink();
 
         let font = imp.font.borrow();
         let target = (font.col_to_x(col as f64), font.row_to_y(row as f64));
         let start_pos = imp.pos.borrow().pos;
+        let easing = imp.pos.borrow().easing;
 
         let end = if imp.pos.borrow().is_set {
             start + imp.pos.borrow().transition
@@ -111,7 +159,7 @@ impl Cursor {
 
                     let imp = this.imp();
                     if now < end {
-                        let t = ease_out_cubic((now - start) / (end - start));
+                        let t = easing.apply((now - start) / (end - start));
                         let col = start_pos.0 + ((target.0 - start_pos.0) * t);
                         let row = start_pos.1 + ((target.1 - start_pos.1) * t);
                         imp.pos.borrow_mut().pos = (col, row);
@@ -135,7 +183,8 @@ impl Cursor {
         let imp = self.imp();
 
         imp.text.replace(cell.text.clone());
-        imp.double_width.replace(cell.double_width);
+        imp.cell_hl_id.replace(cell.hl_id);
+        self.set_double_width(cell.double_width);
         imp.pos.borrow_mut().grid = (col, row);
 
         self.move_to_transition(col, row);
@@ -144,11 +193,27 @@ impl Cursor {
         imp.node.replace(None);
     }
 
-    pub fn set_text(&self, text: String) {
+    /// Restarts the blink cycle, keeping the cursor visible for now.
+    pub fn reset_blink(&self) {
+        self.imp().reset_blink();
+    }
+
+    /// Updates the content of the cell under the cursor.
+    pub fn set_content(&self, cell: &Cell) {
         let imp = self.imp();
-        imp.text.replace(text);
+        imp.text.replace(cell.text.clone());
+        imp.cell_hl_id.replace(cell.hl_id);
+        self.set_double_width(cell.double_width);
         imp.node.replace(None);
     }
+
+    /// Sets if the cursor is on a double width cell, in which case the
+    /// cursor spans two cells.
+    fn set_double_width(&self, double_width: bool) {
+        if self.imp().double_width.replace(double_width) != double_width {
+            self.queue_resize();
+        }
+    }
 }
 
 impl Default for Cursor {
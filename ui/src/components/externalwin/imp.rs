@@ -11,7 +11,7 @@ use gtk::{
 };
 use nvim::NeovimApi;
 
-use crate::{components::Grid, spawn_local};
+use crate::{components::Grid, spawn_local, warn};
 
 #[derive(Default)]
 pub struct ExternalWindow {
@@ -42,7 +42,9 @@ impl ExternalWindow {
                         .await
                         .unwrap();
 
-                    res.await.expect("nvim_ui_try_resize failed");
+                    if let Err(err) = res.await {
+                        warn!("nvim_ui_try_resize failed: {:?}", err);
+                    }
                 }));
 
                 // Clear after our selves, so we don't try to remove
@@ -123,6 +125,13 @@ impl ObjectImpl for ExternalWindow {
             _ => unimplemented!(),
         };
     }
+
+    fn dispose(&self) {
+        // The grid might be already destroyed, don't try to resize it.
+        if let Some(id) = self.resize_id.take() {
+            id.remove();
+        }
+    }
 }
 
 impl WidgetImpl for ExternalWindow {
@@ -145,4 +154,41 @@ impl WidgetImpl for ExternalWindow {
     }
 }
 
-impl WindowImpl for ExternalWindow {}
+impl WindowImpl for ExternalWindow {
+    fn close_request(&self) -> glib::Propagation {
+        let obj = self.obj();
+        let win = match self.grid.borrow().nvim_window_handle() {
+            Some(win) => win,
+            None => return glib::Propagation::Proceed,
+        };
+
+        // Dock the window back. We're destroyed once nvim has positioned the
+        // window in the main window. If that doesn't happen, we close like
+        // any other window.
+        spawn_local!(clone!(@weak obj => async move {
+            let docked = match obj
+                .nvim()
+                .nvim_exec_lua("return require('gnvim').dock_win(...)", vec![rmpv::Value::from(win)])
+                .await
+            {
+                Ok(res) => match res.await {
+                    Ok(docked) => docked.as_bool().unwrap_or(false),
+                    Err(err) => {
+                        warn!("failed to dock window: {:?}", err);
+                        false
+                    }
+                },
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    false
+                }
+            };
+
+            if !docked {
+                obj.destroy();
+            }
+        }));
+
+        glib::Propagation::Stop
+    }
+}
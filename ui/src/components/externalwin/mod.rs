@@ -17,11 +17,11 @@ impl ExternalWindow {
             .property("main-window", parent)
             .property("transient-for", parent)
             .property("grid", grid)
-            .property("deletable", false)
+            .property("deletable", true)
             .build()
     }
 
-    fn nvim(&self) -> Neovim {
+    pub(crate) fn nvim(&self) -> Neovim {
         self.imp().grid.borrow().nvim()
     }
 
@@ -0,0 +1,113 @@
+use std::cell::RefCell;
+
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::NeovimApi;
+
+use crate::nvim::Neovim;
+use crate::warn;
+
+#[derive(gtk::CompositeTemplate, glib::Properties, Default)]
+#[properties(wrapper_type = super::FindBar)]
+#[template(resource = "/com/github/vhakulinen/gnvim/findbar.ui")]
+pub struct FindBar {
+    #[template_child(id = "entry")]
+    pub entry: TemplateChild<gtk::SearchEntry>,
+    /// Label for the match count.
+    #[template_child(id = "count")]
+    pub count: TemplateChild<gtk::Label>,
+
+    #[property(get, set)]
+    pub nvim: RefCell<Neovim>,
+}
+
+impl FindBar {
+    /// Searches the entry's text with nvim's `search()`, and updates the
+    /// match count.
+    async fn find(&self, flags: &str) {
+        let pattern = self.entry.text();
+        if pattern.is_empty() {
+            self.count.set_text("");
+            return;
+        }
+
+        let nvim = self.nvim.borrow().clone();
+        let res = match nvim
+            .nvim_exec_lua(
+                "return require('gnvim').find(...)",
+                vec![pattern.as_str().into(), flags.into()],
+            )
+            .await
+        {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("call to nvim failed: {:?}", err);
+                return;
+            }
+        };
+
+        match res.await {
+            Ok(count) => {
+                let current = count[0].as_i64().unwrap_or(0);
+                let total = count[1].as_i64().unwrap_or(0);
+                self.count.set_text(&format!("{}/{}", current, total));
+            }
+            Err(err) => warn!("find failed: {:?}", err),
+        }
+    }
+}
+
+#[gtk::template_callbacks]
+impl FindBar {
+    #[template_callback]
+    async fn search_changed(&self) {
+        // Include the match under the cursor, so the cursor doesn't jump
+        // around while typing.
+        self.find("c").await;
+    }
+
+    #[template_callback]
+    async fn find_next(&self) {
+        self.find("").await;
+    }
+
+    #[template_callback]
+    async fn find_prev(&self) {
+        self.find("b").await;
+    }
+
+    #[template_callback]
+    fn close(&self) {
+        self.obj().close();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for FindBar {
+    const NAME: &'static str = "FindBar";
+    type Type = super::FindBar;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+        klass.set_css_name("findbar");
+
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for FindBar {
+    fn dispose(&self) {
+        while let Some(child) = self.obj().first_child() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for FindBar {}
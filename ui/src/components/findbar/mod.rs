@@ -0,0 +1,41 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+mod imp;
+
+glib::wrapper! {
+    /// Find bar that drives nvim's search.
+    pub struct FindBar(ObjectSubclass<imp::FindBar>)
+        @extends gtk::Widget,
+        @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
+}
+
+impl FindBar {
+    /// Shows the find bar and focuses the search entry.
+    pub fn open(&self) {
+        let imp = self.imp();
+
+        self.set_visible(true);
+        imp.entry.grab_focus();
+        imp.entry.select_region(0, -1);
+    }
+
+    /// Hides the find bar, and gives the focus back to the window.
+    pub fn close(&self) {
+        self.set_visible(false);
+
+        if let Some(root) = self.root() {
+            root.set_focus(None::<&gtk::Widget>);
+        }
+    }
+
+    /// If the find bar currently has the keyboard focus.
+    pub fn has_focus_within(&self) -> bool {
+        self.is_visible() && self.focus_child().is_some()
+    }
+}
+
+impl Default for FindBar {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
@@ -3,6 +3,8 @@ use std::cell::{Cell, RefCell};
 use gtk::subclass::prelude::*;
 use gtk::{glib, gsk, prelude::*};
 
+use crate::components::Fixedz;
+
 #[derive(Default, glib::Properties)]
 #[properties(wrapper_type = super::Child)]
 pub struct Child {
@@ -29,6 +31,11 @@ impl ObjectImpl for Child {
         });
 
         self.obj().connect_z_index_notify(|this| {
+            let widget = this.child_widget();
+            if let Some(fixed) = widget.parent().and_downcast::<Fixedz>() {
+                fixed.restack(&widget);
+            }
+
             gtk::prelude::LayoutChildExt::layout_manager(this).layout_changed();
         });
     }
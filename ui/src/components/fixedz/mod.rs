@@ -1,5 +1,7 @@
 use gtk::{glib, gsk, prelude::*, subclass::prelude::*};
 
+use crate::child_iter::IterChildren;
+
 mod imp;
 #[path = "./layout-child/mod.rs"]
 mod layout_child;
@@ -31,6 +33,10 @@ impl Fixedz {
             .set_z_index(z);
     }
 
+    pub fn zindex(&self, widget: &impl IsA<gtk::Widget>) -> i64 {
+        self.imp().layout_manager.layout_child(widget).z_index()
+    }
+
     pub fn move_(&self, widget: &impl IsA<gtk::Widget>, x: f32, y: f32) {
         self.imp()
             .layout_manager
@@ -40,12 +46,27 @@ impl Fixedz {
 
     pub fn put(&self, widget: &impl IsA<gtk::Widget>, x: f32, y: f32) {
         widget.set_parent(self);
+        self.restack(widget.upcast_ref());
 
         self.imp()
             .layout_manager
             .layout_child(widget)
             .set_position_xy(x, y);
     }
+
+    /// Moves `widget` among its siblings, so that our children are in
+    /// z-index order. Besides the drawing order, this is the order in which
+    /// the widget under the pointer is found (from the last child to the
+    /// first).
+    fn restack(&self, widget: &gtk::Widget) {
+        let z = self.zindex(widget);
+        let below = self
+            .iter_children()
+            .filter(|child| child != widget && self.zindex(child) <= z)
+            .last();
+
+        widget.insert_after(self, below.as_ref());
+    }
 }
 
 impl Default for Fixedz {
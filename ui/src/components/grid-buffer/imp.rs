@@ -1,14 +1,16 @@
 use std::cell::{self, RefCell};
 
 use gtk::subclass::prelude::*;
-use gtk::{glib, graphene, gsk, prelude::*};
+use gtk::{gdk, glib, graphene, gsk, prelude::*};
 
+use crate::colors::{Color, Colors};
 use crate::font::Font;
 use crate::math::ease_out_cubic;
+use crate::render::EmojiPresentation;
 use crate::{some_or_return, warn, SCALE};
 
 use super::row::Cell;
-use super::Row;
+use super::{RenderStats, Row};
 
 #[derive(glib::Properties, Default)]
 #[properties(wrapper_type = super::GridBuffer)]
@@ -19,6 +21,14 @@ pub struct GridBuffer {
     pub row_nodes: RefCell<Vec<gsk::RenderNode>>,
     /// Background nodes.
     pub background_nodes: RefCell<Vec<gsk::RenderNode>>,
+    /// Default background color from the latest `flush` event.
+    pub default_bg: cell::Cell<Color>,
+    /// Background color overriding the default one (e.g. through
+    /// 'winhighlight').
+    pub normal_bg: cell::Cell<Option<Color>>,
+    /// Transparency of the backgrounds, from 0 (opaque) to 100 (e.g. from
+    /// 'winblend').
+    pub blend: cell::Cell<u8>,
 
     /// Node containing the "background" buffer (used for the scroll effect).
     pub scroll_node: RefCell<Option<gsk::RenderNode>>,
@@ -33,17 +43,53 @@ pub struct GridBuffer {
 
     #[property(get, set = Self::set_font)]
     pub font: RefCell<Font>,
+    /// If ligatures are rendered.
+    #[property(get, set = Self::set_ligatures)]
+    pub ligatures: cell::Cell<bool>,
+    /// Scale of the undercurl's waves.
+    #[property(get, set = Self::set_undercurl_amplitude, minimum = 0.0)]
+    pub undercurl_amplitude: cell::Cell<f32>,
+    /// How emojis are presented.
+    #[property(get, set = Self::set_emoji_presentation)]
+    pub emoji_presentation: cell::Cell<EmojiPresentation>,
+    /// If the render nodes are rendered from scratch on each flush, instead
+    /// of being cached.
+    #[property(get, set)]
+    pub safe_rendering: cell::Cell<bool>,
 
     /// The viewport delta value from win_viewport event.
     ///
     /// Setting this property will cause the buffer to do a scroll animation.
     #[property(get, set = Self::set_scroll_delta)]
     pub scroll_delta: std::cell::Cell<f64>,
+    /// Rows of the diff bands (i.e. continuous rows sharing a diff
+    /// highlight), as `(start, end)` ranges.
+    pub diff_bands: RefCell<Vec<(usize, usize)>>,
+    /// Diff band nodes.
+    pub diff_nodes: RefCell<Vec<gsk::RenderNode>>,
+    /// Columns of the guide lines (e.g. from 'colorcolumn').
+    pub guides: RefCell<Vec<usize>>,
+    /// Color of the guide lines, from the latest `flush` event.
+    pub guide_color: cell::Cell<Color>,
+    /// Guide line nodes.
+    pub guide_nodes: RefCell<Vec<gsk::RenderNode>>,
     /// If our content is "dirty" (i.e. we're waiting for flush event).
     #[property(get, set)]
     pub dirty: std::cell::Cell<bool>,
     /// Previous render. Drawn when we're "dirty".
     backbuffer: RefCell<Option<gsk::RenderNode>>,
+    /// Render statistics.
+    pub stats: cell::Cell<RenderStats>,
+    /// If the latest flush has been drawn.
+    pub drawn: cell::Cell<bool>,
+    /// Colors of the latest flush, while some of the grid is left
+    /// unrendered for not being visible.
+    pub offscreen_colors: RefCell<Option<Colors>>,
+    /// The visible area when the unrendered parts were left out.
+    pub offscreen_visible: cell::Cell<Option<(f32, f32, f32, f32)>>,
+    /// Frame clock's layout handler, while we're watching for the
+    /// unrendered parts to become visible.
+    pub offscreen_watch: RefCell<Option<(gdk::FrameClock, glib::SignalHandlerId)>>,
 
     scroll_nodes: RefCell<Vec<ScrollNode>>,
 }
@@ -76,8 +122,26 @@ impl ObjectSubclass for GridBuffer {
 impl GridBuffer {
     fn set_font(&self, value: Font) {
         self.font.replace(value);
+        self.clear_nodes();
+    }
+
+    fn set_ligatures(&self, value: bool) {
+        self.ligatures.set(value);
+        self.clear_nodes();
+    }
 
-        // Invalidate all the render nodes.
+    fn set_undercurl_amplitude(&self, value: f32) {
+        self.undercurl_amplitude.set(value);
+        self.clear_nodes();
+    }
+
+    fn set_emoji_presentation(&self, value: EmojiPresentation) {
+        self.emoji_presentation.set(value);
+        self.clear_nodes();
+    }
+
+    /// Invalidates all the render nodes.
+    pub fn clear_nodes(&self) {
         self.rows
             .borrow_mut()
             .iter_mut()
@@ -102,16 +166,27 @@ impl GridBuffer {
     fn set_scroll_delta(&self, delta: f64) {
         self.scroll_delta.set(delta);
 
-        if delta == 0.0 {
-            // Avoid unnecessary work.
-            return;
-        }
-
         let start_time = some_or_return!(
             self.obj().frame_clock(),
             "Failed to get a frame clock for grid buffer animation"
         )
         .frame_time() as f64;
+
+        self.start_scroll_animation(delta, start_time);
+    }
+
+    pub fn start_scroll_animation(&self, delta: f64, start_time: f64) {
+        if delta == 0.0 {
+            // Avoid unnecessary work.
+            return;
+        }
+
+        // The content in between was never rendered, so jumps further than
+        // the grid's height only slide by the height. Otherwise the grid would
+        // be blank for most of the animation.
+        let height = self.rows.borrow().len() as f64;
+        let delta = delta.clamp(-height, height);
+
         let end_time = start_time + self.scroll_transition.get();
 
         let font = self.font.borrow();
@@ -200,6 +275,12 @@ impl GridBuffer {
 impl ObjectImpl for GridBuffer {}
 
 impl WidgetImpl for GridBuffer {
+    fn unrealize(&self) {
+        // Our frame clock goes away with our window.
+        self.obj().unwatch_offscreen();
+        self.parent_unrealize();
+    }
+
     fn snapshot(&self, snapshot: &gtk::Snapshot) {
         if self.dirty.get() {
             if let Some(ref node) = self.backbuffer.borrow().as_ref() {
@@ -208,6 +289,8 @@ impl WidgetImpl for GridBuffer {
             return;
         }
 
+        self.drawn.set(true);
+
         let background = gsk::ContainerNode::new(&self.background_nodes.borrow());
 
         let scroll_nodes = self
@@ -233,12 +316,22 @@ impl WidgetImpl for GridBuffer {
         let scroll = gsk::ContainerNode::new(&scroll_nodes);
 
         let foreground = gsk::TransformNode::new(
-            &gsk::ContainerNode::new(&self.row_nodes.borrow()),
+            &gsk::ContainerNode::new(&[
+                gsk::ContainerNode::new(&self.row_nodes.borrow()).upcast(),
+                gsk::ContainerNode::new(&self.diff_nodes.borrow()).upcast(),
+            ]),
             &gsk::Transform::new().translate(&graphene::Point::new(0.0, self.y_offset.get())),
         );
 
-        let node =
-            gsk::ContainerNode::new(&[background.upcast(), scroll.upcast(), foreground.upcast()]);
+        // The guides span the whole height, so they don't scroll.
+        let guides = gsk::ContainerNode::new(&self.guide_nodes.borrow());
+
+        let node = gsk::ContainerNode::new(&[
+            background.upcast(),
+            scroll.upcast(),
+            guides.upcast(),
+            foreground.upcast(),
+        ]);
 
         snapshot.append_node(&node);
         self.backbuffer.replace(Some(node.upcast()));
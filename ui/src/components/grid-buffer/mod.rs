@@ -3,13 +3,38 @@ use std::cell::{Ref, RefMut};
 use gtk::{glib, graphene, gsk, prelude::*, subclass::prelude::*};
 use nvim::types::uievents::{GridLine, GridScroll};
 
-use crate::colors::Colors;
+use crate::colors::{Color, Colors, HlGroup};
+use crate::font::Font;
+use crate::{some_or_return, SCALE};
 
 mod imp;
 pub mod row;
 
 use row::{Cell, Row};
 
+/// Grids with more cells than this (e.g. on ultrawide monitors) are only
+/// rendered where they're visible.
+const VIRTUALIZE_MIN_CELLS: usize = 300 * 100;
+
+/// Render statistics of a grid buffer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderStats {
+    /// Rows that were (at least partly) rendered on the latest flush.
+    pub rows_rendered: usize,
+    /// Segments (i.e. cells sharing a highlight) rendered on the latest
+    /// flush.
+    pub segments_rendered: usize,
+    /// Segments reused from the previous flush on the latest flush.
+    pub cache_hits: usize,
+    /// Segments left unrendered on the latest flush, because they weren't
+    /// visible.
+    pub segments_skipped: usize,
+    /// Total number of flushes.
+    pub flushes: u64,
+    /// Flushes that were never drawn (i.e. the next flush came first).
+    pub dropped_frames: u64,
+}
+
 glib::wrapper! {
     pub struct GridBuffer(ObjectSubclass<imp::GridBuffer>)
         @extends gtk::Widget,
@@ -79,42 +104,278 @@ impl GridBuffer {
         self.set_dirty(true);
     }
 
+    /// Renders the rows. The new content is drawn on the next `queue_draw`,
+    /// which is left to the caller so the redraws can be rate limited.
     pub fn flush(&self, colors: &Colors) {
         let imp = self.imp();
 
         let ctx = self.pango_context();
 
+        if imp.safe_rendering.get() {
+            imp.clear_nodes();
+        }
+
         let mut row_nodes = imp.row_nodes.borrow_mut();
         row_nodes.clear();
 
+        let prev = imp.stats.get();
+        let mut stats = RenderStats {
+            flushes: prev.flushes + 1,
+            dropped_frames: prev.dropped_frames
+                + u64::from(prev.flushes > 0 && !imp.drawn.replace(false)),
+            ..Default::default()
+        };
+
         let font = imp.font.borrow();
+        let (cols, rows) = self.grid_size();
+        let virtualize = cols * rows >= VIRTUALIZE_MIN_CELLS;
+        let visible = virtualize.then(|| self.visible_area()).flatten();
+        let row_h = font.height() / SCALE;
         for (i, row) in imp.rows.borrow_mut().iter_mut().enumerate() {
-            row.generate_nodes(&ctx, colors, &font);
-            row_nodes.push(
-                gsk::TransformNode::new(
-                    row.to_render_node(),
-                    &gsk::Transform::new()
-                        .translate(&graphene::Point::new(0.0, font.row_to_y(i as f64) as f32)),
-                )
-                .upcast(),
+            let y = font.row_to_y(i as f64) as f32;
+            let cols_visible = visible.map(|(x, vy, w, h)| {
+                if y + row_h <= vy || y >= vy + h {
+                    (0.0, 0.0)
+                } else {
+                    (x, x + w)
+                }
+            });
+
+            let (rendered, cached, skipped) = row.generate_nodes(
+                &ctx,
+                colors,
+                &font,
+                imp.ligatures.get(),
+                imp.undercurl_amplitude.get(),
+                imp.emoji_presentation.get(),
+                cols_visible,
+                virtualize,
             );
+            stats.rows_rendered += usize::from(rendered > 0);
+            stats.segments_rendered += rendered;
+            stats.cache_hits += cached;
+            stats.segments_skipped += skipped;
+
+            row_nodes.push(row_node(row, &font, i, self.bg_opacity()));
         }
 
-        let (alloc, _) = self.preferred_size();
-        let mut nodes = imp.background_nodes.borrow_mut();
-        nodes.clear();
-        nodes.push(
-            gsk::ColorNode::new(
-                &colors.bg,
-                &graphene::Rect::new(0.0, 0.0, alloc.width() as f32, alloc.height() as f32),
-            )
-            .upcast(),
+        imp.default_bg.set(colors.bg);
+        let bg = self.background();
+
+        self.update_diff_bands(&bg);
+
+        // The special color allows lines without highlighting the cells.
+        let colorcolumn = colors.get_hl_group(&HlGroup::ColorColumn);
+        imp.guide_color.set(
+            colorcolumn
+                .hl_attr()
+                .and_then(|hl| hl.special)
+                .unwrap_or(*colorcolumn.bg()),
         );
+        self.update_guides();
+
+        self.update_background();
+        imp.stats.set(stats);
+
+        // Render the rest once it's visible.
+        if stats.segments_skipped > 0 {
+            imp.offscreen_colors.replace(Some(colors.clone()));
+            self.watch_offscreen(visible);
+        } else {
+            imp.offscreen_colors.replace(None);
+            self.unwatch_offscreen();
+        }
 
         self.set_dirty(false);
+    }
+
+    /// Sets the rows of the diff bands, as `(start, end)` ranges.
+    pub fn set_diff_bands(&self, bands: Vec<(usize, usize)>) {
+        self.imp().diff_bands.replace(bands);
+        self.update_diff_bands(&self.background());
+        self.queue_draw();
+    }
+
+    /// The part of us that's visible in our window, as `(x, y, width,
+    /// height)`.
+    fn visible_area(&self) -> Option<(f32, f32, f32, f32)> {
+        let root = self.root()?;
+        let bounds = self.compute_bounds(&root)?;
+        let root_rect = graphene::Rect::new(0.0, 0.0, root.width() as f32, root.height() as f32);
+        let visible = bounds
+            .intersection(&root_rect)
+            .unwrap_or_else(graphene::Rect::zero);
+
+        Some((
+            visible.x() - bounds.x(),
+            visible.y() - bounds.y(),
+            visible.width(),
+            visible.height(),
+        ))
+    }
+
+    /// Flushes again when the visible area changes (i.e. after a layout
+    /// that resized or moved us or our window), until everything that was
+    /// left unrendered is rendered.
+    fn watch_offscreen(&self, visible: Option<(f32, f32, f32, f32)>) {
+        let imp = self.imp();
+        imp.offscreen_visible.set(visible);
+        if imp.offscreen_watch.borrow().is_some() {
+            return;
+        }
+
+        // Without a frame clock we're not shown, and the next flush will
+        // try again.
+        let clock = some_or_return!(self.frame_clock());
+        let id = clock.connect_layout(glib::clone!(@weak self as obj => move |_| {
+            obj.flush_offscreen();
+        }));
+        imp.offscreen_watch.replace(Some((clock, id)));
+    }
+
+    fn unwatch_offscreen(&self) {
+        if let Some((clock, id)) = self.imp().offscreen_watch.take() {
+            clock.disconnect(id);
+        }
+    }
+
+    /// Renders the parts left unrendered on the latest flush, if the
+    /// visible area has changed since.
+    fn flush_offscreen(&self) {
+        let imp = self.imp();
+        if imp.offscreen_colors.borrow().is_none()
+            || self.dirty()
+            || self.visible_area() == imp.offscreen_visible.get()
+        {
+            return;
+        }
+
+        let colors = imp.offscreen_colors.take().expect("no offscreen colors");
+        self.flush(&colors);
+        self.queue_draw();
+    }
+
+    /// Sets the columns of the guide lines (e.g. from 'colorcolumn').
+    pub fn set_guides(&self, guides: Vec<usize>) {
+        self.imp().guides.replace(guides);
+        self.update_guides();
+        self.queue_draw();
+    }
+
+    fn update_guides(&self) {
+        let imp = self.imp();
+        let font = imp.font.borrow();
+        let color = imp.guide_color.get();
+        let (alloc, _) = self.preferred_size();
+        // The columns past our edge (e.g. a 'colorcolumn' wider than the
+        // window) would be drawn outside of the grid.
+        let cols = imp.rows.borrow().first().map_or(0, |row| row.cells.len());
+
+        let nodes = imp
+            .guides
+            .borrow()
+            .iter()
+            .filter(|col| **col < cols)
+            .map(|col| crate::render::render_guide(&font, &color, *col, alloc.height() as f32))
+            .collect();
+        imp.guide_nodes.replace(nodes);
+    }
+
+    fn update_diff_bands(&self, bg: &Color) {
+        let imp = self.imp();
+        let font = imp.font.borrow();
+        let (alloc, _) = self.preferred_size();
+
+        let nodes = imp
+            .diff_bands
+            .borrow()
+            .iter()
+            .map(|(start, end)| {
+                let y = font.row_to_y(*start as f64) as f32;
+                let h = font.row_to_y(end.saturating_sub(*start) as f64) as f32;
+                crate::render::render_diff_band(
+                    &font,
+                    bg,
+                    graphene::Rect::new(0.0, y, alloc.width() as f32, h),
+                )
+            })
+            .collect();
+        imp.diff_nodes.replace(nodes);
+    }
+
+    /// Render statistics, mainly for diagnosing slow rendering.
+    pub fn render_stats(&self) -> RenderStats {
+        self.imp().stats.get()
+    }
+
+    /// Sets the background color overriding the default one (e.g. through
+    /// 'winhighlight'), so the whole grid gets the color instead of only
+    /// the cells.
+    pub fn set_normal_bg(&self, bg: Option<Color>) {
+        self.imp().normal_bg.set(bg);
+        self.update_background();
+        self.queue_draw();
+    }
+
+    /// Sets the transparency of the backgrounds, from 0 (opaque) to 100
+    /// (e.g. from 'winblend').
+    pub fn set_blend(&self, blend: u8) {
+        let imp = self.imp();
+        let blend = blend.min(100);
+        if imp.blend.replace(blend) == blend {
+            return;
+        }
+
+        // The cells' nodes are cached, so only the rows need to be put
+        // together again.
+        let font = imp.font.borrow();
+        let nodes = imp
+            .rows
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, row)| row_node(row, &font, i, self.bg_opacity()))
+            .collect();
+        imp.row_nodes.replace(nodes);
+
+        self.update_background();
         self.queue_draw();
     }
 
+    fn bg_opacity(&self) -> f32 {
+        1.0 - self.imp().blend.get() as f32 / 100.0
+    }
+
+    fn background(&self) -> Color {
+        let imp = self.imp();
+        imp.normal_bg.get().unwrap_or_else(|| imp.default_bg.get())
+    }
+
+    fn update_background(&self) {
+        let (alloc, _) = self.preferred_size();
+
+        let mut nodes = self.imp().background_nodes.borrow_mut();
+        nodes.clear();
+        let node = gsk::ColorNode::new(
+            &self.background(),
+            &graphene::Rect::new(0.0, 0.0, alloc.width() as f32, alloc.height() as f32),
+        )
+        .upcast();
+        nodes.push(if self.bg_opacity() < 1.0 {
+            gsk::OpacityNode::new(&node, self.bg_opacity()).upcast()
+        } else {
+            node
+        });
+    }
+
+    /// Starts a scroll animation for `delta` rows. The animation starts at
+    /// `start_time`, so multiple buffers can be scrolled in sync.
+    pub fn scroll_viewport(&self, delta: f64, start_time: f64) {
+        let imp = self.imp();
+        imp.scroll_delta.set(delta);
+        imp.start_scroll_animation(delta, start_time);
+    }
+
     fn scroll_region(event: &GridScroll) -> (Box<dyn Iterator<Item = i64>>, i64) {
         if event.rows > 0 {
             let top = event.top + event.rows;
@@ -167,6 +428,16 @@ impl GridBuffer {
     }
 }
 
+/// Render node of the `i`th row, positioned in the grid.
+fn row_node(row: &Row, font: &Font, i: usize, bg_opacity: f32) -> gsk::RenderNode {
+    gsk::TransformNode::new(
+        row.to_render_node(bg_opacity),
+        &gsk::Transform::new()
+            .translate(&graphene::Point::new(0.0, font.row_to_y(i as f64) as f32)),
+    )
+    .upcast()
+}
+
 impl Default for GridBuffer {
     fn default() -> Self {
         Self::new()
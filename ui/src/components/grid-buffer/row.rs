@@ -8,7 +8,13 @@ use gtk::{graphene, gsk, pango, prelude::*};
 
 use nvim::types::uievents::GridLine;
 
-use crate::{colors::Colors, font::Font, SCALE};
+use crate::{colors::Colors, font::Font, render::EmojiPresentation, SCALE};
+
+/// Number of cells after which a segment is split at the next blank cell,
+/// when splitting. Long runs of cells sharing a highlight on very large
+/// grids are split, so a change in a wide row only reshapes the part around
+/// it. Splitting at blanks keeps words (and ligatures) in one piece.
+const SEGMENT_MAX_CELLS: usize = 64;
 
 #[derive(Debug)]
 pub struct CellNodes {
@@ -22,6 +28,9 @@ pub struct Cell {
     pub text: String,
     pub hl_id: i64,
     pub double_width: bool,
+    /// Url from the cell's highlight, if any. Updated when the cell's nodes
+    /// are generated.
+    pub url: Option<Rc<str>>,
     /// Cell's render nodes. Render nodes might be shared across cells (e.g.
     /// for ligatures).
     pub nodes: Rc<RefCell<Option<CellNodes>>>,
@@ -33,6 +42,7 @@ impl Default for Cell {
             text: String::from(" "),
             hl_id: 0,
             double_width: false,
+            url: None,
 
             nodes: Rc::new(RefCell::new(None)),
         }
@@ -61,7 +71,9 @@ struct LineSegment<'a> {
     cells: Vec<&'a mut Cell>,
     dirty: bool,
     width: i64,
-    double_width: bool,
+    /// If the segment is rendered on its own and fit to its cells (e.g. a
+    /// double width cell).
+    fit: bool,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -74,7 +86,15 @@ impl Row {
         self.cells = vec![Cell::default(); self.cells.len()];
     }
 
-    pub fn to_render_node(&self) -> gsk::RenderNode {
+    /// Text of the row, as rendered. Continuation cells of double width
+    /// cells are empty, so each character appears once.
+    pub fn text(&self) -> String {
+        self.cells.iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    /// Render node of the row. The backgrounds are drawn with `bg_opacity`
+    /// (e.g. for 'winblend').
+    pub fn to_render_node(&self, bg_opacity: f32) -> gsk::RenderNode {
         let mut bg_nodes = vec![];
         let mut fg_nodes = vec![];
 
@@ -85,11 +105,14 @@ impl Row {
             }
         }
 
-        gsk::ContainerNode::new(&[
-            gsk::ContainerNode::new(&bg_nodes).upcast(),
-            gsk::ContainerNode::new(&fg_nodes).upcast(),
-        ])
-        .upcast()
+        let bg = gsk::ContainerNode::new(&bg_nodes).upcast();
+        let bg = if bg_opacity < 1.0 {
+            gsk::OpacityNode::new(&bg, bg_opacity).upcast()
+        } else {
+            bg
+        };
+
+        gsk::ContainerNode::new(&[bg, gsk::ContainerNode::new(&fg_nodes).upcast()]).upcast()
     }
 
     pub fn update(&mut self, event: &GridLine) {
@@ -100,26 +123,31 @@ impl Row {
             .hl_id
             .expect("first item should have hl_id");
 
-        let start = event.col_start as usize;
-
-        let mut iter = self.cells.iter_mut().skip(start);
-        let mut data_iter = event.data.iter().peekable();
-        while let Some(data) = data_iter.next() {
+        let mut col = event.col_start as usize;
+        for data in event.data.iter() {
             if let Some(id) = data.hl_id {
                 hl_id = id;
             }
 
-            let double_width = data_iter
-                .peek()
-                .map(|peek| peek.text.is_empty())
-                .unwrap_or(false);
-
             for _ in 0..data.repeat.unwrap_or(1) {
-                let mut cell = iter.next().expect("too long grid line event");
+                let cell = self.cells.get_mut(col).expect("too long grid line event");
                 cell.hl_id = hl_id;
                 cell.text = data.text.clone();
-                cell.double_width = double_width;
+                cell.double_width = false;
                 cell.clear_nodes();
+
+                // The empty text is the continuation of a double width cell
+                // on the left. The double width cell might've been received
+                // in a earlier grid line event.
+                if data.text.is_empty() && col > 0 {
+                    let prev = &mut self.cells[col - 1];
+                    if !prev.double_width {
+                        prev.double_width = true;
+                        prev.clear_nodes();
+                    }
+                }
+
+                col += 1;
             }
         }
     }
@@ -130,90 +158,100 @@ impl Row {
         }
     }
 
-    pub fn generate_nodes(&mut self, ctx: &pango::Context, colors: &Colors, font: &Font) {
-        // Gather cells into continuous segments based on hl ids.
-        let mut segments = self
-            .cells
-            .iter_mut()
-            // TODO(ville): Try to use a iterator to avoid unnecessary allocations.
-            .fold(Vec::<LineSegment>::new(), |mut acc, cell| {
-                let dirty = cell.nodes.borrow().is_none();
-                let width = cell.width();
-
-                // If the cell is double width, render it independently.
-                if cell.double_width {
-                    acc.push(LineSegment {
-                        hl_id: cell.hl_id,
-                        cells: vec![cell],
-                        width,
-                        dirty,
-                        double_width: true,
-                    });
-
-                    return acc;
-                }
-
-                match acc.last_mut() {
-                    // Double width cells are always followed by a "empty" cell.
-                    // We want to render these together.
-                    Some(prev) if prev.double_width && width == 0 => {
-                        prev.cells.push(cell);
-                        prev.dirty = dirty || prev.dirty;
-                        prev.width += width;
-                    }
-                    // Combine neighbouring cells that share share same hl id,
-                    // but not when the other is a double width (excluding
-                    // the above case).
-                    Some(prev) if !prev.double_width && prev.hl_id == cell.hl_id => {
-                        prev.cells.push(cell);
-                        prev.dirty = dirty || prev.dirty;
-                        prev.width += width;
-                    }
-                    _ => acc.push(LineSegment {
-                        hl_id: cell.hl_id,
-                        cells: vec![cell],
-                        width,
-                        dirty,
-                        double_width: false,
-                    }),
-                };
-
-                acc
-            });
+    /// Generates render nodes for the cells that don't have them. With
+    /// `visible` (the visible x range), the segments outside of it are left
+    /// without nodes, to be generated once they're visible. With `split`,
+    /// long runs of cells are split into multiple segments.
+    ///
+    /// Returns the number of rendered, reused (cached) and skipped segments.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_nodes(
+        &mut self,
+        ctx: &pango::Context,
+        colors: &Colors,
+        font: &Font,
+        ligatures: bool,
+        undercurl_amplitude: f32,
+        emoji: EmojiPresentation,
+        visible: Option<(f32, f32)>,
+        split: bool,
+    ) -> (usize, usize, usize) {
+        let mut segments = line_segments(&mut self.cells, split);
 
         let baseline = font.baseline() / SCALE;
         let bg_h = font.height() / SCALE;
         let ch = font.char_width();
         let mut x = 0.0_f32;
+        let mut rendered = 0;
+        let mut cached = 0;
+        let mut skipped = 0;
         for segment in segments.iter_mut() {
             let width = segment.width as f32 * ch / SCALE;
 
             if !segment.dirty {
+                cached += 1;
                 x += width;
                 continue;
             }
 
-            let attrs = crate::render::create_hl_attrs(&segment.hl_id, colors, font);
+            if let Some((start, end)) = visible {
+                if x + width <= start || x >= end {
+                    skipped += 1;
+                    x += width;
+                    continue;
+                }
+            }
+
+            rendered += 1;
 
+            let attrs = crate::render::create_hl_attrs(&segment.hl_id, colors, font, ligatures);
+
+            let hl = colors.get_hl(&segment.hl_id);
+            let fg = hl.fg();
+
+            // Draw box drawing characters ourselves, and leave blanks for
+            // them in the text.
+            let mut box_nodes = vec![];
+            let mut box_x = x;
             let text = segment
                 .cells
                 .iter()
-                .map(|cell| cell.text.clone())
+                .map(|cell| {
+                    let node = (!segment.fit)
+                        .then(|| crate::box_drawing::render(&cell.text, fg, font, box_x))
+                        .flatten();
+                    box_x += cell.width() as f32 * ch / SCALE;
+
+                    match node {
+                        Some(node) => {
+                            box_nodes.push(node);
+                            String::from(" ")
+                        }
+                        None => emoji.apply(&cell.text, cell.double_width).into_owned(),
+                    }
+                })
                 .collect::<String>();
 
-            let hl = colors.get_hl(&segment.hl_id);
-            let fg = hl.fg();
             let bg = hl.bg();
             let sp = hl.sp();
+            let url: Option<Rc<str>> = hl.url().map(Rc::from);
             let hl = hl.hl_attr();
 
             // Create glyphs.
             let mut nodes = vec![];
-            nodes.push(crate::render::render_text(
-                ctx, &text, fg, &attrs, x, baseline,
-            ));
+            let text_node = crate::render::render_text(ctx, &text, fg, &attrs, x, baseline);
+            let text_node =
+                crate::render::synthesize_style(text_node, &segment.hl_id, colors, font, baseline);
+            nodes.push(if segment.fit {
+                // Wide glyphs (e.g. color emojis) might not respect the cell
+                // size, so make them fit.
+                crate::render::fit_to_cells(text_node, font, x, width)
+            } else {
+                text_node
+            });
+            nodes.extend(box_nodes);
 
-            if hl.and_then(|hl| hl.underline).unwrap_or(false) {
+            if hl.and_then(|hl| hl.underline).unwrap_or(false) || url.is_some() {
                 nodes.push(crate::render::render_underline(
                     font, sp, x, baseline, width,
                 ));
@@ -239,6 +277,7 @@ impl Row {
                     baseline,
                     width,
                     segment.width,
+                    undercurl_amplitude,
                 ));
             }
 
@@ -258,13 +297,76 @@ impl Row {
             })));
             segment.cells.iter_mut().for_each(|cell| {
                 cell.nodes = nodes.clone();
+                cell.url = url.clone();
             });
 
             x += width;
         }
+
+        (rendered, cached, skipped)
     }
 }
 
+/// Gathers `cells` into continuous segments based on hl ids. With `split`,
+/// segments are also split at blank cells once they're `SEGMENT_MAX_CELLS`
+/// long.
+fn line_segments(cells: &mut [Cell], split: bool) -> Vec<LineSegment<'_>> {
+    // TODO(ville): Try to use a iterator to avoid unnecessary allocations.
+    cells
+        .iter_mut()
+        .fold(Vec::<LineSegment>::new(), |mut acc, cell| {
+            let dirty = cell.nodes.borrow().is_none();
+            let width = cell.width();
+
+            // If the cell is double width, or its glyph is likely to
+            // overflow a single cell, render it independently.
+            if cell.double_width || crate::render::is_wide_symbol(&cell.text) {
+                acc.push(LineSegment {
+                    hl_id: cell.hl_id,
+                    cells: vec![cell],
+                    width,
+                    dirty,
+                    fit: true,
+                });
+
+                return acc;
+            }
+
+            match acc.last_mut() {
+                // Double width cells are always followed by a "empty" cell.
+                // We want to render these together.
+                Some(prev) if prev.fit && width == 0 => {
+                    prev.cells.push(cell);
+                    prev.dirty = dirty || prev.dirty;
+                    prev.width += width;
+                }
+                // Combine neighbouring cells that share share same hl id,
+                // but not when the other is a double width (excluding
+                // the above case).
+                Some(prev)
+                    if !prev.fit
+                        && prev.hl_id == cell.hl_id
+                        && !(split
+                            && prev.cells.len() >= SEGMENT_MAX_CELLS
+                            && cell.text == " ") =>
+                {
+                    prev.cells.push(cell);
+                    prev.dirty = dirty || prev.dirty;
+                    prev.width += width;
+                }
+                _ => acc.push(LineSegment {
+                    hl_id: cell.hl_id,
+                    cells: vec![cell],
+                    width,
+                    dirty,
+                    fit: false,
+                }),
+            };
+
+            acc
+        })
+}
+
 pub struct RenderNodeIter<'a> {
     inner: Peekable<std::slice::Iter<'a, Cell>>,
 }
@@ -286,3 +388,95 @@ impl<'a> Iterator for RenderNodeIter<'a> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nvim::types::{uievents::GridLine, GridLineData};
+
+    use super::{line_segments, Cell, Row, SEGMENT_MAX_CELLS};
+
+    fn data(text: &str, hl_id: Option<i64>) -> GridLineData {
+        GridLineData {
+            text: text.to_string(),
+            hl_id,
+            repeat: None,
+        }
+    }
+
+    #[test]
+    fn test_update_double_width() {
+        let mut row = Row {
+            cells: vec![Cell::default(); 4],
+        };
+
+        row.update(&GridLine {
+            grid: 1,
+            row: 0,
+            col_start: 0,
+            data: vec![data("a", Some(0)), data("あ", None), data("", None)],
+        });
+
+        let widths = row.cells.iter().map(Cell::width).collect::<Vec<_>>();
+        assert_eq!(widths, vec![1, 2, 0, 1]);
+
+        // Overwrite the double width cell with narrow ones.
+        row.update(&GridLine {
+            grid: 1,
+            row: 0,
+            col_start: 1,
+            data: vec![data("b", Some(0)), data("c", None)],
+        });
+
+        let widths = row.cells.iter().map(Cell::width).collect::<Vec<_>>();
+        assert_eq!(widths, vec![1, 1, 1, 1]);
+
+        // Continuation cell in a separate event.
+        row.update(&GridLine {
+            grid: 1,
+            row: 0,
+            col_start: 2,
+            data: vec![data("あ", Some(0))],
+        });
+        row.update(&GridLine {
+            grid: 1,
+            row: 0,
+            col_start: 3,
+            data: vec![data("", Some(0))],
+        });
+
+        let widths = row.cells.iter().map(Cell::width).collect::<Vec<_>>();
+        assert_eq!(widths, vec![1, 1, 2, 0]);
+        assert_eq!(row.text(), "abあ");
+    }
+
+    #[test]
+    fn test_line_segments_max_cells() {
+        let mut cells = vec![Cell::default(); SEGMENT_MAX_CELLS * 2 + 10];
+        cells[5].hl_id = 1;
+
+        let widths = |cells: &mut [Cell], split: bool| {
+            line_segments(cells, split)
+                .iter()
+                .map(|segment| segment.width)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            widths(&mut cells, true),
+            vec![5, 1, SEGMENT_MAX_CELLS as i64, SEGMENT_MAX_CELLS as i64, 4]
+        );
+        assert_eq!(
+            widths(&mut cells, false),
+            vec![5, 1, SEGMENT_MAX_CELLS as i64 * 2 + 4]
+        );
+
+        // Words aren't split.
+        for cell in &mut cells[SEGMENT_MAX_CELLS + 4..SEGMENT_MAX_CELLS + 10] {
+            cell.text = String::from("x");
+        }
+        assert_eq!(
+            widths(&mut cells, true),
+            vec![5, 1, SEGMENT_MAX_CELLS as i64 + 4, SEGMENT_MAX_CELLS as i64]
+        );
+    }
+}
@@ -1,20 +1,63 @@
 use std::cell::{Cell, RefCell};
+use std::time::Duration;
 
 use gtk::glib::subclass::InitializingObject;
-use gtk::graphene;
 use gtk::subclass::prelude::*;
+use gtk::{gdk, graphene, gsk};
 use gtk::{
     glib::{self, clone},
     prelude::*,
 };
-use nvim::types::Window;
+use nvim::serde::Deserialize;
+use nvim::types::{CursorShape, Window};
 use nvim::NeovimApi;
 
-use crate::boxed::ModeInfo;
+use crate::api::FoldPreview;
+use crate::boxed::{ModeInfo, MouseMap};
+use crate::colors::{Color, Colors};
 use crate::components::{cursor, Cursor, ExternalWindow, GridBuffer};
 use crate::font::Font;
+use crate::input::{Action, Mouse};
+use crate::math::Easing;
 use crate::nvim::Neovim;
-use crate::spawn_local;
+use crate::render::EmojiPresentation;
+use crate::{some_or_return, spawn_local, warn};
+
+/// Time the pointer has to rest on a fold before it's previewed.
+const FOLD_PREVIEW_DELAY: Duration = Duration::from_millis(500);
+/// Time the fold preview stays after the pointer left the grid, so the
+/// pointer can be moved over the preview.
+const FOLD_PREVIEW_HIDE_DELAY: Duration = Duration::from_millis(300);
+
+/// Corner radius of the decorated floats.
+const FLOAT_RADIUS: f32 = 6.0;
+/// Vertical offset of the decorated floats' shadow.
+const FLOAT_SHADOW_DY: f32 = 2.0;
+/// Blur radius of the decorated floats' shadow.
+const FLOAT_SHADOW_BLUR: f32 = 12.0;
+/// Width of the scrollbar's thumb.
+const SCROLLBAR_WIDTH: f32 = 4.0;
+/// Width of the area at the right edge that grabs the scrollbar.
+const SCROLLBAR_GRAB_WIDTH: f64 = 12.0;
+
+/// The window's viewport, from the latest `win_viewport` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// First visible line, zero based.
+    pub topline: i64,
+    /// Line below the last visible line, zero based.
+    pub botline: i64,
+    pub line_count: i64,
+}
+
+/// Scrollbar being dragged.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollbarGrab {
+    /// Top line when the drag started.
+    pub topline: i64,
+    /// The latest top line sent to nvim.
+    pub sent: i64,
+}
 
 #[derive(gtk::CompositeTemplate, glib::Properties, Default)]
 #[properties(wrapper_type = super::Grid)]
@@ -42,8 +85,66 @@ pub struct Grid {
     pub active: Cell<bool>,
     #[property(get, set, default = false)]
     pub busy: Cell<bool>,
+    /// If ligatures are rendered.
+    #[property(get, set)]
+    pub ligatures: Cell<bool>,
+    /// Scale of the undercurl's waves.
+    #[property(get, set, minimum = 0.0)]
+    pub undercurl_amplitude: Cell<f32>,
+    /// How emojis are presented.
+    #[property(get, set)]
+    pub emoji_presentation: Cell<EmojiPresentation>,
+    /// If power saving is enabled. Disables the animations.
+    #[property(get, set, default = false)]
+    pub power_saver: Cell<bool>,
+    /// If the simplest rendering path is used (see `GridBuffer`'s
+    /// `safe-rendering`). Also disables the float decoration.
+    #[property(get, set, default = false)]
+    pub safe_rendering: Cell<bool>,
+    /// If the grid is displaying a large file. Disables the animations.
+    #[property(get, set, default = false)]
+    pub large_file: Cell<bool>,
     #[property(get, set)]
     pub mode_info: RefCell<ModeInfo>,
+    /// If the cell inspector is enabled.
+    #[property(get, set, default = false)]
+    pub cell_inspector: Cell<bool>,
+    /// User's mapping from mouse buttons to nvim input.
+    #[property(get, set)]
+    pub mouse_map: RefCell<MouseMap>,
+    /// If the grid is a floating window.
+    #[property(get, set = Self::set_floating, default = false)]
+    pub floating: Cell<bool>,
+    /// If floating windows have a shadow and rounded corners.
+    #[property(get, set = Self::set_float_decoration, default = false)]
+    pub float_decoration: Cell<bool>,
+    /// Colors of the latest flush, for the cell inspector. Only kept while
+    /// the inspector is enabled.
+    pub inspector_colors: RefCell<Option<Colors>>,
+    /// If the scrollbar is shown.
+    #[property(get, set = Self::set_scrollbar, default = false)]
+    pub scrollbar: Cell<bool>,
+    /// The window's viewport, for the scrollbar.
+    pub viewport: Cell<Option<Viewport>>,
+    /// Color of the scrollbar, from the latest flush.
+    pub scrollbar_color: Cell<Color>,
+    pub scrollbar_drag: gtk::GestureDrag,
+    pub scrollbar_grab: Cell<Option<ScrollbarGrab>>,
+    /// Popover showing the inspected cell.
+    pub inspector: gtk::Popover,
+    pub inspector_label: gtk::Label,
+    /// If closed folds are previewed when hovered.
+    #[property(get, set, default = false)]
+    pub fold_preview: Cell<bool>,
+    /// Popover previewing the hovered fold.
+    pub fold_popover: gtk::Popover,
+    pub fold_label: gtk::Label,
+    /// Hovered row, for which the fold preview is shown (or pending).
+    pub fold_row: Cell<Option<usize>>,
+    /// First line of the previewed fold.
+    pub fold_lnum: Cell<i64>,
+    /// Timer for showing or hiding the fold preview.
+    pub fold_timer: RefCell<Option<glib::SourceId>>,
 
     pub external_win: RefCell<Option<ExternalWindow>>,
     pub gesture_click: gtk::GestureClick,
@@ -57,12 +158,12 @@ pub struct Grid {
     /// The cursor position animation speed.
     #[property(get, set, minimum = 0.0)]
     pub cursor_position_transition: Cell<f64>,
+    /// The cursor position animation's easing.
+    #[property(get, set)]
+    pub cursor_position_easing: Cell<Easing>,
     /// The scroll animation speed.
     #[property(get, set, minimum = 0.0)]
     pub scroll_transition: Cell<f64>,
-
-    #[property(get, set)]
-    pub viewport_delta: Cell<f64>,
 }
 
 #[gtk::template_callbacks(functions)]
@@ -72,13 +173,29 @@ impl Grid {
         a * b
     }
 
+    #[template_callback]
+    fn transition(transition: f64, large_file: bool, power_saver: bool) -> f64 {
+        if large_file || power_saver {
+            0.0
+        } else {
+            transition * 1000.0
+        }
+    }
+
     #[template_callback]
     fn cursor_width(mode: &ModeInfo) -> f32 {
-        mode.cell_percentage
-            // Make sure we have non 0 value.
-            .map(|v| if v == 0 { 100 } else { v })
-            .map(|v| v as f32 / 100.0)
-            .unwrap_or(100.0)
+        match mode.cursor_shape {
+            Some(CursorShape::Vertical) => cell_percentage(mode),
+            _ => 1.0,
+        }
+    }
+
+    #[template_callback]
+    fn cursor_height(mode: &ModeInfo) -> f32 {
+        match mode.cursor_shape {
+            Some(CursorShape::Horizontal) => cell_percentage(mode),
+            _ => 1.0,
+        }
     }
 
     #[template_callback]
@@ -101,6 +218,304 @@ impl Grid {
     }
 }
 
+impl Grid {
+    fn cancel_fold_timer(&self) {
+        if let Some(id) = self.fold_timer.take() {
+            id.remove();
+        }
+    }
+
+    /// Schedules the preview of the fold on the hovered row, if any.
+    fn hover_fold(&self, y: f64) {
+        if !self.fold_preview.get() {
+            return;
+        }
+
+        let obj = self.obj();
+        let row = obj.font().scale_to_row(y);
+        if self.fold_row.get() == Some(row) {
+            // Keep the pending (or shown) preview.
+            self.cancel_fold_timer();
+            if !self.fold_popover.is_visible() {
+                self.schedule_fold_preview(row);
+            }
+            return;
+        }
+
+        self.hide_fold_preview();
+        self.fold_row.set(Some(row));
+        self.schedule_fold_preview(row);
+    }
+
+    fn schedule_fold_preview(&self, row: usize) {
+        let obj = self.obj();
+        let id = glib::timeout_add_local_once(
+            FOLD_PREVIEW_DELAY,
+            clone!(@weak obj => move || {
+                let imp = obj.imp();
+                imp.fold_timer.replace(None);
+                imp.fetch_fold_preview(row);
+            }),
+        );
+        self.cancel_fold_timer();
+        self.fold_timer.replace(Some(id));
+    }
+
+    fn leave_fold(&self) {
+        self.cancel_fold_timer();
+        self.fold_row.set(None);
+        if !self.fold_popover.is_visible() {
+            return;
+        }
+
+        let obj = self.obj();
+        let id = glib::timeout_add_local_once(
+            FOLD_PREVIEW_HIDE_DELAY,
+            clone!(@weak obj => move || {
+                let imp = obj.imp();
+                imp.fold_timer.replace(None);
+                imp.fold_popover.popdown();
+            }),
+        );
+        self.fold_timer.replace(Some(id));
+    }
+
+    pub fn hide_fold_preview(&self) {
+        self.cancel_fold_timer();
+        self.fold_popover.popdown();
+    }
+
+    /// Asks the runtime for the fold on `row`, and previews it.
+    fn fetch_fold_preview(&self, row: usize) {
+        let obj = self.obj();
+        let win = match obj.nvim_window_handle() {
+            Some(win) => win,
+            None => return,
+        };
+
+        spawn_local!(clone!(@weak obj => async move {
+            let res = match obj
+                .nvim()
+                .nvim_exec_lua(
+                    "return require('gnvim').fold_preview(...)",
+                    vec![rmpv::Value::from(win), rmpv::Value::from(row)],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            let preview = match res.await.map(Option::<FoldPreview>::deserialize) {
+                Ok(Ok(preview)) => preview,
+                Ok(Err(err)) => {
+                    warn!("invalid fold preview: {:?}", err);
+                    return;
+                }
+                Err(err) => {
+                    warn!("failed to get fold preview: {:?}", err);
+                    return;
+                }
+            };
+
+            let imp = obj.imp();
+            // The pointer might've moved while we were waiting.
+            let preview = match preview {
+                Some(preview) if imp.fold_row.get() == Some(row) => preview,
+                _ => return,
+            };
+
+            let font = obj.font();
+            imp.fold_lnum.set(preview.lnum);
+            imp.fold_label.set_markup(&super::fold_preview_markup(&preview));
+            imp.fold_popover.set_pointing_to(Some(&gdk::Rectangle::new(
+                0,
+                font.row_to_y(row as f64) as i32,
+                obj.width(),
+                font.row_to_y(1.0).ceil() as i32,
+            )));
+            imp.fold_popover.popup();
+        }));
+    }
+
+    /// Opens the previewed fold.
+    fn open_fold(&self) {
+        self.hide_fold_preview();
+
+        let obj = self.obj();
+        let win = match obj.nvim_window_handle() {
+            Some(win) => win,
+            None => return,
+        };
+        let lnum = self.fold_lnum.get();
+
+        spawn_local!(clone!(@weak obj => async move {
+            let res = match obj
+                .nvim()
+                .nvim_exec_lua(
+                    "require('gnvim').open_fold(...)",
+                    vec![rmpv::Value::from(win), rmpv::Value::from(lnum)],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to open fold: {:?}", err);
+            }
+        }));
+    }
+
+    fn set_scrollbar(&self, value: bool) {
+        self.scrollbar.set(value);
+        self.obj().queue_draw();
+    }
+
+    /// Starts dragging the scrollbar, if `x`, `y` is on it. Clicking the
+    /// scrollbar's track jumps there.
+    fn scrollbar_begin(&self, x: f64, y: f64) -> bool {
+        let obj = self.obj();
+        let viewport = match self.viewport.get() {
+            Some(viewport) if self.scrollbar.get() => viewport,
+            _ => return false,
+        };
+        if x < obj.width() as f64 - SCROLLBAR_GRAB_WIDTH {
+            return false;
+        }
+        let height = obj.height() as f32;
+        let (thumb_y, thumb_h) = match super::scrollbar_thumb(&viewport, height) {
+            Some(thumb) => thumb,
+            None => return false,
+        };
+
+        let topline = if (thumb_y..thumb_y + thumb_h).contains(&(y as f32)) {
+            viewport.topline
+        } else {
+            let topline = super::scrollbar_topline(&viewport, height, y as f32 - thumb_h / 2.0);
+            self.scroll_to(topline);
+            topline
+        };
+
+        self.scrollbar_grab.set(Some(ScrollbarGrab {
+            topline,
+            sent: topline,
+        }));
+        true
+    }
+
+    fn scrollbar_update(&self, offset_y: f64) {
+        let (mut grab, viewport) = match (self.scrollbar_grab.get(), self.viewport.get()) {
+            (Some(grab), Some(viewport)) => (grab, viewport),
+            _ => return,
+        };
+
+        let height = self.obj().height() as f32;
+        let (start_y, _) = some_or_return!(
+            super::scrollbar_thumb(
+                &Viewport {
+                    topline: grab.topline,
+                    ..viewport
+                },
+                height
+            ),
+            "no scrollbar"
+        );
+        let topline = super::scrollbar_topline(&viewport, height, start_y + offset_y as f32);
+        if topline != grab.sent {
+            grab.sent = topline;
+            self.scrollbar_grab.set(Some(grab));
+            self.scroll_to(topline);
+        }
+    }
+
+    /// Scrolls the window so `topline` (zero based) is the first line.
+    fn scroll_to(&self, topline: i64) {
+        let obj = self.obj();
+        let win = match obj.nvim_window_handle() {
+            Some(win) => win,
+            None => return,
+        };
+
+        spawn_local!(clone!(@weak obj => async move {
+            let res = match obj
+                .nvim()
+                .nvim_exec_lua(
+                    "require('gnvim').scroll_to(...)",
+                    vec![rmpv::Value::from(win), rmpv::Value::from(topline + 1)],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to scroll: {:?}", err);
+            }
+        }));
+    }
+
+    fn set_floating(&self, value: bool) {
+        self.floating.set(value);
+        self.obj().queue_draw();
+    }
+
+    fn set_float_decoration(&self, value: bool) {
+        self.float_decoration.set(value);
+        self.obj().queue_draw();
+    }
+
+    fn open_url(&self, url: &str) {
+        let window = self.obj().root().and_downcast::<gtk::Window>();
+        gtk::show_uri(window.as_ref(), url, gtk::gdk::CURRENT_TIME);
+    }
+
+    /// Shows the cell inspector for the cell under `x`, `y`, if the inspector
+    /// is enabled and ctrl+shift is held. Otherwise, hides the inspector.
+    fn inspect_cell(&self, state: gdk::ModifierType, x: f64, y: f64) {
+        let modifiers = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::SHIFT_MASK;
+        let colors = self.inspector_colors.borrow();
+        let colors = match colors.as_ref() {
+            Some(colors) if self.cell_inspector.get() && state.contains(modifiers) => colors,
+            _ => {
+                self.inspector.popdown();
+                return;
+            }
+        };
+
+        let obj = self.obj();
+        let font = obj.font();
+        let (row, col) = (font.scale_to_row(y), font.scale_to_col(x));
+        let info = match obj.cell_info(row, col, colors) {
+            Some(info) => info,
+            None => {
+                self.inspector.popdown();
+                return;
+            }
+        };
+
+        self.inspector_label.set_text(&info);
+        self.inspector.set_pointing_to(Some(&gdk::Rectangle::new(
+            font.col_to_x(col as f64) as i32,
+            font.row_to_y(row as f64) as i32,
+            font.col_to_x(1.0).ceil() as i32,
+            font.row_to_y(1.0).ceil() as i32,
+        )));
+        self.inspector.popup();
+    }
+}
+
 #[glib::object_subclass]
 impl ObjectSubclass for Grid {
     const NAME: &'static str = "Grid";
@@ -127,8 +542,9 @@ impl ObjectImpl for Grid {
 
         self.gesture_click.set_button(0);
         self.gesture_drag.set_button(0);
+        // NOTE(ville): Not using the discrete flag, so we get the precise
+        // scroll deltas from touchpads.
         let mut flags = gtk::EventControllerScrollFlags::empty();
-        flags.insert(gtk::EventControllerScrollFlags::DISCRETE);
         flags.insert(gtk::EventControllerScrollFlags::BOTH_AXES);
         self.event_controller_scroll.set_flags(flags);
 
@@ -140,10 +556,110 @@ impl ObjectImpl for Grid {
         self.buffer
             .add_controller(self.event_controller_motion.clone());
 
-        // Connect mouse events.
         let obj = self.obj();
+
+        self.inspector_label.set_xalign(0.0);
+        self.inspector_label.add_css_class("monospace");
+        self.inspector.set_child(Some(&self.inspector_label));
+        self.inspector.set_autohide(false);
+        self.inspector.set_can_target(false);
+        self.inspector.set_parent(&*obj);
+        self.event_controller_motion
+            .connect_motion(clone!(@weak obj => move |ctrl, x, y| {
+                obj.imp().inspect_cell(ctrl.current_event_state(), x, y);
+            }));
+        self.event_controller_motion
+            .connect_leave(clone!(@weak obj => move |_| {
+                obj.imp().inspector.popdown();
+            }));
+
+        self.fold_label.set_xalign(0.0);
+        self.fold_popover.add_css_class("fold-preview");
+        self.fold_popover.set_child(Some(&self.fold_label));
+        self.fold_popover.set_autohide(false);
+        self.fold_popover.set_parent(&*obj);
+        self.event_controller_motion
+            .connect_motion(clone!(@weak obj => move |_, _, y| {
+                obj.imp().hover_fold(y);
+            }));
+        self.event_controller_motion
+            .connect_leave(clone!(@weak obj => move |_| {
+                obj.imp().leave_fold();
+            }));
+
+        let motion = gtk::EventControllerMotion::new();
+        motion.connect_enter(clone!(@weak obj => move |_, _, _| {
+            obj.imp().cancel_fold_timer();
+        }));
+        motion.connect_leave(clone!(@weak obj => move |_| {
+            obj.imp().hide_fold_preview();
+        }));
+        self.fold_popover.add_controller(motion);
+
+        let click = gtk::GestureClick::new();
+        click.connect_released(clone!(@weak obj => move |_, _, _, _| {
+            obj.imp().open_fold();
+        }));
+        self.fold_popover.add_controller(click);
+
+        // Capture the presses on the scrollbar, so they don't reach nvim.
+        self.scrollbar_drag
+            .set_propagation_phase(gtk::PropagationPhase::Capture);
+        self.scrollbar_drag
+            .connect_drag_begin(clone!(@weak obj => move |gesture, x, y| {
+                if obj.imp().scrollbar_begin(x, y) {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                } else {
+                    gesture.set_state(gtk::EventSequenceState::Denied);
+                }
+            }));
+        self.scrollbar_drag
+            .connect_drag_update(clone!(@weak obj => move |_, _, y| {
+                obj.imp().scrollbar_update(y);
+            }));
+        self.scrollbar_drag
+            .connect_drag_end(clone!(@weak obj => move |_, _, _| {
+                obj.imp().scrollbar_grab.set(None);
+            }));
+        obj.add_controller(self.scrollbar_drag.clone());
+
+        // Connect mouse events.
         obj.connect_mouse(
             clone!(@weak obj => move |id, mouse, action, modifier, row, col| {
+                // Buttons mapped by the user are sent as keys on press.
+                let mapped = mouse
+                    .as_nvim_key(&modifier)
+                    .and_then(|key| obj.mouse_map().get(&key).cloned());
+                if let Some(keys) = mapped {
+                    if matches!(action, Action::Pressed) {
+                        spawn_local!(async move {
+                            let res = match obj.nvim().nvim_input(&keys).await {
+                                Ok(res) => res,
+                                Err(err) => {
+                                    warn!("call to nvim failed: {:?}", err);
+                                    return;
+                                }
+                            };
+
+                            if let Err(err) = res.await {
+                                warn!("failed to input mapped mouse keys: {:?}", err);
+                            }
+                        });
+                    }
+                    return;
+                }
+
+                // Ctrl+click on a url opens it, instead of passing the click
+                // to nvim.
+                if matches!(mouse, Mouse::Left) && modifier == "C-" {
+                    if let Some(url) = obj.url_at(row, col) {
+                        if matches!(action, Action::Pressed) {
+                            obj.imp().open_url(&url);
+                        }
+                        return;
+                    }
+                }
+
                 spawn_local!(async move {
                     let res = obj
                         .nvim()
@@ -157,31 +673,58 @@ impl ObjectImpl for Grid {
                         )
                         .await.expect("call to nvim failed");
 
-                    res.await.expect("nvim_input_mouse failed");
+                    if let Err(err) = res.await {
+                        warn!("nvim_input_mouse failed: {:?}", err);
+                    }
                 });
             }),
         )
     }
 
     fn dispose(&self) {
+        self.cancel_fold_timer();
+        if let Some(external) = self.external_win.take() {
+            external.destroy();
+        }
+
         self.buffer.unparent();
         self.cursor.unparent();
+        self.inspector.unparent();
+        self.fold_popover.unparent();
     }
 }
 
 impl WidgetImpl for Grid {
     fn snapshot(&self, snapshot: &gtk::Snapshot) {
         let (_, req) = self.obj().preferred_size();
+        let rect = graphene::Rect::new(0.0, 0.0, req.width() as f32, req.height() as f32);
 
-        snapshot.push_clip(&graphene::Rect::new(
-            0.0,
-            0.0,
-            req.width() as f32,
-            req.height() as f32,
-        ));
+        if self.floating.get() && self.float_decoration.get() && !self.safe_rendering.get() {
+            let outline = gsk::RoundedRect::from_rect(rect, FLOAT_RADIUS);
+            snapshot.append_outset_shadow(
+                &outline,
+                &gdk::RGBA::new(0.0, 0.0, 0.0, 0.4),
+                0.0,
+                FLOAT_SHADOW_DY,
+                0.0,
+                FLOAT_SHADOW_BLUR,
+            );
+            snapshot.push_rounded_clip(&outline);
+        } else {
+            snapshot.push_clip(&rect);
+        }
 
         self.parent_snapshot(snapshot);
 
+        if let (true, Some(viewport)) = (self.scrollbar.get(), self.viewport.get()) {
+            if let Some((y, h)) = super::scrollbar_thumb(&viewport, rect.height()) {
+                snapshot.append_color(
+                    &self.scrollbar_color.get(),
+                    &graphene::Rect::new(rect.width() - SCROLLBAR_WIDTH, y, SCROLLBAR_WIDTH, h),
+                );
+            }
+        }
+
         snapshot.pop();
     }
 
@@ -204,5 +747,17 @@ impl WidgetImpl for Grid {
 
             child = sib.next_sibling();
         }
+
+        self.inspector.present();
+        self.fold_popover.present();
     }
 }
+
+/// The portion of the cell the cursor covers, for the bar shaped cursors.
+fn cell_percentage(mode: &ModeInfo) -> f32 {
+    mode.cell_percentage
+        // Make sure we have non 0 value.
+        .map(|v| if v == 0 { 100 } else { v })
+        .map(|v| v as f32 / 100.0)
+        .unwrap_or(1.0)
+}
@@ -1,6 +1,9 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
 
-use gtk::{glib, glib::clone, prelude::*, subclass::prelude::*};
+use gtk::{gdk, glib, glib::clone, prelude::*, subclass::prelude::*};
 
 use nvim::types::{
     uievents::{GridLine, GridResize, GridScroll},
@@ -8,14 +11,18 @@ use nvim::types::{
 };
 
 use crate::{
+    api::FoldPreview,
     boxed::ModeInfo,
-    colors::Colors,
+    colors::{Color, Colors, HlGroup},
     font::Font,
     input::{Action, Mouse},
-    some_or_return,
+    some_or_return, SCALE,
 };
 
-use super::ExternalWindow;
+use super::{
+    grid_buffer::{row::Row, RenderStats},
+    ExternalWindow,
+};
 
 mod imp;
 
@@ -62,15 +69,195 @@ impl Grid {
         *self.imp().external_win.borrow_mut() = Some(external);
     }
 
+    /// Returns the url under the given cell, if any.
+    pub fn url_at(&self, row: usize, col: usize) -> Option<String> {
+        self.imp()
+            .buffer
+            .get_rows()
+            .get(row)
+            .and_then(|row| row.cells.get(col))
+            .and_then(|cell| cell.url.as_deref().map(String::from))
+    }
+
+    /// Returns the highlight id of the given cell, if any.
+    pub fn hl_id_at(&self, row: usize, col: usize) -> Option<i64> {
+        self.imp()
+            .buffer
+            .get_rows()
+            .get(row)
+            .and_then(|row| row.cells.get(col))
+            .map(|cell| cell.hl_id)
+    }
+
+    /// Handle of the nvim window associated to this grid.
+    pub fn nvim_window_handle(&self) -> Option<i64> {
+        self.imp()
+            .nvim_window
+            .borrow()
+            .as_ref()
+            .and_then(Window::handle)
+    }
+
     pub fn set_nvim_window(&self, window: Option<Window>) {
-        self.imp().nvim_window.replace(window);
+        let imp = self.imp();
+        // The viewport is the window's.
+        if window.is_none() && imp.viewport.take().is_some() {
+            self.queue_draw();
+        }
+        imp.nvim_window.replace(window);
+    }
+
+    /// Nvim window associated to this grid.
+    pub fn nvim_window(&self) -> Option<Window> {
+        self.imp().nvim_window.borrow().clone()
+    }
+
+    /// Sets the background color of the grid's window, overriding the
+    /// default background.
+    pub fn set_normal_bg(&self, bg: Option<Color>) {
+        self.imp().buffer.set_normal_bg(bg);
+    }
+
+    /// Sets the transparency of the grid's backgrounds, from 0 (opaque) to
+    /// 100 (i.e. 'winblend').
+    pub fn set_blend(&self, blend: u8) {
+        self.imp().buffer.set_blend(blend);
+    }
+
+    /// Sets the window's viewport, for the scrollbar.
+    pub fn set_viewport(&self, topline: i64, botline: i64, line_count: i64) {
+        let viewport = imp::Viewport {
+            topline,
+            botline,
+            line_count,
+        };
+        if self.imp().viewport.replace(Some(viewport)) != Some(viewport) {
+            self.queue_draw();
+        }
+    }
+
+    /// Sets the columns of the guide lines (e.g. from 'colorcolumn').
+    pub fn set_guides(&self, guides: Vec<usize>) {
+        self.imp().buffer.set_guides(guides);
+    }
+
+    /// Render statistics of the grid's buffer.
+    pub fn render_stats(&self) -> RenderStats {
+        self.imp().buffer.render_stats()
+    }
+
+    /// Text of the grid's rows in the range `start..end` (clamped to the
+    /// grid's height).
+    pub fn text(&self, start: usize, end: usize) -> Vec<String> {
+        let rows = self.imp().buffer.get_rows();
+        let end = end.min(rows.len());
+        let start = start.min(end);
+        rows[start..end].iter().map(Row::text).collect()
+    }
+
+    /// Description of the cell at `row`, `col`, for the cell inspector.
+    fn cell_info(&self, row: usize, col: usize, colors: &Colors) -> Option<String> {
+        let rows = self.imp().buffer.get_rows();
+        let cell = rows.get(row)?.cells.get(col)?;
+        let hl = colors.get_hl(&cell.hl_id);
+
+        let window = self
+            .nvim_window_handle()
+            .map(|handle| handle.to_string())
+            .unwrap_or_else(|| String::from("-"));
+        let codepoints = cell
+            .text
+            .chars()
+            .map(|c| format!("U+{:04X}", c as u32))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut info = format!(
+            "grid {}, window {}, row {}, col {}\n\
+             text {:?} ({}){}\n\
+             hl_id {}: fg #{} bg #{} sp #{}",
+            self.id(),
+            window,
+            row,
+            col,
+            cell.text,
+            codepoints,
+            if cell.double_width {
+                ", double width"
+            } else {
+                ""
+            },
+            cell.hl_id,
+            hl.fg().as_hex(),
+            hl.bg().as_hex(),
+            hl.sp().as_hex(),
+        );
+
+        if let Some(attr) = hl.hl_attr() {
+            let flags = attr.styles();
+            if !flags.is_empty() {
+                info.push('\n');
+                info.push_str(&flags.join(" "));
+            }
+        }
+
+        if let Some(ref url) = cell.url {
+            info.push_str(&format!("\nurl {}", url));
+        }
+
+        Some(info)
+    }
+
+    /// Text of `row` from `col` up to the cursor. Empty if the cursor isn't
+    /// on `row`, or is before `col`.
+    pub fn text_before_cursor(&self, row: i64, col: i64) -> String {
+        let imp = self.imp();
+        let col = col.max(0);
+        let cursor_col = imp.cursor.col();
+        if imp.cursor.row() != row || cursor_col <= col {
+            return String::new();
+        }
+
+        imp.buffer
+            .get_rows()
+            .get(row as usize)
+            .map(|row| {
+                row.cells
+                    .iter()
+                    .skip(col as usize)
+                    .take((cursor_col - col) as usize)
+                    .map(|cell| cell.text.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Draws the content of the latest flush.
+    pub fn queue_draw_buffer(&self) {
+        self.imp().buffer.queue_draw();
+    }
+
+    /// Sets the rows of the diff bands, as `(start, end)` ranges.
+    pub fn set_diff_bands(&self, bands: Vec<(usize, usize)>) {
+        self.imp().buffer.set_diff_bands(bands);
     }
 
     pub fn connect_mouse<F>(&self, f: F)
     where
         F: Fn(i64, Mouse, Action, String, usize, usize) + 'static + Clone,
     {
-        let click = clone!(@weak self as obj, @strong f, => move |
+        // Latest drag position that hasn't been sent to nvim yet. Drags are
+        // sent at most once per frame, so fast drag selections don't go
+        // through all the intermediate states.
+        let pending_drag: Rc<RefCell<Option<(Mouse, String, usize, usize)>>> = Default::default();
+        let send_drag = clone!(@weak self as obj, @strong pending_drag, @strong f => move || {
+            let drag = pending_drag.borrow_mut().take();
+            if let Some((mouse, modifier, row, col)) = drag {
+                f(obj.imp().id.get(), mouse, Action::Drag, modifier, row, col);
+            }
+        });
+
+        let click = clone!(@weak self as obj, @strong f, @strong send_drag => move |
             gst: &gtk::GestureClick,
             action: Action,
             n: i32,
@@ -84,6 +271,11 @@ impl Grid {
             let modifier = crate::input::modifier_to_nvim(&gst.current_event_state());
             let mouse = Mouse::from(gst);
 
+            // Make sure the release is sent after the last drag position.
+            if matches!(action, Action::Released) {
+                send_drag();
+            }
+
             for _ in 0..n {
                 f(obj.imp().id.get(), mouse, action, modifier.clone(), row, col)
             }
@@ -104,7 +296,7 @@ impl Grid {
                 start.replace((x, y));
             }));
         imp.gesture_drag.connect_drag_update(
-            clone!(@strong start, @strong pos, @weak self as obj, @strong f => move |gst, x, y| {
+            clone!(@strong start, @strong pos, @weak self as obj, @strong pending_drag, @strong send_drag => move |gst, x, y| {
                 let start = start.borrow();
                 let x = start.0 + x;
                 let y = start.1 + y;
@@ -119,7 +311,15 @@ impl Grid {
 
                     let modifier = crate::input::modifier_to_nvim(&gst.current_event_state());
                     let mouse = Mouse::from(gst);
-                    f(obj.imp().id.get(), mouse, Action::Drag, modifier, row, col);
+                    let scheduled = pending_drag
+                        .replace(Some((mouse, modifier, row, col)))
+                        .is_some();
+                    if !scheduled {
+                        obj.add_tick_callback(clone!(@strong send_drag => move |_, _| {
+                            send_drag();
+                            glib::ControlFlow::Break
+                        }));
+                    }
                 }
             }),
         );
@@ -130,8 +330,15 @@ impl Grid {
                 mouse_pos.replace((x, y));
             }));
 
+        // Accumulated scroll delta (in cells) that hasn't been sent to nvim yet.
+        let scroll_acc = Rc::new(Cell::new((0.0, 0.0)));
+        imp.event_controller_scroll
+            .connect_scroll_begin(clone!(@strong scroll_acc => move |_| {
+                scroll_acc.set((0.0, 0.0));
+            }));
+
         imp.event_controller_scroll.connect_scroll(
-            clone!(@weak self as obj, @strong mouse_pos => @default-return glib::Propagation::Proceed, move |evt, dx, dy| {
+            clone!(@weak self as obj, @strong mouse_pos, @strong scroll_acc => @default-return glib::Propagation::Proceed, move |evt, dx, dy| {
                 let modifier = crate::input::modifier_to_nvim(&evt.current_event_state());
                 let pos = mouse_pos.borrow();
                 let font = obj.font();
@@ -140,14 +347,39 @@ impl Grid {
 
                 let id = obj.imp().id.get();
 
-                if dx > 0.0 {
-                    f(id, Mouse::Wheel, Action::ScrollRight, modifier, row, col);
-                } else if dx < 0.0 {
-                    f(id, Mouse::Wheel, Action::ScrollLeft, modifier, row, col);
-                } else if dy > 0.0 {
-                    f(id, Mouse::Wheel, Action::ScrollDown, modifier, row, col);
-                } else if dy < 0.0 {
-                    f(id, Mouse::Wheel, Action::ScrollUp, modifier, row, col);
+                // NOTE(ville): Touchpads report their deltas in surface pixels
+                // while mouse wheels report (possibly fractional) wheel ticks.
+                // Scale the pixels to cells and accumulate the deltas, so we
+                // only send whole scroll steps to nvim.
+                let (dx, dy) = match evt.unit() {
+                    gdk::ScrollUnit::Surface => (
+                        dx / (font.char_width() / SCALE) as f64,
+                        dy / (font.height() / SCALE) as f64,
+                    ),
+                    _ => (dx, dy),
+                };
+
+                let (acc_x, acc_y) = scroll_acc.get();
+                let (acc_x, acc_y) = (acc_x + dx, acc_y + dy);
+                let (steps_x, steps_y) = (acc_x.trunc(), acc_y.trunc());
+                scroll_acc.set((acc_x - steps_x, acc_y - steps_y));
+
+                let action_x = if steps_x > 0.0 {
+                    Action::ScrollRight
+                } else {
+                    Action::ScrollLeft
+                };
+                for _ in 0..steps_x.abs() as usize {
+                    f(id, Mouse::Wheel, action_x, modifier.clone(), row, col);
+                }
+
+                let action_y = if steps_y > 0.0 {
+                    Action::ScrollDown
+                } else {
+                    Action::ScrollUp
+                };
+                for _ in 0..steps_y.abs() as usize {
+                    f(id, Mouse::Wheel, action_y, modifier.clone(), row, col);
                 }
 
                 glib::Propagation::Stop
@@ -165,9 +397,15 @@ impl Grid {
             .resize(event.width as usize, event.height as usize);
     }
 
+    /// Renders the grid's content, see `GridBuffer::flush`.
     pub fn flush(&self, colors: &Colors) {
         let imp = self.imp();
         imp.buffer.flush(colors);
+        imp.scrollbar_color
+            .set(*colors.get_hl_group(&HlGroup::PmenuThumb).bg());
+
+        imp.inspector_colors
+            .replace(self.cell_inspector().then(|| colors.clone()));
 
         if imp.active.get() {
             // Update the text under the cursor, since in some cases neovim doesn't
@@ -177,18 +415,23 @@ impl Grid {
             // valid. In those cases, set the cursor's text to empty string and
             // hope that neovim will soon give us updated cursor position.
             let rows = imp.buffer.get_rows();
-            let text = rows
+            let cell = rows
                 .get(imp.cursor.row() as usize)
-                .and_then(|row| row.cells.get(imp.cursor.col() as usize))
-                .map(|cell| cell.text.clone())
-                .unwrap_or_default();
-            imp.cursor.set_text(text);
+                .and_then(|row| row.cells.get(imp.cursor.col() as usize));
+            imp.cursor.set_content(&cell.cloned().unwrap_or_default());
             imp.cursor.flush(colors);
         }
     }
 
+    /// Clears the grid (i.e. `grid_clear`). The cursor's cell is updated
+    /// on the next flush.
     pub fn clear(&self) {
-        self.imp().buffer.clear();
+        let imp = self.imp();
+        imp.buffer.clear();
+
+        // The popovers show the cleared content.
+        imp.hide_fold_preview();
+        imp.inspector.popdown();
     }
 
     pub fn cursor_goto(&self, col: i64, row: i64) {
@@ -206,6 +449,21 @@ impl Grid {
         self.imp().buffer.scroll(event);
     }
 
+    /// Starts a scroll animation for `delta` rows (from win_viewport event).
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - The viewport's scroll delta.
+    /// * `start_time` - Start time of the animation, in frame clock time.
+    pub fn scroll_viewport(&self, delta: f64, start_time: f64) {
+        self.imp().buffer.scroll_viewport(delta, start_time);
+    }
+
+    /// Restarts the cursor's blink cycle, keeping it visible for now.
+    pub fn reset_cursor_blink(&self) {
+        self.imp().cursor.reset_blink();
+    }
+
     pub fn mode_change(&self, mode: &ModeInfo) {
         self.set_property("mode-info", mode);
     }
@@ -216,3 +474,114 @@ impl Default for Grid {
         Self::new(0, &Default::default())
     }
 }
+
+/// Pango markup of a fold preview's lines.
+fn fold_preview_markup(preview: &FoldPreview) -> String {
+    preview
+        .lines
+        .iter()
+        .map(|line| {
+            line.iter()
+                .map(|chunk| {
+                    let mut attrs = String::new();
+                    if let Some(fg) = chunk.fg {
+                        attrs.push_str(&format!(
+                            " foreground=\"#{}\"",
+                            Color::from_i64(fg).as_hex()
+                        ));
+                    }
+                    if chunk.bold {
+                        attrs.push_str(" weight=\"bold\"");
+                    }
+                    if chunk.italic {
+                        attrs.push_str(" style=\"italic\"");
+                    }
+
+                    format!(
+                        "<span{}>{}</span>",
+                        attrs,
+                        glib::markup_escape_text(&chunk.text)
+                    )
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Minimum height of the scrollbar's thumb.
+const SCROLLBAR_MIN_THUMB: f32 = 16.0;
+
+/// Position and height of the scrollbar's thumb in a grid of `height`, or
+/// `None` if the whole buffer fits in the window.
+fn scrollbar_thumb(viewport: &imp::Viewport, height: f32) -> Option<(f32, f32)> {
+    let line_count = viewport.line_count.max(1);
+    let visible = viewport.botline.min(line_count) - viewport.topline;
+    if viewport.topline <= 0 && visible >= line_count {
+        return None;
+    }
+
+    let thumb = (height * visible.max(1) as f32 / line_count as f32)
+        .max(SCROLLBAR_MIN_THUMB)
+        .min(height);
+    let scrollable = (line_count - visible).max(1);
+    let y = (height - thumb) * (viewport.topline.min(scrollable) as f32 / scrollable as f32);
+
+    Some((y, thumb))
+}
+
+/// The top line (zero based) for the scrollbar's thumb at `y`.
+fn scrollbar_topline(viewport: &imp::Viewport, height: f32, y: f32) -> i64 {
+    let (_, thumb) = match scrollbar_thumb(viewport, height) {
+        Some(thumb) => thumb,
+        None => return viewport.topline,
+    };
+
+    let line_count = viewport.line_count.max(1);
+    let visible = viewport.botline.min(line_count) - viewport.topline;
+    let scrollable = (line_count - visible).max(1);
+    let track = (height - thumb).max(1.0);
+
+    ((y.clamp(0.0, track) / track) * scrollable as f32).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{imp::Viewport, scrollbar_thumb, scrollbar_topline};
+
+    #[test]
+    fn test_scrollbar_thumb() {
+        let viewport = |topline, botline, line_count| Viewport {
+            topline,
+            botline,
+            line_count,
+        };
+
+        // Everything fits.
+        assert_eq!(scrollbar_thumb(&viewport(0, 50, 20), 100.0), None);
+        // At the top, middle and bottom.
+        assert_eq!(
+            scrollbar_thumb(&viewport(0, 25, 100), 100.0),
+            Some((0.0, 25.0))
+        );
+        assert_eq!(
+            scrollbar_thumb(&viewport(25, 50, 100), 100.0),
+            Some((25.0, 25.0))
+        );
+        assert_eq!(
+            scrollbar_thumb(&viewport(75, 100, 100), 100.0),
+            Some((75.0, 25.0))
+        );
+        // Minimum height.
+        assert_eq!(
+            scrollbar_thumb(&viewport(0, 10, 1000), 100.0),
+            Some((0.0, 16.0))
+        );
+
+        // Round trip.
+        let vp = viewport(25, 50, 100);
+        assert_eq!(scrollbar_topline(&vp, 100.0, 25.0), 25);
+        assert_eq!(scrollbar_topline(&vp, 100.0, -10.0), 0);
+        assert_eq!(scrollbar_topline(&vp, 100.0, 500.0), 75);
+    }
+}
@@ -0,0 +1,85 @@
+use std::cell::RefCell;
+use std::time::Duration;
+
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use crate::components::toast::{Severity, Toast};
+
+/// How long the "copied" toast is shown.
+const COPIED_TOAST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(gtk::CompositeTemplate, Default)]
+#[template(resource = "/com/github/vhakulinen/gnvim/hlinspector.ui")]
+pub struct HlInspector {
+    #[template_child(id = "entry")]
+    pub entry: TemplateChild<gtk::SearchEntry>,
+    #[template_child(id = "list")]
+    pub list: TemplateChild<gtk::ListBox>,
+    #[template_child(id = "toast")]
+    pub toast: TemplateChild<Toast>,
+
+    /// Lowercase search text and the copied colors of the rows, by the row
+    /// index.
+    pub entries: RefCell<Vec<(String, String)>>,
+}
+
+#[gtk::template_callbacks]
+impl HlInspector {
+    #[template_callback]
+    fn search_changed(&self) {
+        self.list.invalidate_filter();
+    }
+
+    #[template_callback]
+    fn row_activated(&self, row: &gtk::ListBoxRow) {
+        let entries = self.entries.borrow();
+        if let Some((_, copy)) = entries.get(row.index() as usize) {
+            self.obj().clipboard().set_text(copy);
+            self.toast.show(
+                &format!("Copied {}", copy),
+                Severity::Info,
+                Some(COPIED_TOAST_TIMEOUT),
+                vec![],
+            );
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for HlInspector {
+    const NAME: &'static str = "HlInspector";
+    type Type = super::HlInspector;
+    type ParentType = gtk::Window;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for HlInspector {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        self.list
+            .set_filter_func(glib::clone!(@weak obj => @default-return true, move |row| {
+                let imp = obj.imp();
+                let query = imp.entry.text().to_lowercase();
+                imp.entries
+                    .borrow()
+                    .get(row.index() as usize)
+                    .map(|(text, _)| text.contains(query.as_str()))
+                    .unwrap_or(true)
+            }));
+    }
+}
+
+impl WidgetImpl for HlInspector {}
+
+impl WindowImpl for HlInspector {}
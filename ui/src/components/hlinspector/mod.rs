@@ -0,0 +1,88 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use crate::colors::Colors;
+
+mod imp;
+
+glib::wrapper! {
+    /// Developer window listing all the highlights defined by nvim.
+    pub struct HlInspector(ObjectSubclass<imp::HlInspector>)
+        @extends gtk::Window, gtk::Widget,
+        @implements gtk::Accessible, gtk::Buildable, gtk::ConstraintTarget, gtk::Native, gtk::Root, gtk::ShortcutManager;
+}
+
+impl HlInspector {
+    pub fn new(parent: &gtk::Window) -> Self {
+        glib::Object::builder()
+            .property("transient-for", parent)
+            .build()
+    }
+
+    /// Rebuilds the list from `colors`.
+    pub fn update(&self, colors: &Colors) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.list.first_child() {
+            imp.list.remove(&child);
+        }
+
+        let mut groups = colors
+            .hl_group_names
+            .iter()
+            .map(|(name, id)| (*id, name.as_str()))
+            .collect::<Vec<_>>();
+        groups.sort();
+
+        let mut ids = colors.hls.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+
+        let mut entries = Vec::with_capacity(ids.len());
+        for id in ids {
+            let hl = colors.get_hl(&id);
+            let names = groups
+                .iter()
+                .filter(|(group_id, _)| *group_id == id)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>();
+            let styles = hl.hl_attr().map(|attr| attr.styles()).unwrap_or_default();
+
+            let info = format!(
+                "{:>4}  fg #{} bg #{} sp #{}  {}  {}",
+                id,
+                hl.fg().as_hex(),
+                hl.bg().as_hex(),
+                hl.sp().as_hex(),
+                styles.join(" "),
+                names.join(" "),
+            );
+
+            let sample = gtk::Label::new(None);
+            sample.set_markup(&hl.pango_markup(" Sample "));
+            let label = gtk::Label::builder()
+                .label(&info)
+                .xalign(0.0)
+                .ellipsize(gtk::pango::EllipsizeMode::End)
+                .build();
+            label.add_css_class("monospace");
+
+            let row = gtk::Box::builder()
+                .orientation(gtk::Orientation::Horizontal)
+                .spacing(12)
+                .build();
+            row.append(&sample);
+            row.append(&label);
+            imp.list.append(&row);
+
+            let copy = format!(
+                "fg {} bg {} sp {}",
+                hl.fg().to_str(),
+                hl.bg().to_str(),
+                hl.sp().to_str()
+            );
+            entries.push((info.to_lowercase(), copy));
+        }
+
+        imp.entries.replace(entries);
+        imp.list.invalidate_filter();
+    }
+}
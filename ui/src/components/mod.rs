@@ -2,26 +2,38 @@ pub mod appwindow;
 pub mod cmdline;
 pub mod cursor;
 pub mod externalwin;
+pub mod findbar;
 pub mod fixedz;
 pub mod grid;
 #[path = "./grid-buffer/mod.rs"]
 pub mod grid_buffer;
+pub mod hlinspector;
+pub mod msgarea;
+pub mod msghistory;
+pub mod msgstatus;
 pub mod msgwin;
 pub mod omnibar;
 pub mod overflower;
 pub mod popupmenu;
 pub mod shell;
 pub mod tabline;
+pub mod toast;
 
 pub use cmdline::Cmdline;
 pub use cursor::Cursor;
 pub use externalwin::ExternalWindow;
+pub use findbar::FindBar;
 pub use fixedz::Fixedz;
 pub use grid::Grid;
 pub use grid_buffer::GridBuffer;
+pub use hlinspector::HlInspector;
+pub use msgarea::MsgArea;
+pub use msghistory::MsgHistory;
+pub use msgstatus::MsgStatus;
 pub use msgwin::MsgWin;
 pub use omnibar::Omnibar;
 pub use overflower::Overflower;
 pub use popupmenu::Popupmenu;
 pub use shell::Shell;
 pub use tabline::Tabline;
+pub use toast::Toast;
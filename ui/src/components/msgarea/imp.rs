@@ -0,0 +1,39 @@
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+#[derive(gtk::CompositeTemplate, Default)]
+#[template(resource = "/com/github/vhakulinen/gnvim/msgarea.ui")]
+pub struct MsgArea {
+    #[template_child(id = "scroll")]
+    pub scroll: TemplateChild<gtk::ScrolledWindow>,
+    #[template_child(id = "view")]
+    pub view: TemplateChild<gtk::TextView>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MsgArea {
+    const NAME: &'static str = "MsgArea";
+    type Type = super::MsgArea;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+        klass.set_css_name("msgarea");
+
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for MsgArea {
+    fn dispose(&self) {
+        while let Some(child) = self.obj().first_child() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for MsgArea {}
@@ -0,0 +1,90 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::types::MsgShowContent;
+
+use crate::colors::Colors;
+
+mod imp;
+
+glib::wrapper! {
+    /// Area for nvim's messages (i.e. msg_show), when nvim doesn't draw the
+    /// messages itself (i.e. with ext_messages).
+    pub struct MsgArea(ObjectSubclass<imp::MsgArea>)
+        @extends gtk::Widget,
+        @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
+}
+
+impl MsgArea {
+    /// Shows a message after the previous ones, scrolled to the latest
+    /// message.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The message's text chunks.
+    /// * `replace_last` - If the message replaces the previous one (e.g. a
+    ///                    progress message).
+    /// * `colors` - Colors for the chunks' highlights.
+    pub fn show(&self, content: &[MsgShowContent], replace_last: bool, colors: &Colors) {
+        let imp = self.imp();
+        let buf = imp.view.buffer();
+
+        // The "last" mark is at the start of the latest message.
+        let last = buf.mark("last");
+        match last {
+            Some(ref mark) if replace_last => {
+                let mut start = buf.iter_at_mark(mark);
+                buf.delete(&mut start, &mut buf.end_iter());
+            }
+            Some(_) => buf.insert(&mut buf.end_iter(), "\n"),
+            None => {}
+        }
+
+        let mut iter = buf.end_iter();
+        match last {
+            Some(ref mark) => buf.move_mark(mark, &iter),
+            None => {
+                buf.create_mark(Some("last"), &iter, true);
+            }
+        }
+
+        // Multiline messages (e.g. `:ls`) start on a new line.
+        let markup = content
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let text = if i == 0 {
+                    chunk.text_chunk.trim_start_matches('\n')
+                } else {
+                    &chunk.text_chunk
+                };
+                colors.get_hl(&chunk.attr_id).pango_markup(text)
+            })
+            .collect::<String>();
+        buf.insert_markup(&mut iter, &markup);
+
+        self.set_visible(true);
+
+        let iter = buf.end_iter();
+        let mark = buf
+            .mark("end")
+            .unwrap_or_else(|| buf.create_mark(Some("end"), &iter, false));
+        buf.move_mark(&mark, &iter);
+        imp.view.scroll_to_mark(&mark, 0.0, false, 0.0, 0.0);
+    }
+
+    /// Clears the messages (i.e. msg_clear).
+    pub fn clear(&self) {
+        let buf = self.imp().view.buffer();
+        buf.set_text("");
+        if let Some(mark) = buf.mark("last") {
+            buf.delete_mark(&mark);
+        }
+
+        self.set_visible(false);
+    }
+}
+
+impl Default for MsgArea {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,78 @@
+use std::cell::RefCell;
+
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::NeovimApi;
+
+use crate::nvim::Neovim;
+use crate::warn;
+
+#[derive(gtk::CompositeTemplate, glib::Properties, Default)]
+#[properties(wrapper_type = super::MsgHistory)]
+#[template(resource = "/com/github/vhakulinen/gnvim/msghistory.ui")]
+pub struct MsgHistory {
+    #[template_child(id = "scroll")]
+    pub scroll: TemplateChild<gtk::ScrolledWindow>,
+    #[template_child(id = "view")]
+    pub view: TemplateChild<gtk::TextView>,
+
+    #[property(get, set)]
+    pub nvim: RefCell<Neovim>,
+}
+
+#[gtk::template_callbacks]
+impl MsgHistory {
+    /// Clears nvim's message history too.
+    #[template_callback]
+    async fn clear(&self) {
+        self.obj().clear();
+
+        let nvim = self.nvim.borrow().clone();
+        let res = match nvim.nvim_command("messages clear").await {
+            Ok(res) => res,
+            Err(err) => {
+                warn!("call to nvim failed: {:?}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = res.await {
+            warn!("failed to clear the messages: {:?}", err);
+        }
+    }
+
+    #[template_callback]
+    fn close(&self) {
+        self.obj().close();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MsgHistory {
+    const NAME: &'static str = "MsgHistory";
+    type Type = super::MsgHistory;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+        klass.set_css_name("msghistory");
+
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for MsgHistory {
+    fn dispose(&self) {
+        while let Some(child) = self.obj().first_child() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for MsgHistory {}
@@ -0,0 +1,64 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::types::MsgHistoryShowEntry;
+
+use crate::colors::Colors;
+
+mod imp;
+
+glib::wrapper! {
+    /// Panel for nvim's message history (i.e. `:messages`).
+    pub struct MsgHistory(ObjectSubclass<imp::MsgHistory>)
+        @extends gtk::Widget,
+        @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
+}
+
+impl MsgHistory {
+    /// Shows the panel with `entries`, scrolled to the latest message.
+    pub fn show(&self, entries: &[MsgHistoryShowEntry], colors: &Colors) {
+        let imp = self.imp();
+
+        let buf = imp.view.buffer();
+        buf.set_text("");
+        let mut iter = buf.start_iter();
+
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                buf.insert(&mut iter, "\n");
+            }
+
+            let markup = entry
+                .content
+                .iter()
+                .map(|chunk| {
+                    colors
+                        .get_hl(&chunk.attr_id)
+                        .pango_markup(&chunk.text_chunk)
+                })
+                .collect::<String>();
+            buf.insert_markup(&mut iter, &markup);
+        }
+
+        self.set_visible(true);
+
+        let iter = buf.end_iter();
+        let mark = buf
+            .mark("end")
+            .unwrap_or_else(|| buf.create_mark(Some("end"), &iter, false));
+        buf.move_mark(&mark, &iter);
+        imp.view.scroll_to_mark(&mark, 0.0, false, 0.0, 0.0);
+    }
+
+    pub fn clear(&self) {
+        self.imp().view.buffer().set_text("");
+    }
+
+    pub fn close(&self) {
+        self.set_visible(false);
+    }
+}
+
+impl Default for MsgHistory {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,41 @@
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+#[derive(gtk::CompositeTemplate, Default)]
+#[template(resource = "/com/github/vhakulinen/gnvim/msgstatus.ui")]
+pub struct MsgStatus {
+    #[template_child(id = "showmode")]
+    pub showmode: TemplateChild<gtk::Label>,
+    #[template_child(id = "showcmd")]
+    pub showcmd: TemplateChild<gtk::Label>,
+    #[template_child(id = "ruler")]
+    pub ruler: TemplateChild<gtk::Label>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for MsgStatus {
+    const NAME: &'static str = "MsgStatus";
+    type Type = super::MsgStatus;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+        klass.set_css_name("msgstatus");
+
+        klass.bind_template();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for MsgStatus {
+    fn dispose(&self) {
+        while let Some(child) = self.obj().first_child() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for MsgStatus {}
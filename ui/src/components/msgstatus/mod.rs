@@ -0,0 +1,63 @@
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::types::MsgShowContent;
+
+use crate::colors::Colors;
+
+mod imp;
+
+glib::wrapper! {
+    /// Strip for the showmode, showcmd and ruler messages, when nvim doesn't
+    /// draw the messages itself (i.e. with ext_messages).
+    pub struct MsgStatus(ObjectSubclass<imp::MsgStatus>)
+        @extends gtk::Widget,
+        @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
+}
+
+impl MsgStatus {
+    pub fn set_showmode(&self, content: &[MsgShowContent], colors: &Colors) {
+        self.set_content(&self.imp().showmode, content, colors);
+    }
+
+    pub fn set_showcmd(&self, content: &[MsgShowContent], colors: &Colors) {
+        self.set_content(&self.imp().showcmd, content, colors);
+    }
+
+    pub fn set_ruler(&self, content: &[MsgShowContent], colors: &Colors) {
+        self.set_content(&self.imp().ruler, content, colors);
+    }
+
+    /// Clears the messages, e.g. when the ui state is rebuilt.
+    pub fn clear(&self) {
+        let imp = self.imp();
+        for label in [&imp.showmode, &imp.showcmd, &imp.ruler] {
+            label.set_text("");
+        }
+        self.set_visible(false);
+    }
+
+    fn set_content(&self, label: &gtk::Label, content: &[MsgShowContent], colors: &Colors) {
+        let markup = content
+            .iter()
+            .map(|chunk| {
+                colors
+                    .get_hl(&chunk.attr_id)
+                    .pango_markup(&chunk.text_chunk)
+            })
+            .collect::<String>();
+        label.set_markup(&markup);
+
+        // Only take space when there's something to show.
+        let imp = self.imp();
+        self.set_visible(
+            [&imp.showmode, &imp.showcmd, &imp.ruler]
+                .iter()
+                .any(|label| !label.label().is_empty()),
+        );
+    }
+}
+
+impl Default for MsgStatus {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
@@ -1,12 +1,22 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use gtk::{glib, graphene, gsk, pango, prelude::*, subclass::prelude::*};
 
 use crate::child_iter::IterChildren;
 
 #[derive(Default)]
 pub struct MsgWin {
     pub height: Cell<i32>,
+    /// Row of the root grid where the message grid starts, from the latest
+    /// `msg_set_pos`.
+    pub row: Cell<Option<i64>>,
+    /// `sep_char` of the latest `msg_set_pos`, when the messages have
+    /// scrolled over the other grids.
+    pub sep_char: RefCell<Option<String>>,
+    /// Line filled with `sep_char`, above the message grid.
+    pub sep: gtk::Label,
+    /// Height of the separator line, zero when it's hidden.
+    pub sep_height: Cell<i32>,
 }
 
 #[glib::object_subclass]
@@ -20,13 +30,25 @@ impl ObjectImpl for MsgWin {
     fn constructed(&self) {
         self.parent_constructed();
 
-        self.obj().set_property("overflow", gtk::Overflow::Hidden);
+        let obj = self.obj();
+        obj.set_property("overflow", gtk::Overflow::Hidden);
+
+        self.sep.add_css_class("msg-sep");
+        self.sep.set_xalign(0.0);
+        self.sep.set_ellipsize(pango::EllipsizeMode::End);
+        self.sep.set_visible(false);
+        self.sep.set_parent(&*obj);
+    }
+
+    fn dispose(&self) {
+        self.sep.unparent();
     }
 }
 
 impl WidgetImpl for MsgWin {
     fn measure(&self, orientation: gtk::Orientation, for_size: i32) -> (i32, i32, i32, i32) {
-        let m = if let Some(child) = self.obj().first_child() {
+        let sep = self.sep.upcast_ref::<gtk::Widget>();
+        let m = if let Some(child) = self.obj().iter_children().find(|child| child != sep) {
             child.measure(orientation, for_size)
         } else {
             self.parent_measure(orientation, for_size)
@@ -44,9 +66,24 @@ impl WidgetImpl for MsgWin {
     fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
         self.parent_size_allocate(width, height, baseline);
 
+        let sep_height = self.sep_height.get();
+        if self.sep.should_layout() {
+            self.sep.allocate(width, sep_height, -1, None);
+        }
+
+        let sep = self.sep.upcast_ref::<gtk::Widget>();
+        let offset = gsk::Transform::new().translate(&graphene::Point::new(0.0, sep_height as f32));
         for child in self.obj().iter_children() {
-            if child.should_layout() {
-                child.allocate(width, height, -1, None);
+            if child != *sep && child.should_layout() {
+                // The message grid is usually taller than the area it's
+                // shown in, and the rest of it is clipped.
+                let (min_h, _, _, _) = child.measure(gtk::Orientation::Vertical, width);
+                child.allocate(
+                    width,
+                    (height - sep_height).max(min_h),
+                    -1,
+                    Some(offset.clone()),
+                );
             }
         }
     }
@@ -20,4 +20,35 @@ impl MsgWin {
     pub fn height(&self) -> i32 {
         self.imp().height.get()
     }
+
+    pub fn set_row(&self, row: Option<i64>) {
+        self.imp().row.set(row);
+    }
+
+    pub fn row(&self) -> Option<i64> {
+        self.imp().row.get()
+    }
+
+    pub fn set_sep_char(&self, sep_char: Option<String>) {
+        self.imp().sep_char.replace(sep_char);
+    }
+
+    pub fn sep_char(&self) -> Option<String> {
+        self.imp().sep_char.borrow().clone()
+    }
+
+    /// Shows `line` as the separator above the message grid, `height` tall.
+    /// `None` hides the separator.
+    pub fn set_separator(&self, line: Option<&str>, height: i32) {
+        let imp = self.imp();
+        imp.sep.set_text(line.unwrap_or_default());
+        imp.sep.set_visible(line.is_some());
+        imp.sep_height.set(if line.is_some() { height } else { 0 });
+        self.queue_allocate();
+    }
+
+    /// Height of the separator line, i.e. where the message grid starts.
+    pub fn sep_height(&self) -> i32 {
+        self.imp().sep_height.get()
+    }
 }
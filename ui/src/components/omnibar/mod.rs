@@ -33,6 +33,16 @@ impl Omnibar {
         imp.cmdline_revealer.set_reveal_child(false);
     }
 
+    /// Hides the cmdline and its popupmenu, e.g. when the ui state is
+    /// rebuilt.
+    pub fn reset(&self) {
+        let imp = self.imp();
+
+        imp.cmdline.block_hide();
+        imp.cmdline_revealer.set_reveal_child(false);
+        self.handle_popupmenu_hide();
+    }
+
     pub fn handle_cmdline_pos(&self, event: CmdlinePos) {
         let imp = self.imp();
 
@@ -14,10 +14,15 @@ use super::Row;
 #[properties(wrapper_type = super::Popupmenu)]
 #[template(resource = "/com/github/vhakulinen/gnvim/popupmenu.ui")]
 pub struct Popupmenu {
+    #[template_child(id = "overlay")]
+    pub overlay: TemplateChild<gtk::Overlay>,
     #[template_child(id = "scrolled-window")]
     pub scrolledwindow: TemplateChild<gtk::ScrolledWindow>,
     #[template_child(id = "list-view")]
     pub listview: TemplateChild<gtk::ListView>,
+    /// Position of the selected item, e.g. "12/87".
+    #[template_child(id = "indicator")]
+    pub indicator: TemplateChild<gtk::Label>,
 
     #[property(get, set)]
     pub max_height: Cell<i32>,
@@ -25,6 +30,13 @@ pub struct Popupmenu {
     pub max_width: Cell<i32>,
 
     pub store: super::Model,
+    /// Info (e.g. documentation) of the selected item.
+    #[property(get, set)]
+    pub info: RefCell<String>,
+    /// The completion base (i.e. the text typed so far). Its characters are
+    /// highlighted in the items.
+    #[property(get, set)]
+    pub base: RefCell<String>,
     #[property(get, set)]
     pub font: RefCell<Font>,
 }
@@ -59,6 +71,9 @@ impl ObjectImpl for Popupmenu {
             obj.bind_property("font", &item, "font")
                 .flags(glib::BindingFlags::SYNC_CREATE)
                 .build();
+            obj.bind_property("base", &item, "base")
+                .flags(glib::BindingFlags::SYNC_CREATE)
+                .build();
 
             listitem.set_child(Some(&item));
         }));
@@ -81,6 +96,42 @@ impl ObjectImpl for Popupmenu {
 
         self.listview.set_model(Some(&self.store));
         self.listview.set_factory(Some(&factory));
+
+        self.store
+            .connect_selection_changed(clone!(@weak self as imp => move |_, _, _| {
+                imp.update_indicator();
+            }));
+        self.store
+            .connect_items_changed(clone!(@weak self as imp => move |_, _, _, _| {
+                imp.update_indicator();
+            }));
+        self.scrolledwindow
+            .vadjustment()
+            .connect_changed(clone!(@weak self as imp => move |_| {
+                imp.update_indicator();
+            }));
+    }
+}
+
+impl Popupmenu {
+    /// Updates the position indicator. Only shown when the items don't fit
+    /// in the popupmenu.
+    fn update_indicator(&self) {
+        let adj = self.scrolledwindow.vadjustment();
+        let total = self.store.total_items();
+        let overflows = adj.upper() > adj.page_size() || total > self.store.n_items();
+
+        self.indicator.set_visible(overflows);
+        if !overflows {
+            return;
+        }
+
+        let selected = self
+            .store
+            .selected_item()
+            .map(|n| (n + 1).to_string())
+            .unwrap_or_else(|| String::from("-"));
+        self.indicator.set_text(&format!("{}/{}", selected, total));
     }
 }
 
@@ -105,6 +156,6 @@ impl WidgetImpl for Popupmenu {
     fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
         self.parent_size_allocate(width, height, baseline);
 
-        self.scrolledwindow.allocate(width, height, baseline, None);
+        self.overlay.allocate(width, height, baseline, None);
     }
 }
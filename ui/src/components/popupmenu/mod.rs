@@ -11,7 +11,7 @@ pub use model::Model;
 use nvim::types::PopupmenuItem;
 use row::Row;
 
-use crate::{nvim::Neovim, spawn_local, SCALE};
+use crate::{nvim::Neovim, spawn_local, warn, SCALE};
 
 glib::wrapper! {
     pub struct Popupmenu(ObjectSubclass<imp::Popupmenu>)
@@ -29,6 +29,7 @@ impl Popupmenu {
             .collect::<Vec<_>>();
 
         imp.store.set_items(items);
+        self.set_info("");
     }
 
     pub fn store(&self) -> &Model {
@@ -49,9 +50,11 @@ impl Popupmenu {
 
         if n < 0 {
             imp.store.unselect_all();
+            self.set_info("");
         } else {
             let n = n as u32;
             imp.store.select_item(n, true);
+            self.set_info(imp.store.info(n).unwrap_or_default());
             imp.listview
                 .activate_action("list.scroll-to-item", Some(&n.to_variant()))
                 .expect("failed to activate list.scroll-to-item action");
@@ -76,7 +79,9 @@ impl Popupmenu {
                 .await
                 .unwrap();
 
-            res.await.expect("nvim_ui_pum_set_bounds failed");
+            if let Err(err) = res.await {
+                warn!("nvim_ui_pum_set_bounds failed: {:?}", err);
+            }
         }));
     }
 }
@@ -7,6 +7,8 @@ pub struct Model {
     pub items: RefCell<Vec<glib::BoxedAnyObject>>,
     pub to_add: RefCell<Vec<glib::BoxedAnyObject>>,
     pub lazy: RefCell<Option<glib::SourceId>>,
+    /// Total number of items, including the ones not added yet.
+    pub total: Cell<u32>,
 
     /// Item to select from the lazy loading operations.
     pub selected_item: Cell<Option<u32>>,
@@ -1,5 +1,6 @@
 use glib::{clone, subclass::prelude::*};
 use gtk::{gio, prelude::*};
+use nvim::types::PopupmenuItem;
 
 mod imp;
 
@@ -44,18 +45,71 @@ impl Model {
         }
     }
 
-    pub fn set_items(&self, items: Vec<glib::BoxedAnyObject>) {
+    /// Info (e.g. documentation) of the item at `position`. Includes the
+    /// items that haven't been added yet.
+    pub fn info(&self, position: u32) -> Option<String> {
         let imp = self.imp();
+        let items = imp.items.borrow();
+        let to_add = imp.to_add.borrow();
+
+        items
+            .iter()
+            .chain(to_add.iter())
+            .nth(position as usize)
+            .map(|item| item.borrow::<PopupmenuItem>().info.clone())
+    }
+
+    /// Total number of items, including the ones that haven't been added
+    /// yet.
+    pub fn total_items(&self) -> u32 {
+        self.imp().total.get()
+    }
+
+    pub fn selected_item(&self) -> Option<u32> {
+        self.imp().selected_item.get()
+    }
+
+    /// Replaces the items. Unchanged items at the start and the end are
+    /// reused, so the listview doesn't need to recreate their rows.
+    pub fn set_items(&self, mut items: Vec<glib::BoxedAnyObject>) {
+        let imp = self.imp();
+        imp.total.set(items.len() as u32);
 
         let mut lazy = imp.lazy.borrow_mut();
         if let Some(old) = lazy.take() {
             old.remove();
         }
 
-        let removed = self.n_items();
-        imp.items.replace(vec![]);
-        imp.to_add.replace(items);
-        self.lazy_add(removed);
+        let (prefix, suffix) = common_affixes(&imp.items.borrow()[..], &items[..], |a, b| {
+            *a.borrow::<PopupmenuItem>() == *b.borrow::<PopupmenuItem>()
+        });
+        let old_len = self.n_items() as usize;
+
+        // The items that are still to be added aren't in the list, so the
+        // suffix can only be reused if all the old items were added.
+        if suffix > 0 && imp.to_add.borrow().is_empty() {
+            let middle = items
+                .drain(prefix..items.len() - suffix)
+                .collect::<Vec<_>>();
+            let removed = old_len - prefix - suffix;
+            let added = middle.len();
+
+            imp.items
+                .borrow_mut()
+                .splice(prefix..prefix + removed, middle);
+
+            self.items_changed(prefix as u32, removed as u32, added as u32);
+            self.do_selection_changed(imp.selected_item.get());
+            return;
+        }
+
+        imp.items.borrow_mut().truncate(prefix);
+        imp.to_add.replace(items.split_off(prefix));
+        self.lazy_add((old_len - prefix) as u32);
+
+        if imp.to_add.borrow().is_empty() {
+            return;
+        }
 
         *lazy = Some(glib::idle_add_local(
             clone!(@weak self as this => @default-return glib::ControlFlow::Break, move || {
@@ -73,8 +127,46 @@ impl Model {
     }
 }
 
+/// Lengths of the common prefix and suffix of `old` and `new`. The two
+/// don't overlap.
+fn common_affixes<T>(old: &[T], new: &[T], eq: impl Fn(&T, &T) -> bool) -> (usize, usize) {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| eq(a, b))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| eq(a, b))
+        .count();
+
+    (prefix, suffix)
+}
+
 impl Default for Model {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::common_affixes;
+
+    #[test]
+    fn test_common_affixes() {
+        let eq = |a: &&str, b: &&str| a == b;
+
+        assert_eq!(
+            common_affixes(&["a", "b", "c"], &["a", "x", "c"], eq),
+            (1, 1)
+        );
+        assert_eq!(common_affixes(&["a", "b"], &["a", "b", "c"], eq), (2, 0));
+        assert_eq!(common_affixes(&["b", "c"], &["a", "b", "c"], eq), (0, 2));
+        // The suffix doesn't overlap the prefix.
+        assert_eq!(common_affixes(&["a", "a"], &["a", "a", "a"], eq), (2, 0));
+        assert_eq!(common_affixes(&[], &["a"], eq), (0, 0));
+    }
+}
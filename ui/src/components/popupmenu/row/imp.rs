@@ -16,12 +16,22 @@ pub struct Row {
     pub word: TemplateChild<gtk::Label>,
     #[template_child(id = "kind")]
     pub kind: TemplateChild<gtk::Label>,
+    #[template_child(id = "menu")]
+    pub menu: TemplateChild<gtk::Label>,
 
     #[property(set = Self::set_font)]
     pub font: RefCell<Font>,
+    /// The completion base, highlighted in the word.
+    #[property(set = Self::set_base)]
+    pub base: RefCell<String>,
 }
 
 impl Row {
+    fn set_base(&self, base: String) {
+        self.base.replace(base);
+        self.obj().highlight_matches();
+    }
+
     fn set_font(&self, font: Font) {
         let w = (font.char_width() / SCALE).ceil() as i32;
 
@@ -1,4 +1,4 @@
-use gtk::{glib, subclass::prelude::*};
+use gtk::{glib, pango, prelude::*, subclass::prelude::*};
 use nvim::types::PopupmenuItem;
 
 mod imp;
@@ -19,11 +19,77 @@ impl Row {
 
         imp.word.set_label(&item.word);
         imp.kind.set_label(&item.kind);
+        imp.menu.set_label(&item.menu);
+        // Avoid the extra spacing when there's no menu text.
+        imp.menu.set_visible(!item.menu.is_empty());
+
+        self.highlight_matches();
+    }
+
+    /// Emboldens the completion base's characters in the word.
+    fn highlight_matches(&self) {
+        let imp = self.imp();
+        let word = imp.word.label();
+
+        let attrs = pango::AttrList::new();
+        for (start, end) in match_ranges(&word, &imp.base.borrow()) {
+            let mut attr = pango::AttrInt::new_weight(pango::Weight::Bold);
+            attr.set_start_index(start as u32);
+            attr.set_end_index(end as u32);
+            attrs.insert(attr);
+        }
+
+        imp.word.set_attributes(Some(&attrs));
     }
 }
 
+/// Byte ranges of the characters in `word` that match `base` as a case
+/// insensitive subsequence. Empty if `base` doesn't match.
+fn match_ranges(word: &str, base: &str) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    let mut base = base.chars().peekable();
+
+    for (i, c) in word.char_indices() {
+        let b = match base.peek() {
+            Some(b) => b,
+            None => break,
+        };
+
+        if !c.to_lowercase().eq(b.to_lowercase()) {
+            continue;
+        }
+
+        base.next();
+        let end = i + c.len_utf8();
+        match ranges.last_mut() {
+            Some(last) if last.1 == i => last.1 = end,
+            _ => ranges.push((i, end)),
+        }
+    }
+
+    if base.peek().is_some() {
+        return vec![];
+    }
+
+    ranges
+}
+
 impl Default for Row {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::match_ranges;
+
+    #[test]
+    fn test_match_ranges() {
+        assert!(match_ranges("foobar", "").is_empty());
+        assert_eq!(match_ranges("foobar", "foo"), vec![(0, 3)]);
+        assert_eq!(match_ranges("FooBar", "fb"), vec![(0, 1), (3, 4)]);
+        assert_eq!(match_ranges("äöbar", "öb"), vec![(2, 5)]);
+        assert!(match_ranges("foobar", "fz").is_empty());
+    }
+}
@@ -1,18 +1,53 @@
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
 use glib::clone;
+use gtk::glib;
 use gtk::glib::subclass::InitializingObject;
 use gtk::subclass::prelude::*;
-use gtk::{glib, gsk};
-use gtk::{graphene, prelude::*};
+use gtk::{graphene, gsk, prelude::*};
+use nvim::types::Window;
 
-use crate::boxed::ModeInfo;
+use crate::boxed::{ModeInfo, MouseMap};
+use crate::colors::Color;
 use crate::components::grid::Grid;
 use crate::components::{Fixedz, MsgWin, Popupmenu};
 use crate::font::Font;
+use crate::math::Easing;
 use crate::nvim::Neovim;
+use crate::render::EmojiPresentation;
 use crate::SCALE;
 
+/// Minimum width of the popupmenu's info pane, in columns.
+const PMENU_INFO_MIN_COLS: f32 = 20.0;
+pub const DEFAULT_SPLASH_TEXT: &str = "Starting neovim…";
+/// Opacity of the dimmed windows in focus mode.
+const FOCUS_DIM_OPACITY: f64 = 0.5;
+/// Height of the current tabpage's accent border.
+const ACCENT_HEIGHT: f32 = 2.0;
+
+/// Separator between windows, which can be dragged to resize the window.
+pub struct WinSeparator {
+    /// The separator's line, in our fixed's coordinates.
+    pub rect: graphene::Rect,
+    /// The window on the left (or above) of the separator.
+    pub win: Window,
+    /// If the separator is between windows side by side.
+    pub vertical: bool,
+    /// The window's width (or height), in cells.
+    pub size: usize,
+}
+
+/// A window separator being dragged.
+pub struct SeparatorDrag {
+    pub win: Window,
+    pub vertical: bool,
+    /// The window's size when the drag started.
+    pub start: usize,
+    /// The latest size sent to nvim.
+    pub size: usize,
+}
+
 #[derive(Default)]
 pub struct PopupmenuPos {
     row: i64,
@@ -40,6 +75,16 @@ pub struct Shell {
 
     #[template_child(id = "popupmenu")]
     pub popupmenu: TemplateChild<Popupmenu>,
+    /// Info (e.g. documentation) of the selected popupmenu item, shown
+    /// beside the popupmenu.
+    #[template_child(id = "popupmenu-info")]
+    pub popupmenu_info: TemplateChild<gtk::ScrolledWindow>,
+    /// Placeholder shown until nvim has given us the root grid.
+    #[template_child(id = "splash")]
+    pub splash: TemplateChild<gtk::Box>,
+    /// Buttons of the start screen.
+    #[template_child(id = "start-actions")]
+    pub start_actions: TemplateChild<gtk::Box>,
 
     #[property(get, set)]
     pub nvim: RefCell<Neovim>,
@@ -60,42 +105,166 @@ pub struct Shell {
     pub cursor_blink_transition: Cell<f64>,
     #[property(get, set, minimum = 0.0)]
     pub cursor_position_transition: Cell<f64>,
+    #[property(get, set)]
+    pub cursor_position_easing: Cell<Easing>,
     #[property(get, set, minimum = 0.0)]
     pub scroll_transition: Cell<f64>,
+    /// If ligatures are rendered.
+    #[property(get, set)]
+    pub ligatures: Cell<bool>,
+    /// Scale of the undercurl's waves.
+    #[property(get, set, minimum = 0.0)]
+    pub undercurl_amplitude: Cell<f32>,
+    /// How emojis are presented.
+    #[property(get, set)]
+    pub emoji_presentation: Cell<EmojiPresentation>,
+    /// If the cell inspector is enabled.
+    #[property(get, set)]
+    pub cell_inspector: Cell<bool>,
+    /// If power saving is enabled. Disables the animations.
+    #[property(get, set)]
+    pub power_saver: Cell<bool>,
+    /// If the simplest rendering path is used, for buggy graphics drivers.
+    #[property(get, set)]
+    pub safe_rendering: Cell<bool>,
+    /// User's mapping from mouse buttons to nvim input.
+    #[property(get, set)]
+    pub mouse_map: RefCell<MouseMap>,
+    /// If floating windows have a shadow and rounded corners.
+    #[property(get, set)]
+    pub float_decoration: Cell<bool>,
+    /// If closed folds are previewed when hovered.
+    #[property(get, set)]
+    pub fold_preview: Cell<bool>,
+    /// If the windows have a scrollbar.
+    #[property(get, set)]
+    pub scrollbars: Cell<bool>,
+    /// If the splash is shown (i.e. we don't have the root grid yet).
+    #[property(get, set)]
+    pub show_splash: Cell<bool>,
+    /// If the splash is the start screen (i.e. nvim has exited), with
+    /// buttons instead of the spinner.
+    #[property(get, set)]
+    pub start_screen: Cell<bool>,
+    /// Text (pango markup) shown in the splash.
+    #[property(get, set)]
+    pub splash_text: RefCell<String>,
+    /// Maximum text width in focus mode, in columns. The grids are centered
+    /// with the rest left empty. Zero disables the focus mode.
+    #[property(get, set = Self::set_focus_width)]
+    pub focus_width: Cell<u32>,
+    /// If the windows other than the current one are dimmed in focus mode.
+    #[property(get, set = Self::set_focus_dim)]
+    pub focus_dim: Cell<bool>,
+    /// Viewport scrolls (grid id and scroll delta) to be animated on the
+    /// next flush.
+    pub pending_viewports: RefCell<Vec<(i64, f64)>>,
+    /// Separators between the windows, in our fixed's coordinates.
+    pub separators: RefCell<Vec<WinSeparator>>,
+    /// The separator being dragged, if any.
+    pub separator_drag: RefCell<Option<SeparatorDrag>>,
+    /// Drag gesture for resizing the windows by their separators.
+    pub separator_gesture: gtk::GestureDrag,
+    /// Color of the separators, from the latest flush.
+    pub separator_color: Cell<Color>,
+    /// Accent color of the current tabpage, drawn as a border at the top.
+    pub accent: Cell<Option<Color>>,
+    /// Opacity of the visual bell's flash.
+    pub bell_alpha: Cell<f32>,
+    /// Callback id for the visual bell's animation.
+    pub bell_tick: RefCell<Option<gtk::TickCallbackId>>,
+    /// Source id for showing the wait pointer when nvim stays busy.
+    pub busy_pointer_id: RefCell<Option<glib::SourceId>>,
     /// Source id for debouncing nvim resizing.
     pub resize_id: RefCell<Option<glib::SourceId>>,
     /// Our previous size. Used to track when we need to tell neovim to resize
     /// itself.
     pub prev_size: Cell<(i32, i32)>,
+    /// Latest diff bands of the windows in diff mode, by window handle.
+    /// Applied to the window's grid on `win_pos`, too.
+    pub diff_bands: RefCell<HashMap<i64, Vec<(usize, usize)>>>,
+    /// Latest guide line columns of the windows, by window handle. Applied
+    /// to the window's grid on `win_pos` and `win_float_pos`, too.
+    pub guides: RefCell<HashMap<i64, Vec<usize>>>,
+    /// Latest 'winblend' of the floating windows, by window handle. Applied
+    /// to the window's grid on `win_float_pos`, too.
+    pub blends: RefCell<HashMap<i64, u8>>,
 
     #[property(name = "pmenu-col", member = col, get, set, type = i64)]
     #[property(name = "pmenu-row", member = row, get, set, type = i64)]
     #[property(name = "pmenu-visible", member = visible, get, set, type = bool)]
     #[property(name = "pmenu-grid", member = grid, get, set, type = i64)]
     pub pmenu_pos: RefCell<PopupmenuPos>,
+    /// Set when the popupmenu was shown, and its completion base needs to be
+    /// read from the grid on flush (i.e. after the cursor has moved).
+    pub pmenu_base_pending: Cell<bool>,
 }
 
 impl Shell {
+    fn set_focus_width(&self, value: u32) {
+        self.focus_width.set(value);
+        self.obj().queue_resize();
+        self.update_focus_dim();
+    }
+
+    fn set_focus_dim(&self, value: bool) {
+        self.focus_dim.set(value);
+        self.update_focus_dim();
+    }
+
+    /// Dims the windows (but not the floats) other than the current one,
+    /// when enabled in focus mode.
+    pub fn update_focus_dim(&self) {
+        let dim = self.focus_dim.get() && self.focus_width.get() > 0;
+        let current = self.current_grid.borrow();
+        let fixed = self.fixed.upcast_ref::<gtk::Widget>();
+
+        for grid in self.grids.borrow().iter() {
+            let dimmed = dim
+                && grid != &*current
+                && grid.parent().as_ref() == Some(fixed)
+                && self.fixed.zindex(grid) == 0;
+
+            grid.set_opacity(if dimmed { FOCUS_DIM_OPACITY } else { 1.0 });
+        }
+    }
+
+    /// Horizontal offset and width of the grids' area within our `width`.
+    /// Narrower than us in focus mode.
+    pub fn content_bounds(&self, width: i32) -> (i32, i32) {
+        let cols = self.focus_width.get();
+        if cols == 0 {
+            return (0, width);
+        }
+
+        let content = (self.font.borrow().col_to_x(cols as f64).ceil() as i32).min(width);
+        ((width - content) / 2, content)
+    }
+
     /// Adjust (or set) the popupmenu position.
     pub fn adjust_pmenu(&self) {
         let pmenu_pos = self.pmenu_pos.borrow();
 
         if !pmenu_pos.visible {
+            self.popupmenu_info.set_visible(false);
             return;
         }
 
         let font = self.font.borrow();
 
-        let pos = if pmenu_pos.grid == 1 {
-            gsk::Transform::new()
+        let origin = if pmenu_pos.grid == 1 {
+            graphene::Point::zero()
         } else {
-            self.fixed
-                .child_position(&find_grid_or_return!(self.obj(), pmenu_pos.grid))
-        }
-        .transform_point(&graphene::Point::new(
-            font.col_to_x(pmenu_pos.col as f64) as f32,
-            font.row_to_y(pmenu_pos.row as f64 + 1.0) as f32,
-        ));
+            let grid = find_grid_or_return!(self.obj(), pmenu_pos.grid);
+            // Grids that aren't in this window (i.e. external windows)
+            // fall back to the root grid's coordinates.
+            self.grid_origin(&grid)
+                .unwrap_or_else(graphene::Point::zero)
+        };
+        let pos = graphene::Point::new(
+            origin.x() + font.col_to_x(pmenu_pos.col as f64) as f32,
+            origin.y() + font.row_to_y(pmenu_pos.row as f64 + 1.0) as f32,
+        );
 
         let (_, req) = self.root_grid.preferred_size();
         let max_w = req.width() as f32;
@@ -129,9 +298,62 @@ impl Shell {
         self.popupmenu.set_max_width(max_w.floor() as i32);
         self.popupmenu.set_max_height(max_h.floor() as i32);
         self.fixed.move_(&*self.popupmenu, x, y);
+        self.adjust_pmenu_info(x, y, pmenu_w.min(max_w), max_w, max_h);
 
         self.popupmenu.report_pum_bounds(&self.nvim.borrow(), x, y);
     }
+
+    /// Position of `grid`'s origin in our fixed's coordinates. `None` if the
+    /// grid isn't in our window.
+    pub fn grid_origin(&self, grid: &Grid) -> Option<graphene::Point> {
+        let parent = grid.parent()?;
+        let zero = graphene::Point::zero();
+
+        // Use the layout positions when possible, since the allocations
+        // aren't up to date yet on flush.
+        if parent == *self.fixed.upcast_ref::<gtk::Widget>() {
+            Some(self.fixed.child_position(grid).transform_point(&zero))
+        } else if parent == *self.msg_win.upcast_ref::<gtk::Widget>() {
+            // The grid is below the separator line.
+            let sep = graphene::Point::new(0.0, self.msg_win.sep_height() as f32);
+            Some(
+                self.fixed
+                    .child_position(&*self.msg_win)
+                    .transform_point(&sep),
+            )
+        } else {
+            let (x, y) = grid.translate_coordinates(&*self.fixed, 0.0, 0.0)?;
+            Some(graphene::Point::new(x as f32, y as f32))
+        }
+    }
+
+    /// Places the popupmenu's info pane beside the popupmenu, on the side
+    /// with more room. Hidden if there's no info, or no room for it.
+    fn adjust_pmenu_info(&self, x: f32, y: f32, pmenu_w: f32, max_w: f32, max_h: f32) {
+        let min_w = self.font.borrow().char_width() / SCALE * PMENU_INFO_MIN_COLS;
+        let right = max_w - (x + pmenu_w);
+        let left = x;
+        let room = right.max(left);
+
+        if self.popupmenu.info().is_empty() || room < min_w {
+            self.popupmenu_info.set_visible(false);
+            return;
+        }
+
+        self.popupmenu_info
+            .set_max_content_width(room.floor() as i32);
+        self.popupmenu_info
+            .set_max_content_height(max_h.floor() as i32);
+        self.popupmenu_info.set_visible(true);
+
+        let x = if right >= left {
+            x + pmenu_w
+        } else {
+            let (_, req) = self.popupmenu_info.preferred_size();
+            (x - req.width() as f32).max(0.0)
+        };
+        self.fixed.move_(&*self.popupmenu_info, x, y);
+    }
 }
 
 #[glib::object_subclass]
@@ -166,6 +388,31 @@ impl ObjectImpl for Shell {
         self.grids.borrow_mut().push(self.root_grid.clone());
 
         let obj = self.obj();
+        obj.set_show_splash(true);
+        obj.set_splash_text(DEFAULT_SPLASH_TEXT);
+
+        // Capture the presses on the separators, so they don't reach the
+        // grids.
+        self.separator_gesture
+            .set_propagation_phase(gtk::PropagationPhase::Capture);
+        self.separator_gesture
+            .connect_drag_begin(clone!(@weak obj => move |gesture, x, y| {
+                if obj.separator_drag_begin(x, y) {
+                    gesture.set_state(gtk::EventSequenceState::Claimed);
+                } else {
+                    gesture.set_state(gtk::EventSequenceState::Denied);
+                }
+            }));
+        self.separator_gesture
+            .connect_drag_update(clone!(@weak obj => move |_, dx, dy| {
+                obj.separator_drag_update(dx, dy);
+            }));
+        self.separator_gesture
+            .connect_drag_end(clone!(@weak obj => move |_, _, _| {
+                obj.imp().separator_drag.replace(None);
+            }));
+        obj.add_controller(self.separator_gesture.clone());
+
         self.popupmenu
             .store()
             .connect_items_changed(clone!(@weak obj => move |_, _, _, _| {
@@ -175,6 +422,41 @@ impl ObjectImpl for Shell {
 }
 
 impl WidgetImpl for Shell {
+    fn snapshot(&self, snapshot: &gtk::Snapshot) {
+        let obj = self.obj();
+        obj.snapshot_child(&*self.root_grid, snapshot);
+
+        // The separators go below the windows, so they don't cover the
+        // floats.
+        let (x, _) = self.content_bounds(obj.width());
+        let color = self.separator_color.get();
+        snapshot.save();
+        snapshot.translate(&graphene::Point::new(x as f32, 0.0));
+        for sep in self.separators.borrow().iter() {
+            snapshot.append_color(&color, &sep.rect);
+        }
+        snapshot.restore();
+
+        obj.snapshot_child(&*self.fixed, snapshot);
+
+        if let Some(accent) = self.accent.get() {
+            snapshot.append_color(
+                &accent,
+                &graphene::Rect::new(0.0, 0.0, obj.width() as f32, ACCENT_HEIGHT),
+            );
+        }
+
+        obj.snapshot_child(&*self.splash, snapshot);
+
+        let alpha = self.bell_alpha.get();
+        if alpha > 0.0 {
+            snapshot.append_color(
+                &gtk::gdk::RGBA::new(0.5, 0.5, 0.5, alpha),
+                &graphene::Rect::new(0.0, 0.0, obj.width() as f32, obj.height() as f32),
+            );
+        }
+    }
+
     fn measure(&self, orientation: gtk::Orientation, for_size: i32) -> (i32, i32, i32, i32) {
         // Currently, the shell's size is the same as the root grid's size.
         // Note that for the min width we need to report something smaller so
@@ -187,15 +469,21 @@ impl WidgetImpl for Shell {
     fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
         self.parent_size_allocate(width, height, baseline);
 
-        self.root_grid.allocate(width, height, -1, None);
-        self.fixed.allocate(width, height, -1, None);
+        // In focus mode, the grids are centered and the sides are left
+        // empty (i.e. the window's background shows through).
+        let (x, content) = self.content_bounds(width);
+        let offset = gsk::Transform::new().translate(&graphene::Point::new(x as f32, 0.0));
+        self.root_grid
+            .allocate(content, height, -1, Some(offset.clone()));
+        self.fixed.allocate(content, height, -1, Some(offset));
+        self.splash.allocate(width, height, -1, None);
 
         let prev = self.prev_size.get();
         // TODO(ville): Check for rows/col instead.
         // NOTE(ville): If we try to resize nvim unconditionally, we'll
         // end up in a infinite loop.
-        if prev != (width, height) {
-            self.prev_size.set((width, height));
+        if prev != (content, height) {
+            self.prev_size.set((content, height));
             self.obj().resize_nvim();
         }
     }
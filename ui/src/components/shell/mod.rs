@@ -1,17 +1,23 @@
 use std::time::Duration;
 
 use glib::clone;
-use gtk::{glib, graphene, gsk, prelude::*, subclass::prelude::*};
+use gtk::{gdk, glib, graphene, gsk, prelude::*, subclass::prelude::*};
 use nvim::types::uievents::{
     GridClear, GridCursorGoto, GridDestroy, GridLine, GridResize, GridScroll, MsgSetPos,
     PopupmenuSelect, PopupmenuShow, WinClose, WinExternalPos, WinFloatPos, WinHide, WinPos,
     WinViewport,
 };
-use nvim::NeovimApi;
-
-use crate::{boxed::ModeInfo, colors::Colors, font::Font, spawn_local, warn, SCALE};
+use nvim::{types::Window, NeovimApi};
+
+use crate::{
+    api::{DiffBands, Guides, WinBlend},
+    boxed::ModeInfo,
+    colors::{Color, Colors, HlGroup},
+    font::Font,
+    some_or_return, spawn_local, warn, SCALE,
+};
 
-use super::Grid;
+use super::{toast::ToastAction, Grid};
 
 #[macro_export]
 macro_rules! find_grid_or_return {
@@ -28,6 +34,13 @@ macro_rules! find_grid_or_return {
 
 mod imp;
 
+/// Duration of the visual bell's flash, in microseconds.
+const BELL_FLASH_DURATION: f64 = 150_000.0;
+/// Initial opacity of the visual bell's flash.
+const BELL_FLASH_ALPHA: f32 = 0.3;
+/// How far from a window separator (in pixels) it can be grabbed.
+const SEPARATOR_GRAB: f32 = 4.0;
+
 glib::wrapper! {
     pub struct Shell(ObjectSubclass<imp::Shell>)
         @extends gtk::Widget,
@@ -39,7 +52,16 @@ impl Shell {
         glib::Object::new()
     }
 
-    fn find_grid(&self, id: i64) -> Option<Grid> {
+    pub fn find_grid_by_window(&self, handle: i64) -> Option<Grid> {
+        self.imp()
+            .grids
+            .borrow()
+            .iter()
+            .find(|grid| grid.nvim_window_handle() == Some(handle))
+            .cloned()
+    }
+
+    pub fn find_grid(&self, id: i64) -> Option<Grid> {
         self.imp()
             .grids
             .borrow()
@@ -48,12 +70,74 @@ impl Shell {
             .cloned()
     }
 
+    /// Size of the root grid, in columns and rows.
+    pub fn root_grid_size(&self) -> (usize, usize) {
+        self.imp().root_grid.grid_size()
+    }
+
+    /// Tears down the state received from nvim (e.g. when the ui is
+    /// reattached). All but the root grid are destroyed, and the root grid
+    /// is cleared.
+    pub fn reset(&self) {
+        let imp = self.imp();
+
+        imp.grids.borrow_mut().retain(|grid| {
+            if grid.id() == 1 {
+                return true;
+            }
+
+            grid.unparent();
+            false
+        });
+        imp.root_grid.clear();
+        imp.current_grid.replace(imp.root_grid.clone());
+        imp.pending_viewports.borrow_mut().clear();
+        imp.diff_bands.borrow_mut().clear();
+        imp.guides.borrow_mut().clear();
+        imp.blends.borrow_mut().clear();
+
+        imp.msg_win.set_row(None);
+        imp.msg_win.set_height(0);
+        imp.msg_win.set_sep_char(None);
+        imp.msg_win.set_separator(None, 0);
+        imp.msg_win.remove_css_class("scrolled");
+
+        self.handle_popupmenu_hide();
+        self.busy_stop();
+        self.set_show_splash(true);
+    }
+
+    /// Shows the start screen (i.e. the splash with `text` and `actions`,
+    /// instead of the spinner). Clicking an action turns the start screen
+    /// back into the regular splash.
+    pub fn show_start_screen(&self, text: &str, actions: Vec<ToastAction>) {
+        let imp = self.imp();
+
+        while let Some(child) = imp.start_actions.first_child() {
+            imp.start_actions.remove(&child);
+        }
+        for action in actions {
+            let button = gtk::Button::with_label(&action.label);
+            button.connect_clicked(clone!(@weak self as obj => move |_| {
+                obj.set_start_screen(false);
+                obj.set_splash_text(imp::DEFAULT_SPLASH_TEXT);
+                (action.callback)();
+            }));
+            imp.start_actions.append(&button);
+        }
+
+        self.set_splash_text(text);
+        self.set_show_splash(true);
+        self.set_start_screen(true);
+    }
+
     pub fn resize_nvim(&self) {
-        let (cols, rows) = self
-            .imp()
+        let imp = self.imp();
+        let (_, width) = imp.content_bounds(self.width());
+        let (cols, rows) = imp
             .font
             .borrow()
-            .grid_size_for_allocation(&self.allocation());
+            .grid_size_for_allocation(&gtk::Allocation::new(0, 0, width, self.height()));
 
         let id = glib::timeout_add_local(
             Duration::from_millis(crate::WINDOW_RESIZE_DEBOUNCE_MS),
@@ -64,7 +148,9 @@ impl Shell {
                         .await
                         .unwrap();
 
-                    res.await.expect("nvim_ui_try_resize failed");
+                    if let Err(err) = res.await {
+                        warn!("nvim_ui_try_resize failed: {:?}", err);
+                    }
                 }));
 
                 // Clear after our selves, so we don't try to remove
@@ -82,11 +168,70 @@ impl Shell {
     }
 
     pub fn busy_start(&self) {
+        if self.busy() {
+            return;
+        }
+
         self.set_busy(true);
+
+        // Only show the wait pointer if nvim stays busy for a while, so it
+        // doesn't flicker on short operations.
+        let id = glib::timeout_add_local_once(
+            Duration::from_millis(crate::BUSY_POINTER_DELAY_MS),
+            clone!(@weak self as obj => move || {
+                obj.imp().busy_pointer_id.replace(None);
+                obj.set_cursor_from_name(Some("wait"));
+            }),
+        );
+
+        if let Some(id) = self.imp().busy_pointer_id.replace(Some(id)) {
+            id.remove();
+        }
     }
 
     pub fn busy_stop(&self) {
         self.set_busy(false);
+
+        if let Some(id) = self.imp().busy_pointer_id.take() {
+            id.remove();
+        }
+        self.set_cursor_from_name(None);
+    }
+
+    /// Flashes the shell (i.e. the visual bell).
+    pub fn flash(&self) {
+        let start = some_or_return!(
+            self.frame_clock(),
+            "Failed to get a frame clock for the visual bell"
+        )
+        .frame_time() as f64;
+        let end = start + BELL_FLASH_DURATION;
+
+        let imp = self.imp();
+        imp.bell_alpha.set(BELL_FLASH_ALPHA);
+        self.queue_draw();
+
+        let old_id = imp
+            .bell_tick
+            .replace(Some(self.add_tick_callback(move |this, clock| {
+                let now = clock.frame_time() as f64;
+                let t = ((now - start) / (end - start)).clamp(0.0, 1.0) as f32;
+
+                let imp = this.imp();
+                imp.bell_alpha.set(BELL_FLASH_ALPHA * (1.0 - t));
+                this.queue_draw();
+
+                if t < 1.0 {
+                    glib::ControlFlow::Continue
+                } else {
+                    imp.bell_tick.replace(None);
+                    glib::ControlFlow::Break
+                }
+            })));
+
+        if let Some(old_id) = old_id {
+            old_id.remove();
+        }
     }
 
     pub fn handle_grid_line(&self, event: GridLine) {
@@ -94,6 +239,12 @@ impl Shell {
     }
 
     pub fn handle_grid_resize(&self, event: GridResize) {
+        let root = event.grid == 1;
+        if root {
+            // No grids to show (e.g. all of them were destroyed).
+            self.set_show_splash(event.width == 0 || event.height == 0);
+        }
+
         self.find_grid(event.grid)
             .unwrap_or_else(|| {
                 let grid = Grid::new(event.grid, &self.font());
@@ -121,26 +272,262 @@ impl Shell {
                 )
                 .flags(glib::BindingFlags::SYNC_CREATE)
                 .build();
+                self.bind_property("cursor-position-easing", &grid, "cursor-position-easing")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
                 self.bind_property("scroll-transition", &grid, "scroll-transition")
                     .flags(glib::BindingFlags::SYNC_CREATE)
                     .build();
+                self.bind_property("ligatures", &grid, "ligatures")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("undercurl-amplitude", &grid, "undercurl-amplitude")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("emoji-presentation", &grid, "emoji-presentation")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("power-saver", &grid, "power-saver")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("safe-rendering", &grid, "safe-rendering")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("cell-inspector", &grid, "cell-inspector")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("mouse-map", &grid, "mouse-map")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("float-decoration", &grid, "float-decoration")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("fold-preview", &grid, "fold-preview")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
+                self.bind_property("scrollbars", &grid, "scrollbar")
+                    .flags(glib::BindingFlags::SYNC_CREATE)
+                    .build();
 
                 self.imp().grids.borrow_mut().push(grid.clone());
                 grid
             })
             .resize(event);
+
+        // The message grid is anchored to the bottom of the root grid.
+        if root {
+            self.layout_msg_win(&self.font());
+        }
     }
 
     pub fn handle_flush(&self, colors: &Colors) {
+        // NOTE(ville): Start all the scroll animations at the same time, so
+        // windows that scroll together (e.g. with 'scrollbind') move in
+        // lockstep. The animations must be started before the grids are
+        // flushed, since they use the grids' previous content.
+        let viewports = self.imp().pending_viewports.take();
+        if let Some(clock) = self.frame_clock() {
+            let start_time = clock.frame_time() as f64;
+            for (id, delta) in viewports {
+                if let Some(grid) = self.find_grid(id) {
+                    grid.scroll_viewport(delta, start_time);
+                }
+            }
+        }
+
         self.imp()
             .grids
             .borrow()
             .iter()
             .for_each(|grid| grid.flush(colors));
+        self.update_separators(colors);
 
+        if self.imp().pmenu_base_pending.take() {
+            self.update_pmenu_base();
+        }
         self.imp().adjust_pmenu();
     }
 
+    /// Updates the separators between the windows. In multigrid, the windows
+    /// are placed with a gap of one cell between them, where we draw the
+    /// separators.
+    fn update_separators(&self, colors: &Colors) {
+        let imp = self.imp();
+        let font = imp.font.borrow();
+        let cw = font.char_width() / SCALE;
+        let ch = font.height() / SCALE;
+
+        let fixed = imp.fixed.clone();
+        let (windows, wins): (Vec<_>, Vec<_>) = imp
+            .grids
+            .borrow()
+            .iter()
+            .filter(|grid| {
+                !grid.floating()
+                    && grid.parent().map(|parent| parent == fixed).unwrap_or(false)
+                    && fixed.zindex(grid) == 0
+            })
+            .filter_map(|grid| {
+                let pos = fixed
+                    .child_position(grid)
+                    .transform_point(&graphene::Point::zero());
+                let (width, height) = grid.grid_size();
+                let rect = CellRect {
+                    col: (pos.x() / cw).round() as usize,
+                    row: (pos.y() / ch).round() as usize,
+                    width,
+                    height,
+                };
+                Some((grid.nvim_window()?, rect))
+            })
+            .unzip();
+
+        // Horizontal gaps are usually taken by the statuslines, so only
+        // draw separators where nvim has put them (i.e. with a global
+        // statusline).
+        let sep_hl = colors.hl_groups.get(&HlGroup::WinSeparator).copied();
+        let separators = window_separators(&wins)
+            .into_iter()
+            .filter_map(|(i, sep)| match sep {
+                Separator::Vertical { col, start, end } => Some(imp::WinSeparator {
+                    rect: graphene::Rect::new(
+                        (font.col_to_x(col as f64) as f32 + cw / 2.0).floor(),
+                        font.row_to_y(start as f64) as f32,
+                        1.0,
+                        font.row_to_y((end - start) as f64) as f32,
+                    ),
+                    win: windows[i].clone(),
+                    vertical: true,
+                    size: wins[i].width,
+                }),
+                Separator::Horizontal { row, start, end } => {
+                    let hl = imp.root_grid.hl_id_at(row, start);
+                    (sep_hl.is_some() && hl == sep_hl).then(|| imp::WinSeparator {
+                        rect: graphene::Rect::new(
+                            font.col_to_x(start as f64) as f32,
+                            (font.row_to_y(row as f64) as f32 + ch / 2.0).floor(),
+                            font.col_to_x((end - start) as f64) as f32,
+                            1.0,
+                        ),
+                        win: windows[i].clone(),
+                        vertical: false,
+                        size: wins[i].height,
+                    })
+                }
+            })
+            .collect();
+
+        imp.separators.replace(separators);
+        imp.separator_color
+            .set(*colors.get_hl_group(&HlGroup::WinSeparator).fg());
+        self.queue_draw();
+    }
+
+    /// Starts resizing a window, if there's a separator at `x`, `y`. Returns
+    /// false if there isn't.
+    fn separator_drag_begin(&self, x: f64, y: f64) -> bool {
+        let imp = self.imp();
+
+        // Floats (and the like) might be on top of the separator.
+        let picked = self.pick(x, y, gtk::PickFlags::DEFAULT);
+        let covered = picked
+            .map(|widget| match widget.ancestor(Grid::static_type()) {
+                Some(grid) => grid.downcast::<Grid>().map_or(true, |grid| grid.floating()),
+                None => widget.is_ancestor(&*imp.fixed),
+            })
+            .unwrap_or(false);
+        if covered {
+            imp.separator_drag.replace(None);
+            return false;
+        }
+
+        let (offset, _) = imp.content_bounds(self.width());
+        let point = graphene::Point::new(x as f32 - offset as f32, y as f32);
+
+        let drag = imp
+            .separators
+            .borrow()
+            .iter()
+            .find(|sep| {
+                let (dx, dy) = if sep.vertical {
+                    (SEPARATOR_GRAB, 0.0)
+                } else {
+                    (0.0, SEPARATOR_GRAB)
+                };
+                sep.rect.inset_r(-dx, -dy).contains_point(&point)
+            })
+            .map(|sep| imp::SeparatorDrag {
+                win: sep.win.clone(),
+                vertical: sep.vertical,
+                start: sep.size,
+                size: sep.size,
+            });
+
+        let found = drag.is_some();
+        imp.separator_drag.replace(drag);
+        found
+    }
+
+    /// Resizes the window being dragged, for the drag offset `dx`, `dy`.
+    fn separator_drag_update(&self, dx: f64, dy: f64) {
+        let imp = self.imp();
+        let mut drag = imp.separator_drag.borrow_mut();
+        let drag = some_or_return!(drag.as_mut(), "no separator drag");
+
+        let font = imp.font.borrow();
+        let delta = if drag.vertical {
+            dx / (font.char_width() / SCALE) as f64
+        } else {
+            dy / (font.height() / SCALE) as f64
+        };
+        let size = (drag.start as i64 + delta.round() as i64).max(1) as usize;
+        if size == drag.size {
+            return;
+        }
+        drag.size = size;
+
+        let win = drag.win.clone();
+        let vertical = drag.vertical;
+        spawn_local!(clone!(@weak self as obj => async move {
+            let nvim = obj.nvim();
+            let res = if vertical {
+                nvim.nvim_win_set_width(&win, size as i64).await
+            } else {
+                nvim.nvim_win_set_height(&win, size as i64).await
+            };
+            let res = match res {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            if let Err(err) = res.await {
+                warn!("failed to resize window: {:?}", err);
+            }
+        }));
+    }
+
+    /// Sets the popupmenu's completion base (i.e. the text typed so far) from
+    /// the text between the popupmenu's anchor and the cursor.
+    fn update_pmenu_base(&self) {
+        let base = self
+            .find_grid(self.pmenu_grid())
+            .map(|grid| grid.text_before_cursor(self.pmenu_row(), self.pmenu_col()))
+            .unwrap_or_default();
+        self.imp().popupmenu.set_base(base);
+    }
+
+    /// Draws the grids' content of the latest flush.
+    pub fn queue_draw_grids(&self) {
+        self.imp()
+            .grids
+            .borrow()
+            .iter()
+            .for_each(|grid| grid.queue_draw_buffer());
+    }
+
     pub fn handle_grid_clear(&self, event: GridClear) {
         find_grid_or_return!(self, event.grid).clear();
     }
@@ -151,13 +538,22 @@ impl Shell {
 
         // NOTE(ville): In some situations, neovim sends `grid_cursor_goto`
         // message for a grid that already got destroyed.
-        if let Some(grid) = self.find_grid(event.grid) {
+        let changed = if let Some(grid) = self.find_grid(event.grid) {
             grid.cursor_goto(event.col, event.row);
             grid.set_active(true);
 
+            let changed = *current_grid != grid;
             *current_grid = grid;
+            changed
         } else {
             println!("invalid grid for grid_cursor_goto: {}", event.grid);
+            false
+        };
+
+        drop(current_grid);
+        // Only the current grid matters for the dimming.
+        if changed {
+            self.imp().update_focus_dim();
         }
     }
 
@@ -170,7 +566,13 @@ impl Shell {
     }
 
     pub fn handle_grid_destroy(&self, event: GridDestroy) {
-        assert!(event.grid != 1, "cant do grid_destroy for grid 1");
+        // The root grid stays around, but until nvim resizes it again, there
+        // is nothing to show.
+        if event.grid == 1 {
+            self.imp().root_grid.clear();
+            self.set_show_splash(true);
+            return;
+        }
 
         let mut grids = self.imp().grids.borrow_mut();
         if let Some(index) = grids.iter().position(|grid| grid.id() == event.grid) {
@@ -178,7 +580,27 @@ impl Shell {
             // it to be dropped because all the references to the grid will be
             // released.
             let grid = grids.remove(index);
+            let msg_win = self.imp().msg_win.clone();
+            if grid
+                .parent()
+                .map(|parent| parent == msg_win)
+                .unwrap_or(false)
+            {
+                msg_win.set_row(None);
+                msg_win.set_height(0);
+                msg_win.set_sep_char(None);
+                msg_win.set_separator(None, 0);
+            }
             grid.unparent();
+
+            // Don't keep the grid alive through our other references.
+            let imp = self.imp();
+            if *imp.current_grid.borrow() == grid {
+                imp.current_grid.replace(imp.root_grid.clone());
+            }
+            imp.pending_viewports
+                .borrow_mut()
+                .retain(|(id, _)| *id != event.grid);
         } else {
             warn!("grid {} not found in {}:{}", event.grid, file!(), line!());
         }
@@ -193,7 +615,19 @@ impl Shell {
          */
 
         let grid = find_grid_or_return!(self, event.grid);
-        grid.set_nvim_window(Some(event.win));
+        grid.set_nvim_window(Some(event.win.clone()));
+        grid.set_floating(false);
+        // Only floats are blended.
+        grid.set_blend(0);
+        self.update_grid_normal_bg(&grid, event.win.clone());
+        grid.set_guides(self.window_guides(&event.win));
+        grid.set_diff_bands(
+            event
+                .win
+                .handle()
+                .and_then(|win| self.imp().diff_bands.borrow().get(&win).cloned())
+                .unwrap_or_default(),
+        );
 
         let x = font.col_to_x(event.startcol as f64) as f32;
         let y = font.row_to_y(event.startrow as f64) as f32;
@@ -205,57 +639,152 @@ impl Shell {
             grid.unparent();
             fixed.put(&grid, x, y);
         }
+
+        // The grid might've been a float before.
+        fixed.set_zindex(&grid, 0);
+        self.imp().update_focus_dim();
     }
 
-    pub fn handle_float_pos(&self, event: WinFloatPos, font: &Font) {
-        let grid = find_grid_or_return!(self, event.grid);
-        grid.set_nvim_window(Some(event.win));
+    /// Queries the background of the window's `Normal` override (see
+    /// `normal_bg` in the lua runtime), and updates the grid accordingly.
+    fn update_grid_normal_bg(&self, grid: &Grid, win: Window) {
+        let handle = some_or_return!(win.handle(), "invalid window: {:?}", win);
+        spawn_local!(clone!(@weak self as obj, @weak grid => async move {
+            let res = match obj
+                .nvim()
+                .nvim_exec_lua(
+                    "return require('gnvim').normal_bg(...)",
+                    vec![rmpv::Value::from(handle)],
+                )
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("call to nvim failed: {:?}", err);
+                    return;
+                }
+            };
+
+            match res.await {
+                Ok(bg) => grid.set_normal_bg(bg.as_i64().map(Color::from)),
+                Err(err) => warn!("failed to get the normal background: {:?}", err),
+            }
+        }));
+    }
+
+    /// Restarts the cursors' blink cycles (e.g. when the user is typing).
+    pub fn reset_cursor_blink(&self) {
+        for grid in self.imp().grids.borrow().iter() {
+            grid.reset_cursor_blink();
+        }
+    }
+
+    /// Updates the backgrounds of all the windows, e.g. after 'winhighlight'
+    /// or the colorscheme changed.
+    pub fn refresh_normal_bgs(&self) {
+        for grid in self.imp().grids.borrow().iter() {
+            if let Some(win) = grid.nvim_window() {
+                self.update_grid_normal_bg(grid, win);
+            }
+        }
+    }
+
+    /// Sets the transparency of the floating windows whose 'winblend'
+    /// changed.
+    pub fn set_blends(&self, blends: Vec<WinBlend>) {
+        let mut win_blends = self.imp().blends.borrow_mut();
+        for WinBlend { win, blend } in blends {
+            if let Some(grid) = self.find_grid_by_window(win).filter(|grid| grid.floating()) {
+                grid.set_blend(blend);
+            }
+
+            if blend == 0 {
+                win_blends.remove(&win);
+            } else {
+                win_blends.insert(win, blend);
+            }
+        }
+    }
 
-        let east = event.anchor == "NE" || event.anchor == "SE";
-        let south = event.anchor == "SE" || event.anchor == "SW";
+    /// Sets the accent color of the current tabpage, drawn as a thin border
+    /// at our top.
+    pub fn set_accent(&self, accent: Option<Color>) {
+        self.imp().accent.set(accent);
+        self.queue_draw();
+    }
 
-        // Adjust position based on anchor.
-        let (cols, rows) = grid.grid_size();
-        let col = event.anchor_col - if east { cols as f64 } else { 0.0 };
-        let row = event.anchor_row - if south { rows as f64 } else { 0.0 };
+    /// Sets the guide lines of the windows whose 'colorcolumn' (or its
+    /// placement) changed.
+    pub fn set_guides(&self, guides: Vec<Guides>) {
+        let mut guide_cols = self.imp().guides.borrow_mut();
+        for Guides { win, cols } in guides {
+            if let Some(grid) = self.find_grid_by_window(win) {
+                grid.set_guides(cols.clone());
+            }
+
+            if cols.is_empty() {
+                guide_cols.remove(&win);
+            } else {
+                guide_cols.insert(win, cols);
+            }
+        }
+    }
+
+    /// Guide lines of `win`, as last set by `set_guides`.
+    fn window_guides(&self, win: &Window) -> Vec<usize> {
+        win.handle()
+            .and_then(|win| self.imp().guides.borrow().get(&win).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn handle_float_pos(&self, event: WinFloatPos, font: &Font) {
+        let grid = find_grid_or_return!(self, event.grid);
+        grid.set_nvim_window(Some(event.win.clone()));
+        grid.set_floating(true);
+        self.update_grid_normal_bg(&grid, event.win.clone());
+        grid.set_blend(
+            event
+                .win
+                .handle()
+                .and_then(|win| self.imp().blends.borrow().get(&win).copied())
+                .unwrap_or(0),
+        );
+        grid.set_guides(self.window_guides(&event.win));
 
         let fixed = self.imp().fixed.clone();
 
-        let pos = if event.anchor_grid == 1 {
-            gsk::Transform::new()
+        // Floats anchored to a grid in an external window fall back to the
+        // root grid's coordinates.
+        let origin = if event.anchor_grid == 1 {
+            graphene::Point::zero()
         } else {
-            fixed.child_position(&find_grid_or_return!(self, event.anchor_grid))
-        }
-        .transform_point(&graphene::Point::new(
-            font.col_to_x(col) as f32,
-            font.row_to_y(row) as f32,
-        ));
+            let anchor_grid = find_grid_or_return!(self, event.anchor_grid);
+            self.imp()
+                .grid_origin(&anchor_grid)
+                .unwrap_or_else(graphene::Point::zero)
+        };
+        let point = (
+            origin.x() + font.col_to_x(event.anchor_col) as f32,
+            origin.y() + font.row_to_y(event.anchor_row) as f32,
+        );
 
         let (_, root_req) = self.imp().root_grid.preferred_size();
-        let (max_w, max_h) = (root_req.width(), root_req.height());
         // Don't overlap with the msg window since it has higher z index.
-        let max_h = max_h - self.imp().msg_win.height();
+        let bounds = (
+            root_req.width() as f32,
+            (root_req.height() - self.imp().msg_win.height()) as f32,
+        );
         let (req, _) = grid.preferred_size();
-        let (grid_w, grid_h) = (req.width(), req.height());
-
-        let max_x = (max_w - grid_w)
-            // If the grid is very wide, it might underflow.
-            .max(0) as f32;
+        let size = (req.width() as f32, req.height() as f32);
 
         // NOTE(ville): Not 100% the substraction of one cell height is required.
-        let max_y = ((max_h - grid_h) as f32 - font.height() / SCALE)
-            // If the grid is very tall, it might underflow.
-            .max(0.0);
-
-        let x = pos.x().clamp(0.0, max_x);
-        let y = pos.y().clamp(0.0, max_y);
+        let ((x, y), (adj_w, adj_h)) =
+            place_float(&event.anchor, point, size, bounds, font.height() / SCALE);
 
         // If the grid doesn't fit the screen, clamp it.
-        let adj_w = max_w as f32 - (x + grid_w as f32);
-        let adj_h = max_h as f32 - (y + grid_h as f32);
         if adj_w < 0.0 || adj_h < 0.0 {
-            let cols = font.scale_to_col(grid_w as f64 + adj_w.min(0.0) as f64);
-            let rows = font.scale_to_row(grid_h as f64 + adj_h.min(0.0) as f64);
+            let cols = font.scale_to_col((size.0 + adj_w) as f64);
+            let rows = font.scale_to_row((size.1 + adj_h) as f64);
 
             let grid_id = grid.grid_id();
             spawn_local!(clone!(@weak self as obj => async move {
@@ -268,7 +797,9 @@ impl Shell {
                     .await
                     .unwrap();
 
-                res.await.expect("nvim_ui_try_resize failed");
+                if let Err(err) = res.await {
+                    warn!("nvim_ui_try_resize failed: {:?}", err);
+                }
             }));
         }
 
@@ -280,12 +811,14 @@ impl Shell {
         }
 
         fixed.set_zindex(&grid, event.zindex);
+        self.imp().update_focus_dim();
     }
 
     pub fn handle_win_hide(&self, event: WinHide) {
         assert!(event.grid != 1, "cant do win_hide for grid 1");
 
         let grid = find_grid_or_return!(self, event.grid);
+        self.cancel_separator_drag(&grid);
         grid.unparent();
     }
 
@@ -293,10 +826,38 @@ impl Shell {
         assert!(event.grid != 1, "cant do win_close for grid 1");
 
         let grid = find_grid_or_return!(self, event.grid);
+        self.cancel_separator_drag(&grid);
+        if let Some(handle) = grid.nvim_window_handle() {
+            self.forget_window(handle);
+        }
         grid.set_nvim_window(None);
         grid.unparent();
     }
 
+    /// Drops the state we keep of the closed window `handle`.
+    fn forget_window(&self, handle: i64) {
+        let imp = self.imp();
+        imp.diff_bands.borrow_mut().remove(&handle);
+        imp.guides.borrow_mut().remove(&handle);
+        imp.blends.borrow_mut().remove(&handle);
+    }
+
+    /// Cancels resizing `grid`'s window, which is going away.
+    fn cancel_separator_drag(&self, grid: &Grid) {
+        let imp = self.imp();
+        let dragged = imp
+            .separator_drag
+            .borrow()
+            .as_ref()
+            .and_then(|drag| drag.win.handle())
+            .zip(grid.nvim_window_handle())
+            .map_or(false, |(dragged, handle)| dragged == handle);
+        if dragged {
+            imp.separator_gesture.reset();
+            imp.separator_drag.replace(None);
+        }
+    }
+
     pub fn handle_win_external_pos(&self, event: WinExternalPos, parent: &gtk::Window) {
         assert!(event.grid != 1, "cant do win_external_pos for grid 1");
 
@@ -310,22 +871,28 @@ impl Shell {
 
         let grid = find_grid_or_return!(self, event.grid);
         grid.set_nvim_window(Some(event.win));
+        grid.set_viewport(event.topline, event.botline, event.line_count);
 
-        grid.set_viewport_delta(event.scroll_delta as f64);
+        if event.scroll_delta != 0 {
+            self.imp()
+                .pending_viewports
+                .borrow_mut()
+                .push((event.grid, event.scroll_delta as f64));
+        }
     }
 
+    /// Places the message grid. Nvim only sends `msg_set_pos` when the
+    /// `ext_messages` ui option is disabled (see `:h gnvim-messages`); otherwise
+    /// the messages are shown in the message area.
     pub fn handle_msg_set_pos(&self, event: MsgSetPos, font: &Font) {
         assert!(event.grid != 1, "cant do msg_set_pos for grid 1");
 
         let grid = find_grid_or_return!(self, event.grid);
-        let imp = self.imp();
-        let win = imp.msg_win.clone();
+        let win = self.imp().msg_win.clone();
 
-        let h = imp.root_grid.grid_size().1 - event.row as usize;
-        win.set_height(font.row_to_y(h as f64).ceil() as i32);
-
-        let y = font.row_to_y(event.row as f64);
-        imp.fixed.move_(&win, 0.0, y as f32);
+        win.set_row(Some(event.row));
+        win.set_sep_char(event.scrolled.then_some(event.sep_char));
+        self.layout_msg_win(font);
 
         if grid.parent().map(|parent| parent != win).unwrap_or(true) {
             grid.unparent();
@@ -339,11 +906,55 @@ impl Shell {
         }
     }
 
+    /// Positions the message window from its row, covering the rest of the
+    /// root grid. When the messages have scrolled, the row above them is
+    /// filled with `sep_char`, like nvim's own compositor does.
+    fn layout_msg_win(&self, font: &Font) {
+        let imp = self.imp();
+        let win = imp.msg_win.clone();
+        let row = match win.row() {
+            Some(row) => row,
+            None => return,
+        };
+
+        let (cols, root_rows) = imp.root_grid.grid_size();
+        let sep = win.sep_char().filter(|_| row > 0);
+        let sep_rows = sep.is_some() as i64;
+
+        let rows = (root_rows as i64 - row).max(0) + sep_rows;
+        win.set_height(font.row_to_y(rows as f64).ceil() as i32);
+        win.set_separator(
+            sep.map(|sep| sep.repeat(cols)).as_deref(),
+            font.row_to_y(1.0).ceil() as i32,
+        );
+
+        let y = font.row_to_y((row - sep_rows) as f64);
+        imp.fixed.move_(&win, 0.0, y as f32);
+    }
+
+    /// Updates the diff bands of the windows (see `diff_bands` in the lua
+    /// runtime).
+    pub fn set_diff_bands(&self, bands: Vec<DiffBands>) {
+        let mut diff_bands = self.imp().diff_bands.borrow_mut();
+        for DiffBands { win, bands } in bands {
+            if let Some(grid) = self.find_grid_by_window(win) {
+                grid.set_diff_bands(bands.clone());
+            }
+
+            if bands.is_empty() {
+                diff_bands.remove(&win);
+            } else {
+                diff_bands.insert(win, bands);
+            }
+        }
+    }
+
     pub fn handle_popupmenu_show(&self, event: PopupmenuShow) {
         let imp = self.imp();
 
         imp.popupmenu.set_items(event.items);
         imp.popupmenu.select(event.selected);
+        imp.pmenu_base_pending.set(true);
 
         self.set_pmenu_row(event.row);
         self.set_pmenu_col(event.col);
@@ -358,6 +969,32 @@ impl Shell {
     pub fn handle_popupmenu_hide(&self) {
         self.set_pmenu_visible(false)
     }
+
+    /// Renders the shell (cropped to the root grid) offscreen into a texture.
+    /// Uses the cairo renderer, so the output doesn't depend on the GPU.
+    pub fn render_texture(&self) -> Result<gdk::Texture, glib::Error> {
+        let paintable = gtk::WidgetPaintable::new(Some(self));
+        let snapshot = gtk::Snapshot::new();
+        paintable.snapshot(
+            &snapshot,
+            paintable.intrinsic_width() as f64,
+            paintable.intrinsic_height() as f64,
+        );
+
+        let (_, root_req) = self.imp().root_grid.preferred_size();
+        let bounds =
+            graphene::Rect::new(0.0, 0.0, root_req.width() as f32, root_req.height() as f32);
+        let node = snapshot
+            .to_node()
+            .unwrap_or_else(|| gsk::ContainerNode::new(&[]).upcast());
+
+        let renderer = gsk::CairoRenderer::new();
+        renderer.realize(None)?;
+        let texture = renderer.render_texture(&node, Some(&bounds));
+        renderer.unrealize();
+
+        Ok(texture)
+    }
 }
 
 impl Default for Shell {
@@ -365,3 +1002,177 @@ impl Default for Shell {
         Self::new()
     }
 }
+
+/// A window's position and size, in cells.
+#[derive(Debug, Clone, Copy)]
+struct CellRect {
+    col: usize,
+    row: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Separator between windows, in cells. The range (`start..end`) is
+/// exclusive.
+#[derive(Debug, PartialEq, Eq)]
+enum Separator {
+    Vertical {
+        col: usize,
+        start: usize,
+        end: usize,
+    },
+    Horizontal {
+        row: usize,
+        start: usize,
+        end: usize,
+    },
+}
+
+/// Finds the separators between `wins`, i.e. the one cell gaps between
+/// neighbouring windows. The separators span the window on the left (or
+/// above), and are returned with that window's index.
+fn window_separators(wins: &[CellRect]) -> Vec<(usize, Separator)> {
+    let overlaps = |a: (usize, usize), b: (usize, usize)| a.0 < b.0 + b.1 && b.0 < a.0 + a.1;
+
+    let mut seps = vec![];
+    for (i, a) in wins.iter().enumerate() {
+        let right = wins.iter().any(|b| {
+            b.col == a.col + a.width + 1 && overlaps((a.row, a.height), (b.row, b.height))
+        });
+        if right {
+            seps.push((
+                i,
+                Separator::Vertical {
+                    col: a.col + a.width,
+                    start: a.row,
+                    end: a.row + a.height,
+                },
+            ));
+        }
+
+        let below = wins
+            .iter()
+            .any(|b| b.row == a.row + a.height + 1 && overlaps((a.col, a.width), (b.col, b.width)));
+        if below {
+            seps.push((
+                i,
+                Separator::Horizontal {
+                    row: a.row + a.height,
+                    start: a.col,
+                    end: a.col + a.width,
+                },
+            ));
+        }
+    }
+
+    seps
+}
+
+/// Places a float of `size` with its `anchor` corner (`NW`, `NE`, `SW` or
+/// `SE`) at `point`, within `bounds` (minus `bottom_margin` at the bottom).
+/// Returns the float's top left corner, and how much the float needs to
+/// shrink (as non-positive values) to fit the bounds.
+fn place_float(
+    anchor: &str,
+    point: (f32, f32),
+    size: (f32, f32),
+    bounds: (f32, f32),
+    bottom_margin: f32,
+) -> ((f32, f32), (f32, f32)) {
+    let (w, h) = size;
+    let x = if anchor.ends_with('E') {
+        point.0 - w
+    } else {
+        point.0
+    };
+    let y = if anchor.starts_with('S') {
+        point.1 - h
+    } else {
+        point.1
+    };
+
+    // If the float is bigger than the bounds, the maximums might underflow.
+    let x = x.clamp(0.0, (bounds.0 - w).max(0.0));
+    let y = y.clamp(0.0, (bounds.1 - h - bottom_margin).max(0.0));
+
+    let adj = ((bounds.0 - (x + w)).min(0.0), (bounds.1 - (y + h)).min(0.0));
+
+    ((x, y), adj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{place_float, window_separators, CellRect, Separator};
+
+    #[test]
+    fn test_window_separators() {
+        let win = |col, row, width, height| CellRect {
+            col,
+            row,
+            width,
+            height,
+        };
+
+        // Left window, and two windows stacked on the right.
+        let wins = [win(0, 0, 10, 9), win(11, 0, 10, 4), win(11, 5, 10, 4)];
+        assert_eq!(
+            window_separators(&wins),
+            vec![
+                (
+                    0,
+                    Separator::Vertical {
+                        col: 10,
+                        start: 0,
+                        end: 9
+                    }
+                ),
+                (
+                    1,
+                    Separator::Horizontal {
+                        row: 4,
+                        start: 11,
+                        end: 21
+                    }
+                ),
+            ]
+        );
+
+        // No gap, no separator.
+        assert_eq!(
+            window_separators(&[win(0, 0, 10, 9), win(10, 0, 10, 9)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_place_float_anchors() {
+        let bounds = (100.0, 100.0);
+        let size = (20.0, 10.0);
+        let point = (50.0, 50.0);
+
+        assert_eq!(place_float("NW", point, size, bounds, 0.0).0, (50.0, 50.0));
+        assert_eq!(place_float("NE", point, size, bounds, 0.0).0, (30.0, 50.0));
+        assert_eq!(place_float("SW", point, size, bounds, 0.0).0, (50.0, 40.0));
+        assert_eq!(place_float("SE", point, size, bounds, 0.0).0, (30.0, 40.0));
+    }
+
+    #[test]
+    fn test_place_float_clamps() {
+        let bounds = (100.0, 100.0);
+
+        // Pushed back inside the bounds.
+        assert_eq!(
+            place_float("NW", (95.0, 95.0), (20.0, 10.0), bounds, 5.0),
+            ((80.0, 85.0), (0.0, 0.0))
+        );
+        assert_eq!(
+            place_float("SE", (5.0, 5.0), (20.0, 10.0), bounds, 0.0),
+            ((0.0, 0.0), (0.0, 0.0))
+        );
+        // Too big, needs to shrink.
+        assert_eq!(
+            place_float("NW", (10.0, 10.0), (120.0, 110.0), bounds, 0.0),
+            ((0.0, 0.0), (-20.0, -10.0))
+        );
+    }
+}
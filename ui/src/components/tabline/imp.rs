@@ -1,9 +1,11 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 
 use glib::subclass::InitializingObject;
 use gtk::{glib, prelude::*, subclass::prelude::*};
 
 use crate::boxed::ShowTabline;
+use crate::colors::Color;
 use crate::nvim::Neovim;
 
 #[derive(Default, gtk::CompositeTemplate)]
@@ -14,6 +16,10 @@ pub struct Tabline {
 
     pub nvim: RefCell<Neovim>,
     pub show: RefCell<ShowTabline>,
+    /// Accent colors of the tabpages, by tabpage handle.
+    pub accents: RefCell<HashMap<i64, Color>>,
+    /// Handle of the current tabpage.
+    pub current: Cell<Option<i64>>,
 }
 
 #[glib::object_subclass]
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use gtk::{glib, prelude::*, subclass::prelude::*};
 use nvim::types::{uievents::TablineUpdate, ShowTabline};
 
-use crate::{boxed::Tabpage, child_iter::IterChildren};
+use crate::{boxed::Tabpage, child_iter::IterChildren, colors::Color};
 
 mod imp;
 mod tab;
@@ -20,7 +22,10 @@ impl Tabline {
             .iter_children()
             .for_each(|child| child.unparent());
 
+        imp.current.set(event.current.handle());
+
         let nvim = imp.nvim.borrow();
+        let accents = imp.accents.borrow();
         for tab in event.tabs.into_iter() {
             let current = tab.tab == event.current;
             let child = tab::Tab::new(&nvim, &tab.name, Tabpage(tab.tab));
@@ -29,10 +34,48 @@ impl Tabline {
                 child.add_css_class("selected");
             }
 
+            child.set_accent(
+                child
+                    .handle()
+                    .and_then(|handle| accents.get(&handle).copied()),
+            );
             imp.content.append(&child);
         }
     }
 
+    /// Sets the accent colors of the tabpages, by tabpage handle.
+    pub fn set_accents(&self, accents: HashMap<i64, Color>) {
+        let imp = self.imp();
+        imp.content
+            .iter_children()
+            .filter_map(|child| child.downcast::<tab::Tab>().ok())
+            .for_each(|tab| {
+                tab.set_accent(
+                    tab.handle()
+                        .and_then(|handle| accents.get(&handle).copied()),
+                )
+            });
+        imp.accents.replace(accents);
+    }
+
+    /// Accent color of the current tabpage, if any.
+    pub fn current_accent(&self) -> Option<Color> {
+        let imp = self.imp();
+        imp.current
+            .get()
+            .and_then(|handle| imp.accents.borrow().get(&handle).copied())
+    }
+
+    /// Removes the tabs, e.g. when the ui state is rebuilt.
+    pub fn clear(&self) {
+        let imp = self.imp();
+        imp.content
+            .iter_children()
+            .for_each(|child| child.unparent());
+        imp.current.set(None);
+        self.flush();
+    }
+
     pub fn flush(&self) {
         let imp = self.imp();
         let visible = match **imp.show.borrow() {
@@ -1,10 +1,15 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 use glib::{clone, subclass::InitializingObject};
-use gtk::{glib, prelude::*, subclass::prelude::*};
+use gtk::{gdk, glib, graphene, prelude::*, subclass::prelude::*};
 use nvim::NeovimApi;
 
-use crate::{boxed::Tabpage, nvim::Neovim, spawn_local};
+use crate::{boxed::Tabpage, colors::Color, nvim::Neovim, spawn_local, warn};
+
+/// Opacity of the accent color's tint over the tab.
+const ACCENT_TINT_ALPHA: f32 = 0.25;
+/// Height of the accent color's bar at the bottom of the tab.
+const ACCENT_BAR_HEIGHT: f32 = 2.0;
 
 #[derive(Default, gtk::CompositeTemplate)]
 #[template(resource = "/com/github/vhakulinen/gnvim/tab.ui")]
@@ -16,6 +21,8 @@ pub struct Tab {
     // NOTE(ville): Tabpage doesn't have Default impl, hence the wrapped option.
     pub tabpage: RefCell<Option<Tabpage>>,
     pub gesture_click: gtk::GestureClick,
+    /// The tabpage's accent color.
+    pub accent: Cell<Option<Color>>,
 }
 
 #[glib::object_subclass]
@@ -56,7 +63,9 @@ impl ObjectImpl for Tab {
                         .nvim_set_current_tabpage(&page)
                         .await.expect("call to nvim failed");
 
-                    res.await.expect("nvim_set_current_tabpage failed");
+                    if let Err(err) = res.await {
+                        warn!("nvim_set_current_tabpage failed: {:?}", err);
+                    }
                 });
             }));
     }
@@ -110,4 +119,29 @@ impl ObjectImpl for Tab {
     }
 }
 
-impl WidgetImpl for Tab {}
+impl WidgetImpl for Tab {
+    fn snapshot(&self, snapshot: &gtk::Snapshot) {
+        self.parent_snapshot(snapshot);
+
+        let accent = match self.accent.get() {
+            Some(accent) => accent,
+            None => return,
+        };
+
+        let obj = self.obj();
+        let (w, h) = (obj.width() as f32, obj.height() as f32);
+        snapshot.append_color(
+            &gdk::RGBA::new(
+                accent.red(),
+                accent.green(),
+                accent.blue(),
+                ACCENT_TINT_ALPHA,
+            ),
+            &graphene::Rect::new(0.0, 0.0, w, h),
+        );
+        snapshot.append_color(
+            &accent,
+            &graphene::Rect::new(0.0, h - ACCENT_BAR_HEIGHT, w, ACCENT_BAR_HEIGHT),
+        );
+    }
+}
@@ -1,6 +1,6 @@
-use gtk::{glib, subclass::prelude::*};
+use gtk::{glib, prelude::*, subclass::prelude::*};
 
-use crate::{boxed::Tabpage, nvim::Neovim};
+use crate::{boxed::Tabpage, colors::Color, nvim::Neovim};
 
 mod imp;
 
@@ -19,6 +19,21 @@ impl Tab {
             .build()
     }
 
+    /// Handle of the tab's tabpage.
+    pub fn handle(&self) -> Option<i64> {
+        self.imp()
+            .tabpage
+            .borrow()
+            .as_ref()
+            .and_then(|page| page.handle())
+    }
+
+    /// Sets the tabpage's accent color, tinting the tab.
+    pub fn set_accent(&self, accent: Option<Color>) {
+        self.imp().accent.set(accent);
+        self.queue_draw();
+    }
+
     fn nvim(&self) -> Neovim {
         self.imp().nvim.borrow().clone()
     }
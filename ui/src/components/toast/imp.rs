@@ -0,0 +1,90 @@
+use std::cell::RefCell;
+
+use gtk::glib::subclass::InitializingObject;
+use gtk::{glib, prelude::*, subclass::prelude::*};
+
+use super::Severity;
+
+#[derive(gtk::CompositeTemplate, Default)]
+#[template(resource = "/com/github/vhakulinen/gnvim/toast.ui")]
+pub struct Toast {
+    #[template_child(id = "revealer")]
+    pub revealer: TemplateChild<gtk::Revealer>,
+    #[template_child(id = "content")]
+    pub content: TemplateChild<gtk::Box>,
+    #[template_child(id = "label")]
+    pub label: TemplateChild<gtk::Label>,
+    #[template_child(id = "action1")]
+    pub action1: TemplateChild<gtk::Button>,
+    #[template_child(id = "action2")]
+    pub action2: TemplateChild<gtk::Button>,
+
+    /// Callbacks of the action buttons.
+    pub callbacks: RefCell<Vec<Box<dyn Fn()>>>,
+    /// Timeout for hiding the toast.
+    pub timeout: RefCell<Option<glib::SourceId>>,
+    pub severity: RefCell<Option<Severity>>,
+}
+
+impl Toast {
+    fn activate(&self, index: usize) {
+        // Take the callbacks, so they can show a new toast.
+        let callbacks = self.callbacks.take();
+        self.obj().dismiss();
+
+        if let Some(callback) = callbacks.get(index) {
+            callback();
+        }
+    }
+}
+
+#[gtk::template_callbacks]
+impl Toast {
+    #[template_callback]
+    fn action1_clicked(&self) {
+        self.activate(0);
+    }
+
+    #[template_callback]
+    fn action2_clicked(&self) {
+        self.activate(1);
+    }
+
+    #[template_callback]
+    fn dismiss(&self) {
+        self.obj().dismiss();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Toast {
+    const NAME: &'static str = "Toast";
+    type Type = super::Toast;
+    type ParentType = gtk::Widget;
+
+    fn class_init(klass: &mut Self::Class) {
+        klass.set_layout_manager_type::<gtk::BinLayout>();
+        klass.set_css_name("toast");
+
+        klass.bind_template();
+        klass.bind_template_callbacks();
+    }
+
+    fn instance_init(obj: &InitializingObject<Self>) {
+        obj.init_template();
+    }
+}
+
+impl ObjectImpl for Toast {
+    fn dispose(&self) {
+        if let Some(id) = self.timeout.take() {
+            id.remove();
+        }
+
+        while let Some(child) = self.obj().first_child() {
+            child.unparent();
+        }
+    }
+}
+
+impl WidgetImpl for Toast {}
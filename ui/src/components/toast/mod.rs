@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use gtk::{glib, prelude::*, subclass::prelude::*};
+use nvim::serde;
+
+mod imp;
+
+/// Severity of a toast. Sets the toast's style class.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn css_class(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Button on a toast.
+pub struct ToastAction {
+    pub label: String,
+    /// Called when the button is clicked, after the toast is dismissed.
+    pub callback: Box<dyn Fn()>,
+}
+
+impl ToastAction {
+    pub fn new<F: Fn() + 'static>(label: &str, callback: F) -> Self {
+        Self {
+            label: label.to_string(),
+            callback: Box::new(callback),
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Banner with a message and up to two action buttons.
+    pub struct Toast(ObjectSubclass<imp::Toast>)
+        @extends gtk::Widget,
+        @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
+}
+
+impl Toast {
+    /// Shows `text`, replacing the previous toast.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The message.
+    /// * `severity` - Style of the toast.
+    /// * `timeout` - Time after which the toast is dismissed. `None` keeps
+    ///               the toast until it's dismissed or an action is taken.
+    /// * `actions` - Action buttons. Only the first two are shown.
+    pub fn show(
+        &self,
+        text: &str,
+        severity: Severity,
+        timeout: Option<Duration>,
+        actions: Vec<ToastAction>,
+    ) {
+        let imp = self.imp();
+
+        if let Some(id) = imp.timeout.take() {
+            id.remove();
+        }
+
+        if let Some(prev) = imp.severity.replace(Some(severity)) {
+            imp.content.remove_css_class(prev.css_class());
+        }
+        imp.content.add_css_class(severity.css_class());
+
+        imp.label.set_text(text);
+
+        let mut actions = actions.into_iter();
+        let mut callbacks = vec![];
+        for button in [&imp.action1, &imp.action2] {
+            match actions.next() {
+                Some(action) => {
+                    button.set_label(&action.label);
+                    button.set_visible(true);
+                    callbacks.push(action.callback);
+                }
+                None => button.set_visible(false),
+            }
+        }
+        imp.callbacks.replace(callbacks);
+
+        if let Some(timeout) = timeout {
+            let id = glib::timeout_add_local_once(
+                timeout,
+                glib::clone!(@weak self as this => move || {
+                    this.imp().timeout.take();
+                    this.dismiss();
+                }),
+            );
+            imp.timeout.replace(Some(id));
+        }
+
+        imp.revealer.set_reveal_child(true);
+    }
+
+    pub fn dismiss(&self) {
+        let imp = self.imp();
+
+        if let Some(id) = imp.timeout.take() {
+            id.remove();
+        }
+
+        imp.callbacks.replace(vec![]);
+        imp.revealer.set_reveal_child(false);
+    }
+
+    /// Calls `f` once the toast is hidden (i.e. its transition is done).
+    pub fn connect_dismissed<F: Fn(&Self) + 'static>(&self, f: F) {
+        self.imp().revealer.connect_child_revealed_notify(
+            glib::clone!(@weak self as this => move |revealer| {
+                if !revealer.is_child_revealed() {
+                    f(&this);
+                }
+            }),
+        );
+    }
+}
+
+impl Default for Toast {
+    fn default() -> Self {
+        glib::Object::new()
+    }
+}
@@ -0,0 +1,254 @@
+//! Ring buffer of the latest rpc messages from nvim, for attaching the exact
+//! protocol traffic to bug reports.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use gtk::glib;
+use nvim::rpc::message::{Message, Notification, Request};
+
+/// Number of messages kept in the log.
+const EVENT_LOG_LEN: usize = 500;
+
+thread_local! {
+    /// Logs of all the windows, for dumping them on panic.
+    static LOGS: RefCell<Vec<Weak<RefCell<Inner>>>> = RefCell::default();
+}
+
+#[derive(Default)]
+struct Inner {
+    /// If messages are recorded. Off by default, the messages (e.g. redraw
+    /// batches) can be big.
+    enabled: bool,
+    /// Messages with their monotonic time (in microseconds), oldest first.
+    messages: VecDeque<(i64, Message)>,
+}
+
+/// The latest rpc messages received from nvim.
+pub struct EventLog(Rc<RefCell<Inner>>);
+
+impl Default for EventLog {
+    fn default() -> Self {
+        let inner = Rc::<RefCell<Inner>>::default();
+        LOGS.with(|logs| {
+            let mut logs = logs.borrow_mut();
+            logs.retain(|log| log.strong_count() > 0);
+            logs.push(Rc::downgrade(&inner));
+        });
+
+        Self(inner)
+    }
+}
+
+impl EventLog {
+    /// Turns the recording on or off. Turning it off drops the recorded
+    /// messages.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut inner = self.0.borrow_mut();
+        inner.enabled = enabled;
+        if !enabled {
+            inner.messages.clear();
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.borrow().enabled
+    }
+
+    pub fn record(&self, msg: &Message) {
+        let mut inner = self.0.borrow_mut();
+        if !inner.enabled {
+            return;
+        }
+
+        if inner.messages.len() == EVENT_LOG_LEN {
+            inner.messages.pop_front();
+        }
+
+        inner
+            .messages
+            .push_back((glib::monotonic_time(), msg.clone()));
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().messages.clear();
+    }
+
+    /// Writes the log to `path`.
+    pub fn dump(&self, path: &Path) -> io::Result<()> {
+        let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+        self.0.borrow().write(&mut file)?;
+        file.flush()
+    }
+}
+
+impl Inner {
+    /// Writes the messages, oldest first. Redraw notifications are followed
+    /// by their decoded ui events.
+    fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let latest = self.messages.back().map(|(time, _)| *time).unwrap_or(0);
+
+        writeln!(
+            w,
+            "# gnvim {}, {} latest messages from nvim (times relative to the latest)",
+            crate::VERSION,
+            self.messages.len()
+        )?;
+
+        for (time, msg) in self.messages.iter() {
+            let time = (time - latest) as f64 / 1_000_000.0;
+            match msg {
+                Message::Request(Request {
+                    msgid,
+                    method,
+                    params,
+                    ..
+                }) => writeln!(w, "[{:.6}] request {} {}: {}", time, msgid, method, params)?,
+                Message::Response(res) => writeln!(
+                    w,
+                    "[{:.6}] response {}: error={:?} result={:?}",
+                    time, res.msgid, res.error, res.result
+                )?,
+                Message::Notification(Notification { method, params, .. }) => {
+                    writeln!(w, "[{:.6}] notification {}: {}", time, method, params)?;
+
+                    if method == "redraw" {
+                        match nvim::decode_redraw_params(params.clone()) {
+                            Ok(events) => {
+                                for event in events {
+                                    writeln!(w, "    {:?}", event)?;
+                                }
+                            }
+                            Err(err) => writeln!(w, "    failed to decode: {:?}", err)?,
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Dumps the event logs of all the windows when gnvim panics, in addition to
+/// the default panic hook.
+pub fn install_panic_hook() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default(info);
+        dump_all();
+    }));
+}
+
+fn dump_all() {
+    let _ = LOGS.try_with(|logs| {
+        let logs = match logs.try_borrow() {
+            Ok(logs) => logs,
+            Err(_) => return,
+        };
+
+        for (i, log) in logs.iter().filter_map(Weak::upgrade).enumerate() {
+            let path = crash_dump_path(i);
+            let res = log.try_borrow().map_err(io::Error::other).and_then(|log| {
+                path.parent()
+                    .map(std::fs::create_dir_all)
+                    .unwrap_or(Ok(()))?;
+                let mut file = io::BufWriter::new(std::fs::File::create(&path)?);
+                log.write(&mut file)?;
+                file.flush()
+            });
+
+            match res {
+                Ok(()) => eprintln!("gnvim: wrote the latest nvim messages to {:?}", path),
+                Err(err) => eprintln!("gnvim: failed to write {:?}: {}", path, err),
+            }
+        }
+    });
+}
+
+/// Moves the event logs written by crashed gnvims to `crashes` in gnvim's
+/// cache directory, so each crash is reported only once. Returns the moved
+/// logs.
+pub fn take_crash_dumps() -> io::Result<Vec<PathBuf>> {
+    let dir = glib::user_cache_dir().join("gnvim");
+    let crashes = dir.join("crashes");
+
+    let mut dumps = vec![];
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let is_dump = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.starts_with("crash-") && name.ends_with(".log"))
+            .unwrap_or(false);
+        if !is_dump {
+            continue;
+        }
+
+        std::fs::create_dir_all(&crashes)?;
+        let dest = crashes.join(path.file_name().unwrap_or_default());
+        std::fs::rename(&path, &dest)?;
+        dumps.push(dest);
+    }
+
+    Ok(dumps)
+}
+
+/// File for the `n`th window's event log, when gnvim crashes.
+fn crash_dump_path(n: usize) -> PathBuf {
+    glib::user_cache_dir()
+        .join("gnvim")
+        .join(format!("crash-{}-{}.log", std::process::id(), n))
+}
+
+#[cfg(test)]
+mod tests {
+    use nvim::rpc::message::{Message, Notification};
+
+    use super::{EventLog, EVENT_LOG_LEN};
+
+    #[test]
+    fn test_event_log_off_by_default() {
+        let log = EventLog::default();
+        log.record(&Message::Notification(Notification::new(
+            "method",
+            rmpv::Value::Nil,
+        )));
+        assert!(log.0.borrow().messages.is_empty());
+
+        log.set_enabled(true);
+        log.record(&Message::Notification(Notification::new(
+            "method",
+            rmpv::Value::Nil,
+        )));
+        assert_eq!(log.0.borrow().messages.len(), 1);
+
+        log.set_enabled(false);
+        assert!(log.0.borrow().messages.is_empty());
+    }
+
+    #[test]
+    fn test_event_log_keeps_latest() {
+        let log = EventLog::default();
+        log.set_enabled(true);
+        for i in 0..EVENT_LOG_LEN + 2 {
+            log.record(&Message::Notification(Notification::new(
+                format!("method{}", i),
+                rmpv::Value::Nil,
+            )));
+        }
+
+        let mut out = vec![];
+        log.0.borrow().write(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+
+        let last = format!("notification method{}: nil", EVENT_LOG_LEN + 1);
+        assert_eq!(lines.len(), EVENT_LOG_LEN + 1);
+        assert!(lines[1].ends_with("notification method2: nil"));
+        assert!(lines[EVENT_LOG_LEN].ends_with(&last));
+    }
+}
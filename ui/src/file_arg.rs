@@ -0,0 +1,208 @@
+use std::{
+    ffi::{OsStr, OsString},
+    path::Path,
+};
+
+use gtk::{gio, prelude::*};
+
+/// A file to open, with an optional cursor position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileArg {
+    pub path: OsString,
+    pub line: Option<u64>,
+    pub col: Option<u64>,
+}
+
+impl FileArg {
+    /// Parses `arg`, stripping a `:line` or `:line:col` suffix (e.g. from
+    /// compiler output) unless a file with the literal name exists.
+    pub fn parse(arg: &OsStr) -> Self {
+        let plain = Self {
+            path: arg.to_owned(),
+            line: None,
+            col: None,
+        };
+
+        let s = match arg.to_str() {
+            Some(s) if !Path::new(arg).exists() => s,
+            _ => return plain,
+        };
+
+        let (rest, last) = match s.rsplit_once(':') {
+            Some((rest, last)) if !rest.is_empty() => (rest, last),
+            _ => return plain,
+        };
+        let last = match last.parse::<u64>() {
+            Ok(last) => last,
+            Err(_) => return plain,
+        };
+
+        let line = rest
+            .rsplit_once(':')
+            .filter(|(path, _)| !path.is_empty())
+            .and_then(|(path, line)| Some((path, line.parse::<u64>().ok()?)));
+
+        match line {
+            Some((path, line)) => Self {
+                path: OsString::from(path),
+                line: Some(line),
+                col: Some(last),
+            },
+            None => Self {
+                path: OsString::from(rest),
+                line: Some(last),
+                col: None,
+            },
+        }
+    }
+
+    /// Parses `file` received through `GApplication::open`.
+    pub fn from_file(file: &gio::File) -> Self {
+        match file.path() {
+            Some(path) => Self::parse(path.as_os_str()),
+            None => Self::parse(OsStr::new(file.uri().as_str())),
+        }
+    }
+
+    /// Whether this is a `+cmd` argument for nvim, rather than a file.
+    pub fn is_cmd(&self) -> bool {
+        self.path.to_string_lossy().starts_with('+')
+    }
+
+    /// Formats the file back to an argument, with an absolute path (e.g. for
+    /// sending it to another process). URIs and `+cmd` arguments are kept as
+    /// is.
+    pub fn to_absolute_arg(&self) -> OsString {
+        let mut arg = if self.is_cmd() || self.path.to_string_lossy().contains("://") {
+            self.path.clone()
+        } else {
+            let cwd = std::env::current_dir().unwrap_or_default();
+            cwd.join(&self.path).into_os_string()
+        };
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => arg.push(format!(":{}:{}", line, col)),
+            (Some(line), None) => arg.push(format!(":{}", line)),
+            _ => {}
+        }
+
+        arg
+    }
+
+    /// The nvim `+cmd` argument that moves the cursor to the position.
+    pub fn cursor_cmd(&self) -> Option<OsString> {
+        match (self.line, self.col) {
+            (Some(line), Some(col)) => Some(format!("+call cursor({}, {})", line, col).into()),
+            (Some(line), None) => Some(format!("+{}", line).into()),
+            _ => None,
+        }
+    }
+}
+
+/// Parses file arguments. A `+N` argument sets the line of the next file,
+/// as in `nvim +N file`. Other `+cmd` arguments are kept as is, for nvim.
+pub fn parse_files(args: &[OsString]) -> Vec<FileArg> {
+    let mut files = vec![];
+    let mut line = None;
+
+    for arg in args {
+        let n = arg
+            .to_str()
+            .and_then(|s| s.strip_prefix('+'))
+            .and_then(|n| n.parse::<u64>().ok());
+        if n.is_some() {
+            line = n;
+            continue;
+        }
+
+        let mut file = FileArg::parse(arg);
+        if let Some(line) = line.take() {
+            file.line = Some(line);
+            file.col = None;
+        }
+
+        files.push(file);
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{OsStr, OsString};
+
+    use super::{parse_files, FileArg};
+
+    fn file(path: &str, line: Option<u64>, col: Option<u64>) -> FileArg {
+        FileArg {
+            path: OsString::from(path),
+            line,
+            col,
+        }
+    }
+
+    #[test]
+    fn test_parse_position_suffix() {
+        let cases = [
+            ("foo.rs", file("foo.rs", None, None)),
+            ("foo.rs:12", file("foo.rs", Some(12), None)),
+            ("foo.rs:12:3", file("foo.rs", Some(12), Some(3))),
+            ("foo:bar.rs:12", file("foo:bar.rs", Some(12), None)),
+            ("foo.rs:bar", file("foo.rs:bar", None, None)),
+            (":12", file(":12", None, None)),
+        ];
+
+        for (arg, expected) in cases {
+            assert_eq!(FileArg::parse(OsStr::new(arg)), expected, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn test_parse_files_plus_line() {
+        let args = ["+12", "a.rs", "b.rs:3:4", "+cmd", "+5", "c.rs:1:2"]
+            .into_iter()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_files(&args),
+            vec![
+                file("a.rs", Some(12), None),
+                file("b.rs", Some(3), Some(4)),
+                file("+cmd", None, None),
+                file("c.rs", Some(5), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_absolute_arg() {
+        let cwd = std::env::current_dir().unwrap();
+
+        assert_eq!(
+            file("a.rs", Some(2), None).to_absolute_arg(),
+            OsString::from(format!("{}:2", cwd.join("a.rs").display()))
+        );
+        assert_eq!(
+            file("/tmp/a.rs", None, None).to_absolute_arg(),
+            OsString::from("/tmp/a.rs")
+        );
+        assert_eq!(
+            file("+cmd", None, None).to_absolute_arg(),
+            OsString::from("+cmd")
+        );
+        assert_eq!(
+            file("sftp://host/a.rs", None, None).to_absolute_arg(),
+            OsString::from("sftp://host/a.rs")
+        );
+    }
+
+    #[test]
+    fn test_cursor_cmd() {
+        assert_eq!(file("a", None, None).cursor_cmd(), None);
+        assert_eq!(file("a", Some(2), None).cursor_cmd(), Some("+2".into()));
+        assert_eq!(
+            file("a", Some(2), Some(5)).cursor_cmd(),
+            Some("+call cursor(2, 5)".into())
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use gtk::pango;
+
+use crate::SCALE;
+
+/// Parsed 'guifont' value.
+///
+/// Supports both vim style fonts (e.g. `Fira Code:h14:b,Noto Mono`) and
+/// pango font descriptions (e.g. `Fira Code Bold 14`). Vim style fonts can
+/// also have OpenType features (e.g. `Fira Code:h14:ss03:cv02=2:-liga`).
+#[derive(Debug, Default, PartialEq)]
+pub struct GuiFont {
+    /// Font families, in order of preference.
+    pub families: Vec<String>,
+    /// Font size in points.
+    pub size: Option<f32>,
+    pub bold: bool,
+    pub italic: bool,
+    /// OpenType features, in harfbuzz's format (e.g. `ss03`, `cv02=2`,
+    /// `-liga`).
+    pub features: Vec<String>,
+}
+
+impl GuiFont {
+    /// Parses vim style guifont value. Returns `None` if the value doesn't
+    /// look like a vim style value, in which case it should be treated as
+    /// pango font description.
+    pub fn parse(guifont: &str) -> Option<Self> {
+        if !guifont.contains(':') {
+            return None;
+        }
+
+        let mut font = GuiFont::default();
+        for (i, part) in split_escaped(guifont, ',').iter().enumerate() {
+            let mut opts = split_escaped(part, ':').into_iter();
+
+            let family = opts.next().unwrap_or_default();
+            let family = family.trim();
+            if !family.is_empty() {
+                font.families.push(family.to_string());
+            }
+
+            // Only the first font's options are used, rest of the fonts are
+            // just fallbacks.
+            if i > 0 {
+                continue;
+            }
+
+            for opt in opts {
+                let mut chars = opt.chars();
+                match (chars.next(), chars.as_str()) {
+                    (Some('h'), size) if size.parse::<f32>().is_ok() => {
+                        font.size = size.parse().ok()
+                    }
+                    (Some('b'), "") => font.bold = true,
+                    (Some('i'), "") => font.italic = true,
+                    _ if is_feature(&opt) => font.features.push(opt.clone()),
+                    _ => {}
+                }
+            }
+        }
+
+        Some(font)
+    }
+
+    /// Replaces the size of a vim style guifont value, keeping the rest of
+    /// its options (e.g. the OpenType features) as is. Returns `None` if
+    /// the value isn't a vim style value.
+    pub fn with_size(guifont: &str, size: f32) -> Option<String> {
+        if !guifont.contains(':') {
+            return None;
+        }
+
+        // The options are only read from the first font.
+        let (first, rest) = match find_unescaped(guifont, ',') {
+            Some(i) => guifont.split_at(i),
+            None => (guifont, ""),
+        };
+
+        let mut parts = vec![];
+        let mut first = first;
+        while let Some(i) = find_unescaped(first, ':') {
+            parts.push(&first[..i]);
+            first = &first[i + 1..];
+        }
+        parts.push(first);
+
+        let size = format!("h{}", size);
+        let mut opts = parts.into_iter();
+        let family = opts.next().unwrap_or_default();
+        let opts = std::iter::once(size.as_str()).chain(opts.filter(|opt| {
+            let mut chars = opt.chars();
+            !matches!(chars.next(), Some('h') if chars.as_str().parse::<f32>().is_ok())
+        }));
+
+        let mut guifont = family.to_string();
+        for opt in opts {
+            guifont.push(':');
+            guifont.push_str(opt);
+        }
+        guifont.push_str(rest);
+
+        Some(guifont)
+    }
+
+    pub fn font_desc(&self) -> pango::FontDescription {
+        let mut desc = pango::FontDescription::new();
+
+        if !self.families.is_empty() {
+            desc.set_family(&self.families.join(","));
+        }
+
+        if let Some(size) = self.size {
+            desc.set_size((size * SCALE) as i32);
+        }
+
+        if self.bold {
+            desc.set_weight(pango::Weight::Bold);
+        }
+
+        if self.italic {
+            desc.set_style(pango::Style::Italic);
+        }
+
+        desc
+    }
+}
+
+/// Checks if `opt` is a OpenType feature, i.e. a four character tag with
+/// optional `-`/`+` prefix and `=value` suffix.
+fn is_feature(opt: &str) -> bool {
+    let opt = opt.trim_start_matches(['-', '+']);
+    let (tag, value) = match opt.split_once('=') {
+        Some((tag, value)) => (tag, Some(value)),
+        None => (opt, None),
+    };
+
+    tag.len() == 4
+        && tag.chars().all(|c| c.is_ascii_alphanumeric())
+        && value.map(|v| v.parse::<u32>().is_ok()).unwrap_or(true)
+}
+
+/// Byte index of the first `sep` in `s` that isn't escaped with a backslash.
+fn find_unescaped(s: &str, sep: char) -> Option<usize> {
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == sep => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Splits `s` by `sep`, unless the separator is escaped with a backslash.
+/// Escaped characters are unescaped.
+fn split_escaped(s: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![];
+    let mut current = String::new();
+
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    if c != sep {
+                        current.push('\\');
+                    }
+                    current.push(c);
+                }
+            }
+            c if c == sep => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pango() {
+        assert_eq!(GuiFont::parse("Monospace 12"), None);
+        assert_eq!(GuiFont::parse("Fira Code Bold 14"), None);
+    }
+
+    #[test]
+    fn test_parse_vim() {
+        assert_eq!(
+            GuiFont::parse("Fira Code:h14"),
+            Some(GuiFont {
+                families: vec!["Fira Code".to_string()],
+                size: Some(14.0),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            GuiFont::parse("Fira Code:h10.5:b:i,Noto Mono:h20"),
+            Some(GuiFont {
+                families: vec!["Fira Code".to_string(), "Noto Mono".to_string()],
+                size: Some(10.5),
+                bold: true,
+                italic: true,
+                features: vec![],
+            })
+        );
+
+        assert_eq!(
+            GuiFont::parse("Foo\\,Bar:hx:u"),
+            Some(GuiFont {
+                families: vec!["Foo,Bar".to_string()],
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_features() {
+        assert_eq!(
+            GuiFont::parse("Fira Code:h12:ss03:cv02=2:-liga:cANSI:qDRAFT"),
+            Some(GuiFont {
+                families: vec!["Fira Code".to_string()],
+                size: Some(12.0),
+                features: vec![
+                    "ss03".to_string(),
+                    "cv02=2".to_string(),
+                    "-liga".to_string()
+                ],
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_size() {
+        assert_eq!(GuiFont::with_size("Monospace 12", 14.0), None);
+        assert_eq!(
+            GuiFont::with_size("Fira Code:h12:ss03:-liga,Noto Mono:h20", 14.5),
+            Some("Fira Code:h14.5:ss03:-liga,Noto Mono:h20".to_string())
+        );
+        assert_eq!(
+            GuiFont::with_size("Foo\\:Bar:cv02=2", 10.0),
+            Some("Foo\\:Bar:h10:cv02=2".to_string())
+        );
+    }
+}
@@ -10,7 +10,12 @@ const DEFAULT_WIDTH: f32 = 8.0 * SCALE;
 #[derive(Default)]
 pub struct Font {
     pub guifont: RefCell<String>,
+    pub guifontwide: RefCell<String>,
+    /// Additional OpenType features (i.e. not from guifont).
+    pub features: RefCell<String>,
     pub font_desc: RefCell<pango::FontDescription>,
+    /// OpenType features from guifont and `features`.
+    pub font_features: RefCell<String>,
 
     pub linespace: Cell<f32>,
     pub height: Cell<f32>,
@@ -21,6 +26,58 @@ pub struct Font {
     pub underline_thickness: Cell<f32>,
     pub strikethrough_position: Cell<f32>,
     pub strikethrough_thickness: Cell<f32>,
+
+    /// If the primary font family has a bold face.
+    pub has_bold: Cell<bool>,
+    /// If the primary font family has a italic (or oblique) face.
+    pub has_italic: Cell<bool>,
+
+    /// Family for bold text, instead of the primary family.
+    pub bold_family: RefCell<String>,
+    /// Family for italic text, instead of the primary family.
+    pub italic_family: RefCell<String>,
+    pub bold_desc: RefCell<Option<pango::FontDescription>>,
+    pub italic_desc: RefCell<Option<pango::FontDescription>>,
+}
+
+/// Pango font description for `guifont`, with the `guifontwide` families as
+/// fallbacks.
+pub fn font_desc(guifont: &str, guifontwide: &str) -> pango::FontDescription {
+    let mut font_desc = super::GuiFont::parse(guifont)
+        .map(|font| font.font_desc())
+        .unwrap_or_else(|| pango::FontDescription::from_string(guifont));
+
+    if font_desc.size() == 0 {
+        // TODO(ville): Should probably notify the user here.
+        font_desc.set_size(12 * SCALE as i32);
+    }
+
+    // Add the guifontwide families as fallbacks. Pango will use them for
+    // the glyphs that the primary families lack.
+    let wide = super::GuiFont::parse(guifontwide)
+        .map(|font| font.families)
+        .unwrap_or_else(|| {
+            pango::FontDescription::from_string(guifontwide)
+                .family()
+                .map(|family| family.split(',').map(String::from).collect())
+                .unwrap_or_default()
+        });
+    if !wide.is_empty() {
+        let mut families = font_desc
+            .family()
+            .map(|family| family.split(',').map(String::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for family in wide {
+            let family = family.trim().to_string();
+            if !family.is_empty() && !families.contains(&family) {
+                families.push(family);
+            }
+        }
+
+        font_desc.set_family(&families.join(","));
+    }
+
+    font_desc
 }
 
 #[glib::object_subclass]
@@ -31,6 +88,93 @@ impl ObjectSubclass for Font {
 }
 
 impl Font {
+    fn update_font_desc(&self) {
+        let guifont = self.guifont.borrow();
+        let features = super::GuiFont::parse(&guifont)
+            .map(|font| font.features)
+            .unwrap_or_default()
+            .into_iter()
+            .chain(
+                self.features
+                    .borrow()
+                    .split(',')
+                    .map(|f| f.trim().to_string())
+                    .filter(|f| !f.is_empty()),
+            )
+            .collect::<Vec<_>>();
+        self.font_features.replace(features.join(", "));
+
+        self.font_desc
+            .replace(font_desc(&guifont, &self.guifontwide.borrow()));
+    }
+
+    /// Creates the font descriptions for the bold and italic families. The
+    /// primary families are kept as fallbacks.
+    pub fn update_style_descs(&self) {
+        let desc = |family: &str| {
+            let family = family.trim();
+            if family.is_empty() {
+                return None;
+            }
+
+            let mut desc = self.font_desc.borrow().clone();
+            let families = std::iter::once(family.to_string())
+                .chain(desc.family().map(|family| family.to_string()))
+                .collect::<Vec<_>>();
+            desc.set_family(&families.join(","));
+            Some(desc)
+        };
+
+        self.bold_desc.replace(desc(&self.bold_family.borrow()));
+        self.italic_desc.replace(desc(&self.italic_family.borrow()));
+    }
+
+    /// Checks which faces the primary font family has, so the missing ones
+    /// can be synthesized.
+    fn update_faces(&self, ctx: &pango::Context) {
+        let primary = self
+            .font_desc
+            .borrow()
+            .family()
+            .and_then(|family| family.split(',').next().map(|f| f.trim().to_lowercase()));
+        let family = ctx
+            .list_families()
+            .into_iter()
+            .find(|family| Some(family.name().to_lowercase()) == primary);
+
+        // NOTE(ville): Aliases (e.g. "Monospace") aren't listed. Assume they
+        // have all the faces.
+        let (bold, italic) = match family {
+            Some(family) => {
+                let faces = family
+                    .list_faces()
+                    .iter()
+                    .map(|face| face.describe())
+                    .collect::<Vec<_>>();
+
+                (
+                    faces.iter().any(|desc| {
+                        matches!(
+                            desc.weight(),
+                            pango::Weight::Semibold
+                                | pango::Weight::Bold
+                                | pango::Weight::Ultrabold
+                                | pango::Weight::Heavy
+                                | pango::Weight::Ultraheavy
+                        )
+                    }),
+                    faces.iter().any(|desc| {
+                        matches!(desc.style(), pango::Style::Italic | pango::Style::Oblique)
+                    }),
+                )
+            }
+            None => (true, true),
+        };
+
+        self.has_bold.set(bold);
+        self.has_italic.set(italic);
+    }
+
     pub fn update_metrics(&self, ctx: pango::Context) {
         let font_metrics = ctx.metrics(Some(&self.font_desc.borrow()), None);
 
@@ -52,11 +196,13 @@ impl Font {
 
         let height = font_metrics.height() as f32;
         self.height.set(
-            if height != 0.0 {
+            (if height != 0.0 {
                 height
             } else {
                 DEFAULT_HEIGHT
-            } + self.linespace.get(),
+            } + self.linespace.get())
+            // Don't let negative linespace to collapse the lines.
+            .max(SCALE),
         );
 
         let char_width = font_metrics.approximate_char_width() as f32;
@@ -72,7 +218,10 @@ impl ObjectImpl for Font {
     fn constructed(&self) {
         self.parent_constructed();
 
+        self.update_font_desc();
+
         let ctx = self.obj().pango_context();
+        self.update_faces(&ctx);
         self.update_metrics(ctx);
     }
 
@@ -84,8 +233,17 @@ impl ObjectImpl for Font {
                     .default_value(Some("Monospace 12"))
                     .flags(glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY)
                     .build(),
+                glib::ParamSpecString::builder("guifontwide")
+                    .default_value(Some(""))
+                    .flags(glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY)
+                    .build(),
+                glib::ParamSpecString::builder("features")
+                    .default_value(Some(""))
+                    .flags(glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY)
+                    .build(),
+                // NOTE(ville): Negative linespace is allowed, so lines can be
+                // tightened too.
                 glib::ParamSpecFloat::builder("linespace")
-                    .minimum(0.0)
                     .default_value(0.0)
                     .flags(glib::ParamFlags::READWRITE | glib::ParamFlags::CONSTRUCT_ONLY)
                     .build(),
@@ -98,6 +256,8 @@ impl ObjectImpl for Font {
     fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
         match pspec.name() {
             "guifont" => self.guifont.borrow().to_value(),
+            "guifontwide" => self.guifontwide.borrow().to_value(),
+            "features" => self.features.borrow().to_value(),
             "linespace" => self.linespace.get().to_value(),
             _ => unimplemented!(),
         }
@@ -110,14 +270,23 @@ impl ObjectImpl for Font {
                     .get::<&str>()
                     .expect("property guifont needs to be &str");
 
-                let mut font_desc = pango::FontDescription::from_string(font_str);
-                if font_desc.size() == 0 {
-                    // TODO(ville): Should probably notify the user here.
-                    font_desc.set_size(12 * SCALE as i32);
-                }
-
                 self.guifont.replace(font_str.to_string());
-                self.font_desc.replace(font_desc);
+            }
+            "guifontwide" => {
+                let font_str = value
+                    .get::<Option<&str>>()
+                    .expect("property guifontwide needs to be &str")
+                    .unwrap_or_default();
+
+                self.guifontwide.replace(font_str.to_string());
+            }
+            "features" => {
+                let features = value
+                    .get::<Option<&str>>()
+                    .expect("property features needs to be &str")
+                    .unwrap_or_default();
+
+                self.features.replace(features.to_string());
             }
             "linespace" => {
                 self.linespace.set(
@@ -1,11 +1,14 @@
 use std::cell::Ref;
 
-use gtk::{glib, pango, subclass::prelude::*};
+use gtk::{glib, pango, prelude::*, subclass::prelude::*};
 
 use crate::SCALE;
 
+mod guifont;
 mod imp;
 
+pub use guifont::GuiFont;
+
 glib::wrapper! {
     /// Font for gnvim. Combines neovim's font settings (i.e. guifont and
     /// linespace) with pango font description & font metrics.
@@ -14,18 +17,72 @@ glib::wrapper! {
         @implements gtk::ConstraintTarget, gtk::Buildable, gtk::Accessible;
 }
 
+/// Loads `guifont` (and `guifontwide`) with a font map of its own, so it can
+/// be done off the main thread. This warms up fontconfig's caches, which the
+/// ui's font map shares, so creating the same `Font` later is quick.
+///
+/// # Arguments
+///
+/// * `font_map` - Type of the font map to use, i.e. the ui's font map's type.
+/// * `guifont` - The neovim guifont value.
+/// * `guifontwide` - The neovim guifontwide value.
+pub fn warm_up(font_map: glib::Type, guifont: &str, guifontwide: &str) {
+    let font_map = match glib::Object::with_type(font_map).downcast::<pango::FontMap>() {
+        Ok(font_map) => font_map,
+        Err(_) => return,
+    };
+
+    let desc = imp::font_desc(guifont, guifontwide);
+    let ctx = font_map.create_context();
+    ctx.load_fontset(&desc, &pango::Language::default());
+    ctx.metrics(Some(&desc), None);
+}
+
 impl Font {
     /// Creates new font.
     ///
     /// # Arguments
     ///
-    /// * `guifont` - The neovim guifont value. Should be something that
-    ///               `pango::FontDescription::from_value` knows.
+    /// * `guifont` - The neovim guifont value. Either vim style value (e.g.
+    ///               `Fira Code:h14`) or something that
+    ///               `pango::FontDescription::from_string` knows.
     /// * `linespace` - The neovim linespace value.
     pub fn new(guifont: &str, linespace: f32) -> Self {
+        Self::new_with_wide(guifont, "", linespace)
+    }
+
+    /// Creates new font with fallback fonts.
+    ///
+    /// # Arguments
+    ///
+    /// * `guifont` - The neovim guifont value.
+    /// * `guifontwide` - The neovim guifontwide value. The fonts are used
+    ///                   for glyphs that are missing from `guifont`.
+    /// * `linespace` - The neovim linespace value.
+    pub fn new_with_wide(guifont: &str, guifontwide: &str, linespace: f32) -> Self {
+        Self::new_with_features(guifont, guifontwide, linespace, "")
+    }
+
+    /// Creates new font with fallback fonts and OpenType features.
+    ///
+    /// # Arguments
+    ///
+    /// * `guifont` - The neovim guifont value.
+    /// * `guifontwide` - The neovim guifontwide value.
+    /// * `linespace` - The neovim linespace value.
+    /// * `features` - Comma separated OpenType features (e.g. `ss01,cv02=2`),
+    ///                in addition to the ones in `guifont`.
+    pub fn new_with_features(
+        guifont: &str,
+        guifontwide: &str,
+        linespace: f32,
+        features: &str,
+    ) -> Self {
         glib::Object::builder()
             .property("guifont", guifont)
+            .property("guifontwide", guifontwide)
             .property("linespace", linespace)
+            .property("features", features)
             .build()
     }
 
@@ -34,12 +91,94 @@ impl Font {
         self.imp().font_desc.borrow()
     }
 
+    /// Pango font description for `bold` and/or `italic` text. The bold and
+    /// italic families are used if set, italic taking precedence.
+    pub fn style_font_desc(&self, bold: bool, italic: bool) -> pango::FontDescription {
+        let imp = self.imp();
+        let italic_desc = italic.then(|| imp.italic_desc.borrow().clone()).flatten();
+        let bold_desc = || bold.then(|| imp.bold_desc.borrow().clone()).flatten();
+
+        italic_desc
+            .or_else(bold_desc)
+            .unwrap_or_else(|| self.font_desc().clone())
+    }
+
+    /// Sets the families for bold and italic text. Empty family uses the
+    /// primary family.
+    ///
+    /// # Arguments
+    ///
+    /// * `bold` - Family for bold text (e.g. `Victor Mono`).
+    /// * `italic` - Family for italic text.
+    pub fn set_style_families(&self, bold: &str, italic: &str) {
+        let imp = self.imp();
+        imp.bold_family.replace(bold.to_string());
+        imp.italic_family.replace(italic.to_string());
+        imp.update_style_descs();
+    }
+
+    /// Family for bold text, empty if the primary family is used.
+    pub fn bold_family(&self) -> Ref<String> {
+        self.imp().bold_family.borrow()
+    }
+
+    /// Family for italic text, empty if the primary family is used.
+    pub fn italic_family(&self) -> Ref<String> {
+        self.imp().italic_family.borrow()
+    }
+
+    /// Creates a copy of this font with different size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new font size in points.
+    pub fn with_size(&self, size: f32) -> Self {
+        // NOTE(ville): Keep vim style guifonts as is, so their features
+        // survive the later font changes (e.g. to the features set through
+        // gnvim).
+        let guifont = GuiFont::with_size(&self.guifont(), size).unwrap_or_else(|| {
+            let mut desc = self.font_desc().clone();
+            desc.set_size((size * SCALE) as i32);
+            desc.to_str().to_string()
+        });
+
+        let font = Self::new_with_features(
+            &guifont,
+            &self.guifontwide(),
+            self.linespace() / SCALE,
+            &self.features(),
+        );
+        font.set_style_families(&self.bold_family(), &self.italic_family());
+
+        font
+    }
+
+    /// Font size in points.
+    pub fn size(&self) -> f32 {
+        self.font_desc().size() as f32 / SCALE
+    }
+
     /// Neovim guifont. This is what was used to construct the
     /// pango font description.
     pub fn guifont(&self) -> Ref<String> {
         self.imp().guifont.borrow()
     }
 
+    /// Neovim guifontwide.
+    pub fn guifontwide(&self) -> Ref<String> {
+        self.imp().guifontwide.borrow()
+    }
+
+    /// Additional OpenType features set through gnvim.
+    pub fn features(&self) -> Ref<String> {
+        self.imp().features.borrow()
+    }
+
+    /// All the OpenType features, in pango's format.
+    pub fn font_features(&self) -> Ref<String> {
+        self.imp().font_features.borrow()
+    }
+
     /// Baseline in pango units.
     pub fn baseline(&self) -> f32 {
         self.height() - self.descent() - self.linespace() / 2.0
@@ -85,6 +224,29 @@ impl Font {
         self.imp().height.get()
     }
 
+    /// If the font has a real bold face (or a bold family is set). Otherwise,
+    /// bold is synthesized.
+    pub fn has_bold(&self) -> bool {
+        self.imp().has_bold.get() || self.imp().bold_desc.borrow().is_some()
+    }
+
+    /// If the font has a real italic face (or an italic family is set).
+    /// Otherwise, italic is synthesized.
+    pub fn has_italic(&self) -> bool {
+        self.imp().has_italic.get() || self.imp().italic_desc.borrow().is_some()
+    }
+
+    /// If the font's glyphs for ambiguous width characters (e.g. `①`) are
+    /// two cells wide, as is common with CJK fonts.
+    pub fn has_wide_ambiguous(&self) -> bool {
+        let layout = pango::Layout::new(&self.pango_context());
+        layout.set_font_description(Some(&self.font_desc()));
+        layout.set_text("\u{2460}");
+
+        let (_, logical) = layout.extents();
+        logical.width() as f32 > self.char_width() * 1.5
+    }
+
     /// Approximate character width in pango units.
     pub fn char_width(&self) -> f32 {
         self.imp().char_width.get()
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use gtk::{gdk, prelude::*};
 
 /// Mapping between gtk mouse events and nvim mouse events.
@@ -6,6 +8,10 @@ pub enum Mouse {
     Left,
     Right,
     Middle,
+    /// Back button.
+    X1,
+    /// Forward button.
+    X2,
     Wheel,
 }
 
@@ -15,9 +21,26 @@ impl Mouse {
             Mouse::Left => "left",
             Mouse::Right => "right",
             Mouse::Middle => "middle",
+            Mouse::X1 => "x1",
+            Mouse::X2 => "x2",
             Mouse::Wheel => "wheel",
         }
     }
+
+    /// The button in nvim's key notation (e.g. `MiddleMouse`), prefixed with
+    /// `modifier`. `None` for the wheel.
+    pub fn as_nvim_key(&self, modifier: &str) -> Option<String> {
+        let key = match self {
+            Mouse::Left => "LeftMouse",
+            Mouse::Right => "RightMouse",
+            Mouse::Middle => "MiddleMouse",
+            Mouse::X1 => "X1Mouse",
+            Mouse::X2 => "X2Mouse",
+            Mouse::Wheel => return None,
+        };
+
+        Some(format!("{}{}", modifier, key))
+    }
 }
 
 impl<T> From<&T> for Mouse
@@ -29,6 +52,8 @@ where
             gdk::BUTTON_PRIMARY => Mouse::Left,
             gdk::BUTTON_SECONDARY => Mouse::Right,
             gdk::BUTTON_MIDDLE => Mouse::Middle,
+            8 => Mouse::X1,
+            9 => Mouse::X2,
             _ => {
                 println!("unknown button, defaulting to primary");
                 Mouse::Left
@@ -63,8 +88,17 @@ impl Action {
     }
 }
 
+/// Default notation for the Hyper modifier (i.e. `<T-...>`).
+pub const DEFAULT_HYPER: &str = "T";
+
 /// Turns gtk modifier to nvim input's modifier prefix.
 pub fn modifier_to_nvim(state: &gdk::ModifierType) -> String {
+    modifier_to_nvim_with_hyper(state, DEFAULT_HYPER)
+}
+
+/// Like `modifier_to_nvim`, but with custom notation for the Hyper modifier.
+/// Empty `hyper` drops the modifier.
+pub fn modifier_to_nvim_with_hyper(state: &gdk::ModifierType, hyper: &str) -> String {
     let mut modifier = String::new();
 
     if state.contains(gdk::ModifierType::SHIFT_MASK) {
@@ -79,10 +113,56 @@ pub fn modifier_to_nvim(state: &gdk::ModifierType) -> String {
     if state.contains(gdk::ModifierType::SUPER_MASK) {
         modifier.push_str("D-");
     }
+    // NOTE(ville): Super and Hyper often share the same modifier (e.g. Mod4
+    // on X11), in which case both are set. Prefer Super then.
+    if state.contains(gdk::ModifierType::HYPER_MASK)
+        && !state.contains(gdk::ModifierType::SUPER_MASK)
+        && !hyper.is_empty()
+    {
+        modifier.push_str(hyper);
+        modifier.push('-');
+    }
 
     modifier
 }
 
+/// Turns a gtk key event into nvim input (e.g. `<C-a>`).
+///
+/// # Arguments
+///
+/// * `keyval` - The key.
+/// * `state` - Modifiers of the key event.
+/// * `remap` - User's mapping from gdk key names to nvim keys (e.g.
+///             `XF86AudioPlay` to `F13`). Takes precedence over the default
+///             translation.
+/// * `hyper` - Notation for the Hyper modifier.
+pub fn event_to_nvim_input(
+    keyval: gdk::Key,
+    state: gdk::ModifierType,
+    remap: &HashMap<String, String>,
+    hyper: &str,
+) -> Option<String> {
+    let mut input = modifier_to_nvim_with_hyper(&state, hyper);
+    let keyname = keyval.name()?;
+
+    if let Some(key) = remap.get(keyname.as_str()) {
+        // Allow the key in both `F13` and `<F13>` forms.
+        let key = key
+            .strip_prefix('<')
+            .and_then(|key| key.strip_suffix('>'))
+            .unwrap_or(key);
+        // A plain `<` would start a new key.
+        input.push_str(if key == "<" { "lt" } else { key });
+    } else if keyname.chars().count() > 1 {
+        let n = keyname_to_nvim_key(keyname.as_str())?;
+        input.push_str(n);
+    } else {
+        input.push(keyval.to_unicode()?);
+    }
+
+    Some(format!("<{}>", input))
+}
+
 pub fn keyname_to_nvim_key(s: &str) -> Option<&str> {
     // Originally sourced from python-gui.
     match s {
@@ -142,15 +222,77 @@ pub fn keyname_to_nvim_key(s: &str) -> Option<&str> {
         "F10" => Some("F10"),
         "F11" => Some("F11"),
         "F12" => Some("F12"),
+        // Keypad, with NumLock on.
+        "KP_0" => Some("k0"),
+        "KP_1" => Some("k1"),
+        "KP_2" => Some("k2"),
+        "KP_3" => Some("k3"),
+        "KP_4" => Some("k4"),
+        "KP_5" => Some("k5"),
+        "KP_6" => Some("k6"),
+        "KP_7" => Some("k7"),
+        "KP_8" => Some("k8"),
+        "KP_9" => Some("k9"),
+        "KP_Decimal" => Some("kPoint"),
+        "KP_Separator" => Some("kComma"),
+        // Keypad, with NumLock off.
+        "KP_Insert" => Some("kInsert"),
+        "KP_End" => Some("kEnd"),
+        "KP_Down" => Some("kDown"),
+        "KP_Page_Down" => Some("kPageDown"),
+        "KP_Left" => Some("kLeft"),
+        "KP_Begin" => Some("kOrigin"),
+        "KP_Right" => Some("kRight"),
+        "KP_Home" => Some("kHome"),
+        "KP_Up" => Some("kUp"),
+        "KP_Page_Up" => Some("kPageUp"),
+        "KP_Delete" => Some("kDel"),
+        // Keypad, regardless of NumLock.
+        "KP_Enter" => Some("kEnter"),
+        "KP_Add" => Some("kPlus"),
+        "KP_Subtract" => Some("kMinus"),
+        "KP_Multiply" => Some("kMultiply"),
+        "KP_Divide" => Some("kDivide"),
+        "KP_Equal" => Some("kEqual"),
+        // Keypad keys that nvim has no keypad notation for.
+        "KP_Space" => Some("Space"),
+        "KP_Tab" => Some("Tab"),
+        "KP_F1" => Some("F1"),
+        "KP_F2" => Some("F2"),
+        "KP_F3" => Some("F3"),
+        "KP_F4" => Some("F4"),
         _ => None,
     }
 }
 
+/// If `keyval` is on the keypad. Keypad keys are sent with their own
+/// notation (e.g. `<k0>`), so they can be mapped separately.
+pub fn is_keypad(keyval: gdk::Key) -> bool {
+    keyval
+        .name()
+        .map(|name| name.starts_with("KP_"))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use gtk::gdk;
 
-    use super::modifier_to_nvim;
+    use super::{
+        event_to_nvim_input, modifier_to_nvim, modifier_to_nvim_with_hyper, Mouse, DEFAULT_HYPER,
+    };
+
+    #[test]
+    fn test_mouse_as_nvim_key() {
+        assert_eq!(Mouse::X1.as_nvim_key(""), Some(String::from("X1Mouse")));
+        assert_eq!(
+            Mouse::Middle.as_nvim_key("C-"),
+            Some(String::from("C-MiddleMouse"))
+        );
+        assert_eq!(Mouse::Wheel.as_nvim_key(""), None);
+    }
 
     #[test]
     fn test_modifier_to_nvim() {
@@ -174,5 +316,101 @@ mod tests {
         m.set(gdk::ModifierType::SHIFT_MASK, true);
         m.set(gdk::ModifierType::ALT_MASK, true);
         assert_eq!(&modifier_to_nvim(&m), "S-M-");
+
+        assert_eq!(&modifier_to_nvim(&gdk::ModifierType::HYPER_MASK), "T-");
+        assert_eq!(
+            &modifier_to_nvim_with_hyper(&gdk::ModifierType::HYPER_MASK, "D"),
+            "D-"
+        );
+        assert_eq!(
+            &modifier_to_nvim_with_hyper(&gdk::ModifierType::HYPER_MASK, ""),
+            ""
+        );
+
+        // Super and Hyper sharing a modifier.
+        let mut m = gdk::ModifierType::empty();
+        m.set(gdk::ModifierType::SUPER_MASK, true);
+        m.set(gdk::ModifierType::HYPER_MASK, true);
+        assert_eq!(&modifier_to_nvim(&m), "D-");
+    }
+
+    #[test]
+    fn test_event_to_nvim_input_remap() {
+        let mut remap = HashMap::new();
+        remap.insert(String::from("XF86AudioPlay"), String::from("F13"));
+        remap.insert(String::from("Menu"), String::from("<`>"));
+        remap.insert(String::from("XF86Back"), String::from("<"));
+
+        let key = |name| gdk::Key::from_name(name).expect("unknown key name");
+        let input = |name, state, remap: &HashMap<String, String>| {
+            event_to_nvim_input(key(name), state, remap, DEFAULT_HYPER)
+        };
+
+        assert_eq!(
+            input("XF86AudioPlay", gdk::ModifierType::empty(), &remap),
+            Some(String::from("<F13>"))
+        );
+        assert_eq!(
+            input("Menu", gdk::ModifierType::CONTROL_MASK, &remap),
+            Some(String::from("<C-`>"))
+        );
+        assert_eq!(
+            input("XF86Back", gdk::ModifierType::empty(), &remap),
+            Some(String::from("<lt>"))
+        );
+        assert_eq!(
+            input("Return", gdk::ModifierType::empty(), &remap),
+            Some(String::from("<CR>"))
+        );
+        assert_eq!(
+            input("XF86AudioPlay", gdk::ModifierType::empty(), &HashMap::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_event_to_nvim_input_keypad() {
+        let key = |name| gdk::Key::from_name(name).expect("unknown key name");
+        let input =
+            |name, state| event_to_nvim_input(key(name), state, &HashMap::new(), DEFAULT_HYPER);
+
+        assert_eq!(
+            input("KP_0", gdk::ModifierType::empty()),
+            Some(String::from("<k0>"))
+        );
+        assert_eq!(
+            input("KP_Insert", gdk::ModifierType::empty()),
+            Some(String::from("<kInsert>"))
+        );
+        assert_eq!(
+            input("KP_Add", gdk::ModifierType::CONTROL_MASK),
+            Some(String::from("<C-kPlus>"))
+        );
+        assert_eq!(
+            input("KP_Enter", gdk::ModifierType::empty()),
+            Some(String::from("<kEnter>"))
+        );
+        assert_eq!(
+            input("KP_Space", gdk::ModifierType::empty()),
+            Some(String::from("<Space>"))
+        );
+        assert_eq!(
+            input("KP_Tab", gdk::ModifierType::SHIFT_MASK),
+            Some(String::from("<S-Tab>"))
+        );
+        for (name, expected) in [
+            ("KP_F1", "<F1>"),
+            ("KP_F2", "<F2>"),
+            ("KP_F3", "<F3>"),
+            ("KP_F4", "<F4>"),
+        ] {
+            assert_eq!(
+                input(name, gdk::ModifierType::empty()),
+                Some(String::from(expected))
+            );
+        }
+
+        assert!(super::is_keypad(key("KP_9")));
+        assert!(!super::is_keypad(key("9")));
     }
 }
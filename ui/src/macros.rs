@@ -43,6 +43,17 @@ macro_rules! log {
     };
 }
 
+#[macro_export]
+macro_rules! debug {
+    ($format:literal $(,$arg:expr)* $(,)?) => {
+        $crate::log!(
+            gtk::glib::LogLevel::Debug,
+            $format,
+            $($arg),*
+        )
+    };
+}
+
 #[macro_export]
 macro_rules! warn {
     // TODO(ville): It would make sense to display some error to the user here too.
@@ -1,46 +1,229 @@
+use std::ffi::OsString;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use clap::ValueEnum;
 use glib::ExitCode;
-use gtk::{gio, pango, prelude::*};
+use gtk::{gdk, gio, pango, prelude::*};
+use once_cell::sync::OnceCell;
 
 mod api;
 mod arguments;
+mod box_drawing;
 mod boxed;
 mod child_iter;
 mod colors;
 mod components;
+mod event_log;
+mod file_arg;
 mod font;
 mod input;
 mod macros;
 mod math;
 mod nvim;
+mod remote;
 mod render;
+mod renderer_info;
+mod slow_link;
+mod updater;
 
 use components::appwindow::AppWindow;
+use file_arg::FileArg;
 
 pub const SCALE: f32 = pango::SCALE as f32;
+/// Gnvim's version, and the commit it was built from.
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), " (", env!("GNVIM_COMMIT"), ")");
 pub const WINDOW_RESIZE_DEBOUNCE_MS: u64 = 10;
+/// How long nvim needs to be busy before the wait pointer is shown.
+pub const BUSY_POINTER_DELAY_MS: u64 = 500;
+/// Exit status of nvim, which becomes ours.
+pub static NVIM_EXIT_STATUS: AtomicI32 = AtomicI32::new(0);
+/// Set when we set GSK_RENDERER ourselves, so it's not passed on to nvim.
+pub static OWN_GSK_RENDERER: AtomicBool = AtomicBool::new(false);
+/// The user's GDK_BACKEND, unset for `--backend` and restored for nvim.
+pub static USER_GDK_BACKEND: OnceCell<OsString> = OnceCell::new();
+/// Default size of the `--scratch` window.
+const SCRATCH_WIDTH: i32 = 560;
+const SCRATCH_HEIGHT: i32 = 360;
 
 fn main() -> ExitCode {
     gio::resources_register_include!("gnvim.gresource").expect("Failed to register resources.");
 
     let args = arguments::Arguments::parse();
+    event_log::install_panic_hook();
+
+    if let Some(code) = remote::run(&args) {
+        return code;
+    }
+
+    // The renderer can't be changed once gtk is initialized. Let the user's
+    // own choice take precedence.
+    if args.safe_rendering && std::env::var_os("GSK_RENDERER").is_none() {
+        std::env::set_var("GSK_RENDERER", "cairo");
+        OWN_GSK_RENDERER.store(true, Ordering::Relaxed);
+    }
+
+    // Same for the backend. GDK_BACKEND would take precedence over our
+    // choice, so it's unset here and restored for nvim (and its :terminal).
+    if let Some(backend) = args.backend {
+        if let Some(value) = std::env::var_os("GDK_BACKEND") {
+            std::env::remove_var("GDK_BACKEND");
+            let _ = USER_GDK_BACKEND.set(value);
+        }
+        gdk::set_allowed_backends(backend.gdk_backend());
+    }
 
     let mut flags = gio::ApplicationFlags::empty();
-    flags.insert(gio::ApplicationFlags::NON_UNIQUE);
+    if !args.single_instance {
+        flags.insert(gio::ApplicationFlags::NON_UNIQUE);
+    }
+    // Files from other processes (e.g. xdg-open) come through `open`.
     flags.insert(gio::ApplicationFlags::HANDLES_OPEN);
+    let remote_cmdline = args.single_instance && (args.wait || !args.files.is_empty());
+    if remote_cmdline {
+        // Our own files go through the command line instead, so our options
+        // reach the running instance, and with `--wait` the invoking process
+        // is kept waiting until the running instance releases it.
+        flags.insert(gio::ApplicationFlags::HANDLES_COMMAND_LINE);
+    }
 
     let app = gtk::Application::builder()
         .application_id("com.github.vhakulinen.gnvim")
         .flags(flags)
         .build();
 
-    app.connect_activate(move |app| build_ui(app, &args));
+    let gtk_args: Vec<String> = if remote_cmdline {
+        app.add_main_option(
+            "open-in",
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::String,
+            "Where to open the files",
+            Some("OPEN_IN"),
+        );
+        app.add_main_option(
+            "wait",
+            glib::Char(0),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            "Wait for the files to be closed",
+            None,
+        );
+
+        let open_in = args
+            .open_in
+            .to_possible_value()
+            .map(|value| format!("--open-in={}", value.get_name()));
+        let wait = args.wait.then(|| String::from("--wait"));
+
+        std::iter::once(String::from("gnvim"))
+            .chain(open_in)
+            .chain(wait)
+            .chain(
+                file_arg::parse_files(&args.files)
+                    .iter()
+                    .map(|file| file.to_absolute_arg().to_string_lossy().into_owned()),
+            )
+            .collect()
+    } else {
+        vec![]
+    };
+
+    // Used by the desktop notifications, to focus the window they're from.
+    let present = gio::SimpleAction::new("present-window", Some(glib::VariantTy::UINT32));
+    present.connect_activate(glib::clone!(@weak app => move |_, id| {
+        if let Some(window) = id
+            .and_then(|id| id.get::<u32>())
+            .and_then(|id| app.window_by_id(id))
+        {
+            window.present();
+        }
+    }));
+    app.add_action(&present);
 
-    // NOTE(ville): Pass empty arguments to the gtk application. We handle
-    // those manually.
-    app.run_with_args::<&str>(&[])
+    let open_args = args.clone();
+    let wait_args = args.clone();
+    app.connect_activate(move |app| {
+        build_ui(app, &args);
+    });
+    app.connect_open(move |app, files, _| {
+        let files = files.iter().map(FileArg::from_file).collect();
+        open_files(app, &open_args, files, None);
+    });
+    app.connect_command_line(move |app, cmdline| {
+        if cmdline.is_remote() {
+            let files = file_arg::parse_files(cmdline.arguments().get(1..).unwrap_or_default());
+            let options = cmdline.options_dict();
+            let args = arguments::Arguments {
+                open_in: options
+                    .lookup::<String>("open-in")
+                    .ok()
+                    .flatten()
+                    .and_then(|open_in| arguments::OpenIn::from_str(&open_in, true).ok())
+                    .unwrap_or(wait_args.open_in),
+                ..wait_args.clone()
+            };
+            let wait = options.contains("wait").then_some(cmdline);
+            open_files(app, &args, files, wait);
+
+            // A held command line gets its status once it's released (i.e.
+            // nvim's, if nvim exits first).
+            ExitCode::from(cmdline.exit_status())
+        } else {
+            // Our own invocation, which waits by running.
+            build_ui(app, &wait_args);
+            ExitCode::SUCCESS
+        }
+    });
+
+    // Other than the files, we handle the arguments manually.
+    let code = app.run_with_args(&gtk_args);
+    if code != ExitCode::SUCCESS {
+        return code;
+    }
+
+    ExitCode::from(NVIM_EXIT_STATUS.load(Ordering::Relaxed))
 }
 
-fn build_ui(app: &gtk::Application, args: &arguments::Arguments) {
+fn build_ui(app: &gtk::Application, args: &arguments::Arguments) -> AppWindow {
     let window = AppWindow::new(app, args);
+    if args.scratch.is_some() {
+        window.set_default_size(SCRATCH_WIDTH, SCRATCH_HEIGHT);
+    }
     window.present();
+    window
+}
+
+/// Opens files received from another gnvim process (or e.g. xdg-open), in
+/// the active window or a new one, as set by `--open-in`. With `wait` (i.e.
+/// the other process' `--wait`), the other process waits until the files
+/// are closed.
+fn open_files(
+    app: &gtk::Application,
+    args: &arguments::Arguments,
+    files: Vec<FileArg>,
+    wait: Option<&gio::ApplicationCommandLine>,
+) {
+    let window = app
+        .active_window()
+        .and_then(|window| window.downcast::<AppWindow>().ok());
+
+    match (window, args.open_in.ex_cmd()) {
+        (Some(window), Some(cmd)) => {
+            window.open_files(files, cmd, wait.cloned());
+            window.present();
+        }
+        (window, _) => {
+            let args = arguments::Arguments {
+                files: files.iter().map(FileArg::to_absolute_arg).collect(),
+                // Stdin belongs to the first window.
+                stdin_fd: args.stdin_fd.filter(|_| window.is_none()),
+                ..args.clone()
+            };
+            let window = build_ui(app, &args);
+            if let Some(wait) = wait {
+                // The files are all the new window has, so wait for it.
+                window.hold_command_line(wait.clone());
+            }
+        }
+    }
 }
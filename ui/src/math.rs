@@ -1,3 +1,44 @@
+use nvim::serde;
+
 pub fn ease_out_cubic(t: f64) -> f64 {
     1.0 + (t - 1.0).powi(3)
 }
+
+/// Easing of an animation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, glib::Boxed, serde::Deserialize)]
+#[boxed_type(name = "Easing")]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    EaseOutQuad,
+    #[default]
+    EaseOutCubic,
+    EaseOutExpo,
+    EaseInOutCubic,
+}
+
+impl Easing {
+    /// Eases the animation's progress `t` (from 0.0 to 1.0).
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseOutQuad => 1.0 - (1.0 - t).powi(2),
+            Self::EaseOutCubic => ease_out_cubic(t),
+            Self::EaseOutExpo => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2.0_f64.powf(-10.0 * t)
+                }
+            }
+            Self::EaseInOutCubic => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
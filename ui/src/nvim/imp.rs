@@ -2,13 +2,22 @@ use std::cell::RefCell;
 
 use futures::lock::Mutex;
 use gtk::{gio, glib, subclass::prelude::*};
-use nvim::rpc::caller::Sender;
+use nvim::rpc::{caller::PendingCall, Metrics};
+
+use crate::slow_link::LinkState;
 
 #[derive(Default)]
 pub struct Neovim {
+    /// The nvim subprocess, if not replaying.
+    pub process: RefCell<Option<gio::Subprocess>>,
+    /// The connection to a remote nvim, if attached over TCP.
+    pub connection: RefCell<Option<gio::SocketConnection>>,
     pub writer: Mutex<Option<gio::OutputStreamAsyncWrite<gio::PollableOutputStream>>>,
     pub msgid_counter: RefCell<u32>,
-    pub callbacks: RefCell<Vec<(u32, Sender)>>,
+    pub callbacks: RefCell<Vec<PendingCall>>,
+    pub metrics: RefCell<Metrics>,
+    /// Simulated slow connection for the writes.
+    pub slow_link: RefCell<Option<LinkState>>,
 }
 
 #[glib::object_subclass]
@@ -1,15 +1,47 @@
+use std::cell::Ref;
 use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::atomic::Ordering;
 
 use futures::channel::oneshot;
 use gtk::{gio, glib, prelude::*, subclass::prelude::*};
 use nvim::{
     async_trait,
-    rpc::{caller::Response, Caller, HandleError, RpcWriter, WriteError},
+    rpc::{
+        caller::{PendingCall, Response},
+        transport::Halves,
+        Caller, HandleError, Message, Metrics, ReadError, RpcReader, RpcWriter, Transport,
+        WriteError,
+    },
     serde,
 };
 
+use crate::{
+    debug,
+    slow_link::{self, LinkState, SlowLink},
+    spawn_local,
+};
+
 mod imp;
 
+/// Seconds to wait for a connection to a remote nvim.
+const CONNECT_TIMEOUT_SECS: u32 = 10;
+
+/// Reads the next message of a replayed stream (see `Neovim::open_replay`)
+/// to process. The recorded requests and responses are skipped: none of our
+/// calls wait for the responses, and nobody reads our answers to the
+/// requests.
+pub async fn recv_replayed<R: futures::AsyncRead + Unpin>(
+    reader: &mut RpcReader<R>,
+) -> Result<Message, ReadError> {
+    loop {
+        match reader.recv().await? {
+            msg @ Message::Notification(_) => return Ok(msg),
+            msg => debug!("skipping replayed message: {:?}", msg),
+        }
+    }
+}
+
 glib::wrapper! {
     /// Wraps the nvim rpc client into a gobject.
     pub struct Neovim(ObjectSubclass<imp::Neovim>);
@@ -40,7 +72,18 @@ impl Neovim {
             flags.insert(gio::SubprocessFlags::INHERIT_FDS);
         }
 
-        let p = gio::Subprocess::newv(args, flags).expect("failed to open nvim subprocess");
+        let launcher = gio::SubprocessLauncher::new(flags);
+        // Our own renderer choice would leak to nvim, and its :terminal.
+        if crate::OWN_GSK_RENDERER.load(Ordering::Relaxed) {
+            launcher.unsetenv("GSK_RENDERER");
+        }
+        if let Some(backend) = crate::USER_GDK_BACKEND.get() {
+            launcher.setenv("GDK_BACKEND", backend, true);
+        }
+
+        let p = launcher
+            .spawn(args)
+            .expect("failed to open nvim subprocess");
 
         let writer = p
             .stdin_pipe()
@@ -58,6 +101,51 @@ impl Neovim {
             .into_async_read()
             .expect("covert to async read");
 
+        self.imp().process.replace(Some(p));
+
+        self.set_transport(Halves(reader, writer))
+    }
+
+    /// Connect to a nvim listening on a TCP address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address, `HOST:PORT`.
+    pub async fn connect(
+        &self,
+        addr: &str,
+    ) -> Result<gio::InputStreamAsyncRead<gio::PollableInputStream>, glib::Error> {
+        let client = gio::SocketClient::new();
+        client.set_timeout(CONNECT_TIMEOUT_SECS);
+        let connection = client.connect_to_host_future(addr, 0).await?;
+
+        let writer = connection
+            .output_stream()
+            .dynamic_cast::<gio::PollableOutputStream>()
+            .expect("cast to PollableOutputStream")
+            .into_async_write()
+            .expect("convert to async write");
+
+        let reader = connection
+            .input_stream()
+            .dynamic_cast::<gio::PollableInputStream>()
+            .expect("cast to PollableInputStream")
+            .into_async_read()
+            .expect("covert to async read");
+
+        self.imp().connection.replace(Some(connection));
+
+        Ok(self.set_transport(Halves(reader, writer)))
+    }
+
+    /// Takes the writer of `transport` for our calls, and returns its reader
+    /// for the io loop. The rest of the rpc client is our own (see the
+    /// `Caller` impl), so we don't use `nvim::rpc::transport::connect`.
+    fn set_transport<T>(&self, transport: T) -> T::Reader
+    where
+        T: Transport<Writer = gio::OutputStreamAsyncWrite<gio::PollableOutputStream>>,
+    {
+        let (reader, writer) = transport.split();
         self.imp()
             .writer
             .try_lock()
@@ -67,18 +155,124 @@ impl Neovim {
         reader
     }
 
+    /// Discards anything written to nvim from now on, for after nvim has
+    /// exited. The pending calls fail as canceled, and the calls made after
+    /// this never get a response.
+    pub fn close(&self) {
+        let writer = gio::MemoryOutputStream::new_resizable()
+            .dynamic_cast::<gio::PollableOutputStream>()
+            .expect("cast to PollableOutputStream")
+            .into_async_write()
+            .expect("convert to async write");
+
+        // A write might still be holding the writer.
+        spawn_local!(glib::clone!(@weak self as obj => async move {
+            obj.imp().writer.lock().await.replace(writer);
+        }));
+
+        // Dropping the senders cancels the calls.
+        self.imp().callbacks.take();
+    }
+
+    /// Waits for the nvim subprocess to exit, and returns its exit status.
+    /// If nvim didn't exit normally (e.g. was killed), returns 1. The exit
+    /// status of a remote nvim isn't known, so it's always 0.
+    pub async fn exit_status(&self) -> i32 {
+        self.imp().connection.take();
+
+        let p = match self.imp().process.take() {
+            Some(p) => p,
+            None => return 0,
+        };
+
+        match p.wait_future().await {
+            Ok(()) if p.has_exited() => p.exit_status(),
+            _ => 1,
+        }
+    }
+
+    /// Open a recorded nvim msgpack-rpc stream for replaying. Anything
+    /// written to nvim is discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the recorded stream.
+    pub fn open_replay(&self, path: &Path) -> std::io::Result<futures::io::Cursor<Vec<u8>>> {
+        let stream = std::fs::read(path)?;
+
+        let writer = gio::MemoryOutputStream::new_resizable()
+            .dynamic_cast::<gio::PollableOutputStream>()
+            .expect("cast to PollableOutputStream")
+            .into_async_write()
+            .expect("convert to async write");
+
+        Ok(self.set_transport(Halves(futures::io::Cursor::new(stream), writer)))
+    }
+
     pub fn handle_response(&self, response: Response) -> Result<(), HandleError> {
         let mut callbacks = self.imp().callbacks.borrow_mut();
         let caller = callbacks
             .iter()
-            .position(|(msgid, _)| *msgid == response.msgid)
+            .position(|pending| pending.msgid == response.msgid)
             .map(|index| callbacks.swap_remove(index));
 
         match caller {
-            Some((_, recv)) => recv.send(response).map_err(HandleError::CallerDropped),
+            Some(pending) => {
+                self.imp().metrics.borrow_mut().record(
+                    &pending.method,
+                    pending.started.elapsed(),
+                    response.error.is_some(),
+                );
+
+                pending
+                    .sender
+                    .send(response)
+                    .map_err(HandleError::CallerDropped)
+            }
             None => Err(HandleError::CallerMissing(response)),
         }
     }
+
+    /// Delays the writes to nvim as if sent over `link`.
+    pub fn set_slow_link(&self, link: SlowLink) {
+        self.imp().slow_link.replace(Some(LinkState::new(link)));
+    }
+
+    /// Delivery time of a write made now, if the connection is slowed down.
+    /// The size of the writes isn't known beforehand, so the bandwidth
+    /// limit doesn't apply to them.
+    fn write_delivery(&self) -> Option<i64> {
+        self.imp()
+            .slow_link
+            .borrow_mut()
+            .as_mut()
+            .map(|state| state.schedule(glib::monotonic_time(), 0))
+    }
+
+    /// Metrics of the calls made to nvim.
+    pub fn metrics(&self) -> Ref<Metrics> {
+        self.imp().metrics.borrow()
+    }
+
+    /// Write a response to a request made by nvim.
+    pub async fn write_response(
+        &self,
+        msgid: u32,
+        error: Option<rmpv::Value>,
+        result: Option<rmpv::Value>,
+    ) -> Result<(), WriteError> {
+        let at = self.write_delivery();
+        let mut writer = self.imp().writer.lock().await;
+        if let Some(at) = at {
+            slow_link::sleep_until(at).await;
+        }
+
+        writer
+            .as_mut()
+            .expect("nvim writer not set")
+            .write_rpc_response(msgid, error.as_ref(), result.as_ref())
+            .await
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -89,10 +283,13 @@ impl Caller for &Neovim {
         method: S,
         args: V,
     ) -> Result<(), WriteError> {
-        self.imp()
-            .writer
-            .lock()
-            .await
+        let at = self.write_delivery();
+        let mut writer = self.imp().writer.lock().await;
+        if let Some(at) = at {
+            slow_link::sleep_until(at).await;
+        }
+
+        writer
             .as_mut()
             .expect("nvim writer not set")
             .write_rpc_request(msgid, method.as_ref(), &args)
@@ -108,8 +305,11 @@ impl Caller for &Neovim {
         msgid
     }
 
-    fn store_handler(&mut self, msgid: u32, sender: oneshot::Sender<Response>) {
-        self.imp().callbacks.borrow_mut().push((msgid, sender));
+    fn store_handler(&mut self, msgid: u32, method: &str, sender: oneshot::Sender<Response>) {
+        self.imp()
+            .callbacks
+            .borrow_mut()
+            .push(PendingCall::new(msgid, method, sender));
     }
 }
 
@@ -118,3 +318,40 @@ impl Default for Neovim {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nvim::rpc::{Message, RpcReader, RpcWriter};
+
+    use super::recv_replayed;
+
+    #[test]
+    fn test_replay_skips_requests_and_responses() {
+        let mut stream = futures::io::Cursor::new(vec![]);
+        futures::executor::block_on(async {
+            (&mut stream)
+                .write_rpc_request(1, "nvim_ui_attach", &())
+                .await
+                .unwrap();
+            (&mut stream)
+                .write_rpc_response(1, None::<&rmpv::Value>, Some(&rmpv::Value::Nil))
+                .await
+                .unwrap();
+            (&mut stream)
+                .write_rpc_notification("redraw", &rmpv::Value::Array(vec![]))
+                .await
+                .unwrap();
+        });
+        stream.set_position(0);
+
+        let mut reader = RpcReader::new(stream);
+        futures::executor::block_on(async {
+            match recv_replayed(&mut reader).await.unwrap() {
+                Message::Notification(notification) => assert_eq!(notification.method, "redraw"),
+                msg => panic!("unexpected message: {:?}", msg),
+            }
+
+            assert!(recv_replayed(&mut reader).await.is_err());
+        });
+    }
+}
@@ -0,0 +1,81 @@
+//! `--remote-send` and `--remote-expr`, like gvim's. The requests are
+//! forwarded to the nvim of a running gnvim, through the server each gnvim
+//! starts in its nvim (see `server_path`).
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use glib::ExitCode;
+
+use crate::arguments::Arguments;
+
+/// Path of the nvim server of the gnvim named `name` (see `--servername`).
+pub fn server_path(name: &str) -> PathBuf {
+    glib::user_runtime_dir()
+        .join("gnvim")
+        .join(format!("{}.sock", name))
+}
+
+/// Nvim's flag and its value for the remote request of `args`, if any.
+fn remote_request(args: &Arguments) -> Option<(&'static str, &str)> {
+    match (&args.remote_send, &args.remote_expr) {
+        (Some(keys), _) => Some(("--remote-send", keys)),
+        (_, Some(expr)) => Some(("--remote-expr", expr)),
+        _ => None,
+    }
+}
+
+/// Sends the remote request of `args`, if any. The result of `--remote-expr`
+/// is printed to stdout.
+pub fn run(args: &Arguments) -> Option<ExitCode> {
+    let (flag, value) = remote_request(args)?;
+
+    let path = server_path(&args.servername);
+    if !path.exists() {
+        eprintln!("no gnvim running with the servername {}", args.servername);
+        return Some(ExitCode::FAILURE);
+    }
+
+    // Nvim itself does the rpc, and prints the result.
+    let status = Command::new(&args.nvim)
+        .arg("--server")
+        .arg(&path)
+        .arg(flag)
+        .arg(value)
+        .status();
+
+    Some(match status {
+        Ok(status) => ExitCode::from(status.code().unwrap_or(1)),
+        Err(err) => {
+            eprintln!("failed to run {:?}: {}", args.nvim, err);
+            ExitCode::FAILURE
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arguments::Arguments;
+
+    use super::remote_request;
+
+    #[test]
+    fn test_remote_request() {
+        assert_eq!(remote_request(&Arguments::default()), None);
+
+        let args = Arguments {
+            remote_send: Some(String::from("<Esc>:w<CR>")),
+            ..Default::default()
+        };
+        assert_eq!(
+            remote_request(&args),
+            Some(("--remote-send", "<Esc>:w<CR>"))
+        );
+
+        let args = Arguments {
+            remote_expr: Some(String::from("bufname()")),
+            ..Default::default()
+        };
+        assert_eq!(remote_request(&args), Some(("--remote-expr", "bufname()")));
+    }
+}
@@ -1,9 +1,156 @@
+use std::borrow::Cow;
+
 use gtk::{cairo, gdk, graphene, gsk, pango, prelude::*};
+use nvim::serde;
 
 use crate::colors::{Color, Colors};
 use crate::font::Font;
 use crate::SCALE;
 
+/// How emojis are presented.
+#[derive(Debug, Default, Clone, Copy, PartialEq, glib::Boxed, serde::Deserialize)]
+#[boxed_type(name = "EmojiPresentation")]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum EmojiPresentation {
+    /// Color in double width cells, text in single width ones. Follows nvim's
+    /// cell widths (i.e. 'emoji' and 'ambiwidth').
+    #[default]
+    Auto,
+    Color,
+    Text,
+}
+
+impl EmojiPresentation {
+    /// Applies the presentation to a cell's `text`, by adding a variation
+    /// selector after an emoji.
+    pub fn apply<'a>(&self, text: &'a str, double_width: bool) -> Cow<'a, str> {
+        let selector = match self {
+            Self::Auto if double_width => '\u{FE0F}',
+            Self::Auto | Self::Text => '\u{FE0E}',
+            Self::Color => '\u{FE0F}',
+        };
+
+        // Sequences (e.g. flags, or ones that already have a selector) are
+        // rendered as is.
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if is_emoji(c) => Cow::Owned(format!("{}{}", c, selector)),
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// How ambiguous width characters (e.g. `①`) are laid out. Sets nvim's
+/// 'ambiwidth', so nvim's cell widths match the glyphs.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(crate = "nvim::serde", rename_all = "snake_case")]
+pub enum Ambiwidth {
+    /// Two cells if the font's glyphs are wide (e.g. CJK fonts), one
+    /// otherwise.
+    Auto,
+    Single,
+    Double,
+}
+
+/// If `c` is presented as an emoji by default (i.e. it has the
+/// Emoji_Presentation property, as of Unicode 15.0).
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{231A}'..='\u{231B}'
+            | '\u{23E9}'..='\u{23EC}'
+            | '\u{23F0}'
+            | '\u{23F3}'
+            | '\u{25FD}'..='\u{25FE}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{2648}'..='\u{2653}'
+            | '\u{267F}'
+            | '\u{2693}'
+            | '\u{26A1}'
+            | '\u{26AA}'..='\u{26AB}'
+            | '\u{26BD}'..='\u{26BE}'
+            | '\u{26C4}'..='\u{26C5}'
+            | '\u{26CE}'
+            | '\u{26D4}'
+            | '\u{26EA}'
+            | '\u{26F2}'..='\u{26F3}'
+            | '\u{26F5}'
+            | '\u{26FA}'
+            | '\u{26FD}'
+            | '\u{2705}'
+            | '\u{270A}'..='\u{270B}'
+            | '\u{2728}'
+            | '\u{274C}'
+            | '\u{274E}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2795}'..='\u{2797}'
+            | '\u{27B0}'
+            | '\u{27BF}'
+            | '\u{2B1B}'..='\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+            | '\u{1F004}'
+            | '\u{1F0CF}'
+            | '\u{1F18E}'
+            | '\u{1F191}'..='\u{1F19A}'
+            | '\u{1F1E6}'..='\u{1F1FF}'
+            | '\u{1F201}'
+            | '\u{1F21A}'
+            | '\u{1F22F}'
+            | '\u{1F232}'..='\u{1F236}'
+            | '\u{1F238}'..='\u{1F23A}'
+            | '\u{1F250}'..='\u{1F251}'
+            | '\u{1F300}'..='\u{1F320}'
+            | '\u{1F32D}'..='\u{1F335}'
+            | '\u{1F337}'..='\u{1F37C}'
+            | '\u{1F37E}'..='\u{1F393}'
+            | '\u{1F3A0}'..='\u{1F3CA}'
+            | '\u{1F3CF}'..='\u{1F3D3}'
+            | '\u{1F3E0}'..='\u{1F3F0}'
+            | '\u{1F3F4}'
+            | '\u{1F3F8}'..='\u{1F43E}'
+            | '\u{1F440}'
+            | '\u{1F442}'..='\u{1F4FC}'
+            | '\u{1F4FF}'..='\u{1F53D}'
+            | '\u{1F54B}'..='\u{1F54E}'
+            | '\u{1F550}'..='\u{1F567}'
+            | '\u{1F57A}'
+            | '\u{1F595}'..='\u{1F596}'
+            | '\u{1F5A4}'
+            | '\u{1F5FB}'..='\u{1F64F}'
+            | '\u{1F680}'..='\u{1F6C5}'
+            | '\u{1F6CC}'
+            | '\u{1F6D0}'..='\u{1F6D2}'
+            | '\u{1F6D5}'..='\u{1F6D7}'
+            | '\u{1F6DC}'..='\u{1F6DF}'
+            | '\u{1F6EB}'..='\u{1F6EC}'
+            | '\u{1F6F4}'..='\u{1F6FC}'
+            | '\u{1F7E0}'..='\u{1F7EB}'
+            | '\u{1F7F0}'
+            | '\u{1F90C}'..='\u{1F93A}'
+            | '\u{1F93C}'..='\u{1F945}'
+            | '\u{1F947}'..='\u{1F9FF}'
+            | '\u{1FA70}'..='\u{1FA7C}'
+            | '\u{1FA80}'..='\u{1FA88}'
+            | '\u{1FA90}'..='\u{1FABD}'
+            | '\u{1FABF}'..='\u{1FAC5}'
+            | '\u{1FACE}'..='\u{1FADB}'
+            | '\u{1FAE0}'..='\u{1FAE8}'
+            | '\u{1FAF0}'..='\u{1FAF8}'
+    )
+}
+
+/// If the glyph of a cell's `text` is often wider than a single cell (e.g.
+/// emojis and enclosed alphanumerics, when nvim's 'emoji' is off or
+/// 'ambiwidth' is single).
+pub fn is_wide_symbol(text: &str) -> bool {
+    text.chars()
+        .next()
+        .map(|c| is_emoji(c) || matches!(c, '\u{2460}'..='\u{24FF}' | '\u{3248}'..='\u{324F}'))
+        .unwrap_or(false)
+}
+
 /// Creates text render nodes for `text`.
 pub fn render_text(
     ctx: &pango::Context,
@@ -44,6 +191,70 @@ pub fn render_text(
     gsk::ContainerNode::new(&nodes).upcast()
 }
 
+/// Synthesizes bold and italic for text `node` of `hl_id`, if the font lacks
+/// the real faces. Bold is emulated by drawing the text twice, slightly
+/// offset, and italic by slanting the text around the baseline.
+pub fn synthesize_style(
+    node: gsk::RenderNode,
+    hl_id: &i64,
+    colors: &Colors,
+    font: &Font,
+    baseline: f32,
+) -> gsk::RenderNode {
+    let hl = colors.get_hl(hl_id);
+    let hl = match hl.hl_attr() {
+        Some(hl) => hl,
+        None => return node,
+    };
+
+    let node = if hl.bold.unwrap_or(false) && !font.has_bold() {
+        let offset = gsk::TransformNode::new(
+            &node,
+            &gsk::Transform::new().translate(&graphene::Point::new(1.0, 0.0)),
+        );
+        gsk::ContainerNode::new(&[node, offset.upcast()]).upcast()
+    } else {
+        node
+    };
+
+    if hl.italic.unwrap_or(false) && !font.has_italic() {
+        let transform = gsk::Transform::new()
+            .translate(&graphene::Point::new(0.0, baseline))
+            .skew(-12.0, 0.0)
+            .translate(&graphene::Point::new(0.0, -baseline));
+        gsk::TransformNode::new(&node, &transform).upcast()
+    } else {
+        node
+    }
+}
+
+/// Fits `node` (e.g. a double width color emoji) into the cells starting at
+/// `x` and spanning `width`. The node is scaled down (around its center) if
+/// it doesn't fit and clipped to the cells.
+pub fn fit_to_cells(node: gsk::RenderNode, font: &Font, x: f32, width: f32) -> gsk::RenderNode {
+    let height = font.height() / SCALE;
+    let bounds = node.bounds();
+
+    let scale = (width / bounds.width())
+        .min(height / bounds.height())
+        .min(1.0);
+    let node = if scale < 1.0 {
+        let transform = gsk::Transform::new()
+            .translate(&graphene::Point::new(x + width / 2.0, height / 2.0))
+            .scale(scale, scale)
+            .translate(&graphene::Point::new(
+                -(bounds.x() + bounds.width() / 2.0),
+                -(bounds.y() + bounds.height() / 2.0),
+            ));
+
+        gsk::TransformNode::new(&node, &transform).upcast()
+    } else {
+        node
+    };
+
+    gsk::ClipNode::new(&node, &graphene::Rect::new(x, 0.0, width, height)).upcast()
+}
+
 pub fn render_underline(
     font: &Font,
     color: &Color,
@@ -68,10 +279,7 @@ pub fn render_underlineline(
     baseline: f32,
     width: f32,
 ) -> [gsk::RenderNode; 2] {
-    render_underline(font, color, x, baseline, width);
-
     let baseline2 = baseline + font.underline_thickness() / SCALE * 2.0;
-    render_underline(font, color, x, baseline2, width);
 
     [
         render_underline(font, color, x, baseline, width),
@@ -170,22 +378,25 @@ pub fn render_undercurl(
     baseline: f32,
     width: f32,
     cell_count: i64,
+    amplitude: f32,
 ) -> gsk::RenderNode {
     let y = baseline - font.underline_position() / SCALE;
     let h = font.descent() / SCALE;
+    let y_mid = y + h * 0.5;
 
-    let bounds = graphene::Rect::new(x, y, width, h);
+    // Grow the bounds with large amplitudes, so the waves don't get clipped.
+    let half = h * 0.5 * amplitude.max(1.0);
+    let bounds = graphene::Rect::new(x, y_mid - half, width, half * 2.0);
     let node = gsk::CairoNode::new(&bounds);
     let ctx = node.draw_context();
 
     let x = x as f64;
-    let y = y as f64;
     let w = (font.char_width() / SCALE) as f64;
-    let h = h as f64;
+    let h = (h * amplitude) as f64;
+    let y_mid = y_mid as f64;
 
-    let y_top = y - h * 0.5;
-    let y_bot = y + h * 1.5;
-    let y_mid = y + h * 0.5;
+    let y_top = y_mid - h;
+    let y_bot = y_mid + h;
 
     ctx.set_line_width((font.underline_thickness() / SCALE) as f64);
     ctx.move_to(x, y_mid);
@@ -222,20 +433,101 @@ pub fn render_undercurl(
     node.upcast()
 }
 
-pub fn create_hl_attrs(hl_id: &i64, colors: &Colors, font: &Font) -> pango::AttrList {
+pub fn create_hl_attrs(
+    hl_id: &i64,
+    colors: &Colors,
+    font: &Font,
+    ligatures: bool,
+) -> pango::AttrList {
     let attrs = pango::AttrList::new();
+    let hl = colors.get_hl(hl_id);
+    let bold = hl.hl_attr().and_then(|hl| hl.bold).unwrap_or(false);
+    let italic = hl.hl_attr().and_then(|hl| hl.italic).unwrap_or(false);
 
-    attrs.insert(pango::AttrFontDesc::new(&font.font_desc()));
+    attrs.insert(pango::AttrFontDesc::new(
+        &font.style_font_desc(bold, italic),
+    ));
 
-    if let Some(hl) = colors.get_hl(hl_id).hl_attr() {
-        if hl.bold.unwrap_or(false) {
-            attrs.insert(pango::AttrInt::new_weight(pango::Weight::Bold));
-        }
+    let features = font.font_features();
+    if !features.is_empty() {
+        attrs.insert(pango::AttrFontFeatures::new(&features));
+    }
 
-        if hl.italic.unwrap_or(false) {
-            attrs.insert(pango::AttrInt::new_style(pango::Style::Italic));
-        }
+    if !ligatures {
+        attrs.insert(pango::AttrFontFeatures::new(
+            "liga 0, clig 0, calt 0, dlig 0",
+        ));
+    }
+
+    // Without the real faces, pango (or fontconfig) would synthesize the
+    // style on top of our synthesis (see `synthesize_style`).
+    if bold && font.has_bold() {
+        attrs.insert(pango::AttrInt::new_weight(pango::Weight::Bold));
+    }
+
+    if italic && font.has_italic() {
+        attrs.insert(pango::AttrInt::new_style(pango::Style::Italic));
     }
 
     attrs
 }
+
+/// Renders a vertical guide line (e.g. for 'colorcolumn') at the left edge
+/// of the column `col`.
+pub fn render_guide(font: &Font, color: &Color, col: usize, height: f32) -> gsk::RenderNode {
+    let x = font.col_to_x(col as f64).round() as f32;
+    gsk::ColorNode::new(color, &graphene::Rect::new(x, 0.0, 1.0, height)).upcast()
+}
+
+/// Renders a "soft" edge for a diff band (i.e. continuous rows that have the
+/// same diff highlight). The edges of the band fade into the background.
+pub fn render_diff_band(font: &Font, bg: &Color, rect: graphene::Rect) -> gsk::RenderNode {
+    let radius = font.char_width() / SCALE / 2.0;
+    let outline = gsk::RoundedRect::from_rect(rect, radius);
+
+    gsk::InsetShadowNode::new(&outline, bg, 0.0, 0.0, 0.0, radius).upcast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_wide_symbol, EmojiPresentation};
+
+    #[test]
+    fn test_emoji_presentation_apply() {
+        let cases = [
+            (EmojiPresentation::Auto, "😀", true, "😀\u{FE0F}"),
+            (EmojiPresentation::Auto, "😀", false, "😀\u{FE0E}"),
+            (EmojiPresentation::Text, "😀", true, "😀\u{FE0E}"),
+            (EmojiPresentation::Color, "⌚", false, "⌚\u{FE0F}"),
+            // Text by default.
+            (EmojiPresentation::Color, "→", false, "→"),
+            (EmojiPresentation::Color, "■", false, "■"),
+            (EmojiPresentation::Text, "a", false, "a"),
+            // Sequences are kept as is.
+            (EmojiPresentation::Text, "🇫🇮", true, "🇫🇮"),
+            (EmojiPresentation::Color, "😀\u{FE0E}", true, "😀\u{FE0E}"),
+        ];
+
+        for (presentation, text, double_width, expected) in cases {
+            assert_eq!(
+                presentation.apply(text, double_width),
+                expected,
+                "{:?} {:?}",
+                presentation,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_wide_symbol() {
+        assert!(is_wide_symbol("😀"));
+        assert!(is_wide_symbol("⬛"));
+        assert!(is_wide_symbol("①"));
+        assert!(!is_wide_symbol("a"));
+        assert!(!is_wide_symbol("→"));
+        assert!(!is_wide_symbol("⌨"));
+        assert!(!is_wide_symbol("■"));
+        assert!(!is_wide_symbol(""));
+    }
+}
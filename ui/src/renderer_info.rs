@@ -0,0 +1,230 @@
+//! Detection of the gsk renderer and the graphics driver, for bug reports and
+//! for working around the known issues with them.
+
+use std::ffi::CStr;
+use std::fmt;
+
+use gtk::prelude::*;
+
+/// The gsk renderer in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    Ngl,
+    Gl,
+    Vulkan,
+    Cairo,
+    Other,
+}
+
+impl Renderer {
+    fn from_type_name(name: &str) -> Self {
+        match name {
+            "GskNglRenderer" => Renderer::Ngl,
+            "GskGLRenderer" => Renderer::Gl,
+            "GskVulkanRenderer" => Renderer::Vulkan,
+            "GskCairoRenderer" => Renderer::Cairo,
+            _ => Renderer::Other,
+        }
+    }
+
+    fn is_gl(&self) -> bool {
+        matches!(self, Renderer::Ngl | Renderer::Gl)
+    }
+}
+
+/// The OpenGL context provided by the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlInfo {
+    pub major: i32,
+    pub minor: i32,
+    /// OpenGL ES instead of the desktop OpenGL.
+    pub es: bool,
+    /// Legacy (i.e. pre 3.2 compatibility) context.
+    pub legacy: bool,
+}
+
+impl fmt::Display for GlInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}.{}{}",
+            if self.es { "OpenGL ES" } else { "OpenGL" },
+            self.major,
+            self.minor,
+            if self.legacy { " legacy" } else { "" }
+        )
+    }
+}
+
+/// Toggles for working around the known renderer issues.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Workarounds {
+    /// Render without caching and animations (see `--safe-rendering`).
+    pub safe_rendering: bool,
+}
+
+impl fmt::Display for Workarounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.safe_rendering {
+            write!(f, "safe rendering")
+        } else {
+            write!(f, "none")
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RendererInfo {
+    pub renderer: Renderer,
+    /// Type name of the renderer, e.g. `GskNglRenderer`.
+    pub renderer_name: String,
+    /// Type name of the display, e.g. `GdkWaylandDisplay`.
+    pub display_name: String,
+    /// Only for the GL renderers.
+    pub gl: Option<GlInfo>,
+    /// The GL vendor and renderer strings, e.g. `Intel / Mesa Intel(R) UHD
+    /// Graphics 620 (KBL GT2)`. Only for the GL renderers.
+    pub driver: Option<String>,
+}
+
+impl RendererInfo {
+    /// Detects the renderer of `native`, which needs to be realized.
+    pub fn detect(native: &impl IsA<gtk::Native>) -> Self {
+        let renderer_name = native.renderer().type_().name().to_string();
+        let renderer = Renderer::from_type_name(&renderer_name);
+
+        // The renderer's own context isn't exposed, but a context of the
+        // same surface is created by the same driver.
+        let ctx = renderer
+            .is_gl()
+            .then(|| native.surface().create_gl_context().ok())
+            .flatten()
+            .filter(|ctx| ctx.realize().is_ok());
+
+        let gl = ctx.as_ref().map(|ctx| {
+            let (major, minor) = ctx.version();
+            GlInfo {
+                major,
+                minor,
+                es: ctx.uses_es(),
+                legacy: ctx.is_legacy(),
+            }
+        });
+
+        let driver = ctx.as_ref().and_then(|ctx| {
+            ctx.make_current();
+            let driver = gl_string(GL_VENDOR)
+                .zip(gl_string(GL_RENDERER))
+                .map(|(vendor, renderer)| format!("{} / {}", vendor, renderer));
+            gtk::gdk::GLContext::clear_current();
+            driver
+        });
+
+        Self {
+            renderer,
+            renderer_name,
+            display_name: native.display().type_().name().to_string(),
+            gl,
+            driver,
+        }
+    }
+
+    /// The workarounds needed for the renderer.
+    pub fn workarounds(&self) -> Workarounds {
+        let gl = match self.gl {
+            Some(gl) if self.renderer.is_gl() => gl,
+            _ => return Workarounds::default(),
+        };
+
+        Workarounds {
+            // The GL renderers' glyph caches misbehave on the old contexts,
+            // leaving stale or garbled text (hoodr/gnvim#synth-811).
+            safe_rendering: gl.legacy || (gl.es && gl.major < 3),
+        }
+    }
+}
+
+const GL_VENDOR: u32 = 0x1F00;
+const GL_RENDERER: u32 = 0x1F01;
+
+/// Queries `glGetString(name)` of the current context. Gtk doesn't expose
+/// the driver strings, so this goes through libepoxy (which gtk uses for GL)
+/// instead of linking GL ourselves.
+fn gl_string(name: u32) -> Option<String> {
+    type GetString = unsafe extern "C" fn(u32) -> *const libc::c_char;
+
+    // SAFETY: `epoxy_glGetString` is libepoxy's function pointer for
+    // glGetString, which resolves the real function on its first call. The
+    // returned string is owned by the driver.
+    unsafe {
+        let sym = libc::dlsym(libc::RTLD_DEFAULT, b"epoxy_glGetString\0".as_ptr().cast());
+        if sym.is_null() {
+            return None;
+        }
+
+        let get_string = *(sym as *const Option<GetString>);
+        let ptr = get_string?(name);
+        if ptr.is_null() {
+            return None;
+        }
+
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+impl fmt::Display for RendererInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.renderer_name)?;
+        if let Some(gl) = self.gl {
+            write!(f, " ({})", gl)?;
+        }
+        if let Some(ref driver) = self.driver {
+            write!(f, " [{}]", driver)?;
+        }
+        write!(f, " on {}", self.display_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GlInfo, Renderer, RendererInfo, Workarounds};
+
+    fn info(renderer: Renderer, gl: Option<GlInfo>) -> RendererInfo {
+        RendererInfo {
+            renderer,
+            renderer_name: String::new(),
+            display_name: String::new(),
+            gl,
+            driver: None,
+        }
+    }
+
+    #[test]
+    fn test_workarounds() {
+        let gl = |major, minor, es, legacy| GlInfo {
+            major,
+            minor,
+            es,
+            legacy,
+        };
+
+        assert_eq!(
+            info(Renderer::Ngl, Some(gl(4, 6, false, false))).workarounds(),
+            Workarounds::default()
+        );
+        assert!(
+            info(Renderer::Ngl, Some(gl(2, 0, true, false)))
+                .workarounds()
+                .safe_rendering
+        );
+        assert!(
+            info(Renderer::Gl, Some(gl(3, 0, false, true)))
+                .workarounds()
+                .safe_rendering
+        );
+        assert_eq!(
+            info(Renderer::Cairo, None).workarounds(),
+            Workarounds::default()
+        );
+    }
+}
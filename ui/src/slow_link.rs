@@ -0,0 +1,235 @@
+//! Simulated slow connection to nvim, for exercising the ui's behavior with
+//! remote nvims (and the backpressure handling) locally.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::AsyncRead;
+use gtk::glib;
+
+/// Size of the chunks read from the underlying reader.
+const CHUNK_SIZE: usize = 4096;
+
+/// Properties of the simulated connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SlowLink {
+    /// One way latency, in microseconds.
+    pub latency: i64,
+    /// Maximum random latency added on top of `latency`, in microseconds.
+    pub jitter: i64,
+    /// Bytes per second, zero for unlimited.
+    pub bandwidth: i64,
+}
+
+impl SlowLink {
+    /// Parses `LATENCY[:JITTER[:BANDWIDTH]]`, with the latencies in
+    /// milliseconds and the bandwidth in kilobytes per second.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(':').map(|part| {
+            part.trim()
+                .parse::<u32>()
+                .map(i64::from)
+                .map_err(|err| format!("invalid value {:?}: {}", part, err))
+        });
+
+        let latency = parts.next().ok_or("latency missing")??;
+        let jitter = parts.next().transpose()?.unwrap_or(0);
+        let bandwidth = parts.next().transpose()?.unwrap_or(0);
+        if parts.next().is_some() {
+            return Err(String::from("expected LATENCY[:JITTER[:BANDWIDTH]]"));
+        }
+
+        Ok(Self {
+            latency: latency * 1000,
+            jitter: jitter * 1000,
+            bandwidth: bandwidth * 1000,
+        })
+    }
+}
+
+/// Delivery times of the data sent over a `SlowLink`, in one direction.
+#[derive(Debug, Default)]
+pub struct LinkState {
+    link: SlowLink,
+    /// Time when the previously sent data has left the sender (i.e. the
+    /// bandwidth is in use until then).
+    busy_until: i64,
+    /// Delivery time of the previously sent data. Like with TCP, data is
+    /// never delivered out of order.
+    last_delivery: i64,
+}
+
+impl LinkState {
+    pub fn new(link: SlowLink) -> Self {
+        Self {
+            link,
+            ..Default::default()
+        }
+    }
+
+    /// Delivery time of `len` bytes sent at `now`.
+    pub fn schedule(&mut self, now: i64, len: usize) -> i64 {
+        let jitter = if self.link.jitter > 0 {
+            glib::random_int_range(0, self.link.jitter.min(i32::MAX as i64) as i32) as i64
+        } else {
+            0
+        };
+
+        self.schedule_with_jitter(now, len, jitter)
+    }
+
+    fn schedule_with_jitter(&mut self, now: i64, len: usize, jitter: i64) -> i64 {
+        let sent = now.max(self.busy_until);
+        self.busy_until = if self.link.bandwidth > 0 {
+            sent + len as i64 * 1_000_000 / self.link.bandwidth
+        } else {
+            sent
+        };
+
+        self.last_delivery = self
+            .last_delivery
+            .max(self.busy_until + self.link.latency + jitter);
+        self.last_delivery
+    }
+}
+
+/// Waits until the monotonic time `at`.
+pub async fn sleep_until(at: i64) {
+    let now = glib::monotonic_time();
+    if at > now {
+        glib::timeout_future(Duration::from_micros((at - now) as u64)).await;
+    }
+}
+
+/// Reader that delays the data from the underlying reader according to a
+/// `SlowLink`.
+pub struct SlowReader<R> {
+    inner: R,
+    state: LinkState,
+    /// Data read from `inner` with its delivery time, oldest first.
+    queue: VecDeque<(i64, Vec<u8>)>,
+    /// Timer for the delivery of the oldest data.
+    timer: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    /// The underlying reader has reached its end, or failed.
+    done: Option<io::Result<()>>,
+}
+
+impl<R> SlowReader<R> {
+    pub fn new(inner: R, link: SlowLink) -> Self {
+        Self {
+            inner,
+            state: LinkState::new(link),
+            queue: VecDeque::new(),
+            timer: None,
+            done: None,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for SlowReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        // Take in everything that's available, so the data is "in flight"
+        // while we're waiting for the earlier data to be delivered.
+        while this.done.is_none() {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            match Pin::new(&mut this.inner).poll_read(cx, &mut chunk) {
+                Poll::Ready(Ok(0)) => this.done = Some(Ok(())),
+                Poll::Ready(Ok(n)) => {
+                    chunk.truncate(n);
+                    let at = this.state.schedule(glib::monotonic_time(), n);
+                    this.queue.push_back((at, chunk));
+                }
+                Poll::Ready(Err(err)) => this.done = Some(Err(err)),
+                Poll::Pending => break,
+            }
+        }
+
+        let at = match this.queue.front() {
+            Some((at, _)) => *at,
+            None => {
+                return match this.done.take() {
+                    Some(res) => {
+                        // Keep returning EOF.
+                        this.done = Some(Ok(()));
+                        Poll::Ready(res.map(|_| 0))
+                    }
+                    None => Poll::Pending,
+                };
+            }
+        };
+
+        let now = glib::monotonic_time();
+        if at > now {
+            let timer = this.timer.get_or_insert_with(|| {
+                glib::timeout_future(Duration::from_micros((at - now) as u64))
+            });
+            if timer.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+        this.timer = None;
+
+        let (_, chunk) = this.queue.front_mut().expect("queue empty");
+        let n = buf.len().min(chunk.len());
+        buf[..n].copy_from_slice(&chunk[..n]);
+        chunk.drain(..n);
+        if chunk.is_empty() {
+            this.queue.pop_front();
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LinkState, SlowLink};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            SlowLink::parse("80:20:256"),
+            Ok(SlowLink {
+                latency: 80_000,
+                jitter: 20_000,
+                bandwidth: 256_000,
+            })
+        );
+        assert_eq!(
+            SlowLink::parse("80"),
+            Ok(SlowLink {
+                latency: 80_000,
+                ..Default::default()
+            })
+        );
+        assert!(SlowLink::parse("").is_err());
+        assert!(SlowLink::parse("1:2:3:4").is_err());
+    }
+
+    #[test]
+    fn test_schedule_in_order() {
+        let mut state = LinkState::new(SlowLink {
+            latency: 1000,
+            jitter: 0,
+            bandwidth: 1_000_000,
+        });
+
+        // 500 bytes take 500us to send.
+        assert_eq!(state.schedule_with_jitter(0, 500, 0), 1500);
+        // Sent after the previous data.
+        assert_eq!(state.schedule_with_jitter(0, 500, 0), 2000);
+        // Less jitter doesn't overtake the previous data.
+        assert_eq!(state.schedule_with_jitter(10_000, 0, 900), 11_900);
+        assert_eq!(state.schedule_with_jitter(10_000, 0, 0), 11_900);
+    }
+}
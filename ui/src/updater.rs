@@ -0,0 +1,107 @@
+//! Opt-in check for new gnvim releases. The releases are fetched by the
+//! runtime (see `check_for_updates` in the lua runtime), this decides when to
+//! check and whether the latest release is newer than us.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use gtk::glib;
+
+use crate::warn;
+
+/// Time between the checks.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// If gnvim is installed by a package manager, which should handle the
+/// updates instead.
+pub fn is_packaged() -> bool {
+    if std::env::var_os("FLATPAK_ID").is_some() || std::env::var_os("SNAP").is_some() {
+        return true;
+    }
+
+    std::env::current_exe()
+        .map(|exe| {
+            (exe.starts_with("/usr") && !exe.starts_with("/usr/local"))
+                || exe.starts_with("/nix/store")
+        })
+        .unwrap_or(false)
+}
+
+/// File for remembering the time of the latest check.
+fn last_check_path() -> PathBuf {
+    glib::user_cache_dir().join("gnvim").join("update-check")
+}
+
+/// Time until the next check is due.
+pub fn next_check_in() -> Duration {
+    let last = std::fs::read_to_string(last_check_path())
+        .ok()
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+    match last.and_then(|last| last.elapsed().ok()) {
+        Some(elapsed) => CHECK_INTERVAL.saturating_sub(elapsed),
+        None => Duration::ZERO,
+    }
+}
+
+/// Remembers that the check was done now.
+pub fn mark_checked() {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let path = last_check_path();
+    let res = path
+        .parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::write(&path, now.as_secs().to_string()));
+    if let Err(err) = res {
+        warn!("failed to write {:?}: {}", path, err);
+    }
+}
+
+/// Parses `v1.2.3` or `1.2.3-dev`. The bool is true for pre-releases.
+fn parse_version(version: &str) -> Option<([u64; 3], bool)> {
+    let version = version.trim().trim_start_matches('v');
+    let (version, pre) = match version.split_once('-') {
+        Some((version, _)) => (version, true),
+        None => (version, false),
+    };
+
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let mut numbers = [0; 3];
+    for number in numbers.iter_mut() {
+        *number = parts.next().unwrap_or(Some(0))?;
+    }
+
+    Some((numbers, pre))
+}
+
+/// If the release `latest` (e.g. `v0.4.0`) is newer than `current`.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        // Pre-release is older than the release itself.
+        (Some((latest, latest_pre)), Some((current, current_pre))) => {
+            (latest, !latest_pre) > (current, !current_pre)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_newer;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.4.0", "0.3.2"));
+        assert!(is_newer("v0.3.2", "0.3.2-dev"));
+        assert!(is_newer("v1.0", "0.3.2"));
+        assert!(!is_newer("v0.3.2", "0.3.2"));
+        assert!(!is_newer("v0.3.1", "0.3.2-dev"));
+        assert!(!is_newer("v0.3.3-rc1", "0.3.3"));
+        assert!(!is_newer("nightly", "0.3.2"));
+    }
+}